@@ -7,16 +7,36 @@
 //!
 //! calc -i vertices.csv -o edges.csv   # required
 //! calc < vertices.csv > edges.csv     # optional
+//! calc --clusters 5 < vertices.csv > labels.csv
+//! calc --linkage < vertices.csv > linkage.csv
+//! calc --tour walk --two-opt --or-opt < vertices.csv > tour.csv
+//! calc --tour christofides < vertices.csv > tour.csv
+//! calc --steiner steiner.csv < vertices.csv > edges.csv
+//! calc --rectilinear < vertices.csv > wires.csv
+//! calc --max-degree 3 < vertices.csv > edges.csv
+//! calc --required keep.csv --forbidden avoid.csv < vertices.csv > edges.csv
+//! calc --obstacles walls.csv --detour < vertices.csv > paths.csv
+//! calc --k 10 --subset chosen.csv < vertices.csv > edges.csv
+//! calc --sensitivity < vertices.csv > report.csv
+//! calc --indexed < vertices.csv > edges.csv
+//! calc --delimiter comma -c 5 < sensors.csv > labels.csv
+//! calc -i points.geojson -o tree.geojson
+//! calc -f dot < vertices.csv > tree.dot
+//! calc -i points.wkt -o tree.wkt
 
 use clap::{App, Arg};
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::path::Path;
 use std::rc::Rc;
 
-use mst::{self, minimum_spanning_tree, Edge, Vertex};
+use mst::{
+    self, cluster, csv, format, format::Format, kmst, obstacles, rectilinear,
+    sensitivity, steiner, tsp, Edge, Link, Vertex,
+};
 
 /// Graph creation options
 #[derive(Clone)]
@@ -29,13 +49,56 @@ struct Options {
     input: Rc<String>,
     /// The output file name
     output: Rc<String>,
+    /// Write cluster labels for this many clusters
+    clusters: Option<usize>,
+    /// Write cluster labels, cutting edges longer than this
+    cut_length: Option<f32>,
+    /// Write the single-linkage matrix
+    linkage: bool,
+    /// Write a travelling salesman tour found with this method
+    tour: Rc<String>,
+    /// Improve the tour with 2-opt
+    two_opt: bool,
+    /// Improve the tour with Or-opt
+    or_opt: bool,
+    /// Write a Steiner tree, and its Steiner points to this file
+    steiner: Rc<String>,
+    /// Write a rectilinear Steiner tree as horizontal and vertical segments
+    rectilinear: bool,
+    /// Most edges at any vertex
+    max_degree: Option<usize>,
+    /// Per vertex degree limits file name
+    degree_limits: Rc<String>,
+    /// Edges the tree must include, file name
+    required: Rc<String>,
+    /// Edges the tree must not include, file name
+    forbidden: Rc<String>,
+    /// Polygonal obstacles file name
+    obstacles: Rc<String>,
+    /// Route blocked edges around the obstacles instead of forbidding them
+    detour: bool,
+    /// Span only this many of the points
+    k: Option<usize>,
+    /// Write the points chosen for the k-MST to this file
+    subset: Rc<String>,
+    /// Write each edge with its best replacement
+    sensitivity: bool,
+    /// Write edges as indices into the input points
+    indexed: bool,
+    /// Field delimiter of the files read, detected if not given
+    delimiter: Option<csv::Delimiter>,
+    /// Tree output format, from the output file name if not given
+    format: Option<Format>,
 }
 
+/// Tour methods
+const TOUR_METHODS: [&str; 2] = ["walk", "christofides"];
+
 const FSEP: &str = mst::FIELD_SEPARATOR;
 //const RSEP: &str = mst::RECORD_SEPARATOR;
 
 /// Read command line options.
-/// Read a set of points from a file or stdin as CSV.
+/// Read a set of points from a file or stdin, in any format.
 /// Calculate the Minimum Spanning Tree of those points as vertices of a
 /// completely connected undirected graph.
 /// Write the vertices to a file or stdout as CSV.
@@ -56,16 +119,17 @@ fn main() {
     }
 
     // Open input
-    let points = if opts.input.len() > 0 {
+    let (table, graph) = if !opts.input.is_empty() {
         // Use the given input file
         let path = Path::new(&*opts.input);
-        match File::open(&path) {
+        match File::open(path) {
             Ok(f) => {
                 if opts.verbose {
                     eprintln!("Opened input '{}'", path.display());
                 }
-                match ingest(io::BufReader::new(f)) {
-                    Ok(points) => points,
+                let format = Format::from_path(&opts.input);
+                match format::read_graph_file(&f, format, opts.delimiter) {
+                    Ok(input) => input,
                     Err(e) => {
                         println!("{}", e);
                         std::process::exit(3);
@@ -81,8 +145,8 @@ fn main() {
         if opts.verbose {
             eprintln!("Reading from stdin");
         }
-        match ingest(io::stdin().lock()) {
-            Ok(points) => points,
+        match format::read_graph(io::stdin().lock(), None, opts.delimiter) {
+            Ok(input) => input,
             Err(e) => {
                 println!("{}", e);
                 std::process::exit(3);
@@ -90,32 +154,372 @@ fn main() {
         }
     };
 
+    let points = table.points.clone();
+    let extra = table.extra.iter().any(|fields| !fields.is_empty());
+
+    if table.skipped > 0 {
+        eprintln!("WARNING: skipped {} records without a point", table.skipped);
+    }
     if opts.verbose {
         eprintln!("Read {} points", points.len());
+        if !graph.is_empty() {
+            eprintln!("Read {} graph edges", graph.len());
+        }
     }
 
-    // Construct the Minimum Spanning Tree from the set of points
-    let tree = match minimum_spanning_tree(&points) {
-        Ok(tree) => tree,
-        Err(e) => {
+    // A graph's tree is found among its edges, but these options find trees
+    // among all the pairs of points
+    if !graph.is_empty()
+        && (opts.k.is_some()
+            || opts.max_degree.is_some()
+            || !opts.degree_limits.is_empty()
+            || !opts.required.is_empty()
+            || !opts.forbidden.is_empty()
+            || !opts.obstacles.is_empty()
+            || !opts.steiner.is_empty()
+            || opts.rectilinear
+            || opts.sensitivity)
+    {
+        eprintln!(
+            "ERROR: -k, -m, -r, -s, --degree-limits, --required, --forbidden, \
+             --obstacles and --sensitivity take points, not a graph"
+        );
+        std::process::exit(1);
+    }
+
+    // Choose the k points with the shortest tree, and carry on with those,
+    // or construct the Minimum Spanning Tree of the graph or of all the
+    // points
+    let (points, tree, rows, ends) = if let Some(k) = opts.k {
+        let ktree = match kmst::k_mst(&points, k) {
+            Ok(ktree) => ktree,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        if opts.verbose {
+            eprintln!(
+                "Found tree of {} points, length {}",
+                ktree.points.len(),
+                ktree.len()
+            );
+        }
+        let chosen: Vec<Vertex> =
+            ktree.points.iter().map(|&i| points[i]).collect();
+        if !opts.subset.is_empty() {
+            let path = Path::new(&*opts.subset);
+            match File::create(path) {
+                Ok(mut f) => {
+                    if opts.verbose {
+                        eprintln!("Opened subset '{}'", path.display());
+                    }
+                    exhaust_table(&table, &ktree.points, None, &mut f);
+                }
+                Err(e) => {
+                    eprintln!("Failed to open '{}': {}", path.display(), e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        // Indices into all the points as indices into the chosen ones
+        let chosen_of = |i| ktree.points.binary_search(&i).unwrap();
+        let ends = ktree
+            .ends
+            .iter()
+            .map(|&(u, v)| (chosen_of(u), chosen_of(v)))
+            .collect();
+        (chosen, ktree.edges, ktree.points, Some(ends))
+    } else if !graph.is_empty() {
+        let (tree, ends) = mst::minimum_spanning_forest(&points, &graph);
+        if tree.len() + 1 < points.len() {
+            eprintln!("WARNING: graph is disconnected, found a forest");
+        } else if opts.verbose {
+            eprintln!("Found {} edges", tree.len());
+        }
+        (points, tree, (0..table.points.len()).collect(), Some(ends))
+    } else {
+        let (tree, ends) = spanning_tree(&points);
+        if opts.verbose {
+            eprintln!("Found {} edges", tree.len());
+        }
+        (points, tree, (0..table.points.len()).collect(), Some(ends))
+    };
+    // Indices into the chosen points as indices into the input, if none are
+    // of added points
+    let rows_of = |ends: &[(usize, usize)]| -> Option<Vec<(usize, usize)>> {
+        ends.iter()
+            .map(|&(u, v)| Some((*rows.get(u)?, *rows.get(v)?)))
+            .collect()
+    };
+
+    // Keep the required edges and avoid the forbidden ones
+    let (tree, ends) =
+        if !opts.required.is_empty() || !opts.forbidden.is_empty() {
+            let required = read_edges(&opts.required, opts.delimiter);
+            let forbidden = read_edges(&opts.forbidden, opts.delimiter);
+            if opts.verbose {
+                eprintln!(
+                    "Read {} required and {} forbidden edges",
+                    required.len(),
+                    forbidden.len()
+                );
+            }
+            match mst::constrained_mst_indices(&points, &required, &forbidden) {
+                Ok(ends) => (mst::index_edges(&points, &ends), Some(ends)),
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(3);
+                }
+            }
+        } else {
+            (tree, ends)
+        };
+
+    // Avoid the obstacles, or route around them
+    let (tree, ends, paths) = if opts.obstacles.is_empty() {
+        (tree, ends, None)
+    } else if opts.detour {
+        let polygons = read_polygons(&opts.obstacles, opts.delimiter);
+        if opts.verbose {
+            eprintln!("Read {} obstacles", polygons.len());
+        }
+        match obstacles::obstacle_mst(&points, &polygons, true) {
+            Ok(paths) => {
+                if opts.verbose {
+                    let bent = paths.iter().filter(|p| !p.bends.is_empty());
+                    eprintln!("Routed {} edges around obstacles", bent.count());
+                }
+                (paths.iter().map(|p| p.edge).collect(), None, Some(paths))
+            }
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    } else {
+        let polygons = read_polygons(&opts.obstacles, opts.delimiter);
+        if opts.verbose {
+            eprintln!("Read {} obstacles", polygons.len());
+        }
+        match obstacles::obstacle_mst_indices(&points, &polygons) {
+            Ok(ends) => (mst::index_edges(&points, &ends), Some(ends), None),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    };
+
+    // Limit the number of edges at each vertex
+    let limited = opts.max_degree.is_some() || !opts.degree_limits.is_empty();
+    let (tree, ends) = if limited {
+        let limits = degree_limits(&opts, &points);
+        match mst::degree_constrained_mst_indices(&points, &limits) {
+            Ok(mut steps) => {
+                let ends: Vec<(usize, usize)> = steps.by_ref().collect();
+                if !steps.satisfied() {
+                    eprintln!("WARNING: degree limits not met");
+                } else if opts.verbose {
+                    eprintln!("Degree limits met");
+                }
+                (mst::index_edges(&points, &ends), Some(ends))
+            }
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    } else {
+        (tree, ends)
+    };
+
+    // Cut the tree into clusters
+    let labels = if let Some(n) = opts.clusters {
+        Some(cluster::cut_longest(&points, &tree, n.saturating_sub(1)))
+    } else {
+        opts.cut_length
+            .map(|length| cluster::cut_length(&points, &tree, length))
+    };
+    let labels = match labels {
+        Some(Ok(labels)) => {
+            if opts.verbose {
+                eprintln!("Found {} clusters", cluster::num_clusters(&labels));
+            }
+            Some(labels.into_iter().map(Some).collect::<Vec<_>>())
+        }
+        Some(Err(e)) => {
             println!("{}", e);
             std::process::exit(3);
         }
+        None => None,
     };
 
-    if opts.verbose {
-        eprintln!("Found {} edges", tree.len());
-    }
+    // Build the single-linkage hierarchy
+    let links = if opts.linkage {
+        match cluster::linkage(&points, &tree) {
+            Ok(links) => Some(links),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Find a travelling salesman tour
+    let tour = if opts.tour.is_empty() {
+        None
+    } else {
+        let tour = if opts.tour.as_str() == "christofides" {
+            tsp::christofides(&points, &tree)
+        } else {
+            tsp::preorder_tour(&points, &tree)
+        };
+        let mut tour = match tour {
+            Ok(tour) => tour,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        if opts.verbose {
+            eprintln!(
+                "Found tour of length {}",
+                tsp::tour_length(&points, &tour)
+            );
+        }
+        if opts.two_opt && opts.or_opt {
+            tsp::improve(&points, &mut tour);
+        } else if opts.two_opt {
+            tsp::two_opt(&points, &mut tour);
+        } else if opts.or_opt {
+            tsp::or_opt(&points, &mut tour);
+        }
+        if opts.verbose && (opts.two_opt || opts.or_opt) {
+            eprintln!(
+                "Improved tour to length {}",
+                tsp::tour_length(&points, &tour)
+            );
+        }
+        Some(tour.into_iter().map(|i| rows[i]).collect::<Vec<_>>())
+    };
 
-    if opts.output.len() > 0 {
+    // Find the best replacement for each edge, and the second-best tree
+    let replacements = if opts.sensitivity {
+        let replacements = match sensitivity::replacement_costs(&points, &tree)
+        {
+            Ok(replacements) => replacements,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        let length: f32 = tree.iter().map(|e| e.len()).sum();
+        let second = replacements
+            .iter()
+            .filter_map(|r| r.delta())
+            .min_by(|a, b| a.total_cmp(b))
+            .map(|delta| length + delta);
+        if opts.verbose {
+            eprintln!("Found replacements for {} edges", replacements.len());
+        }
+        let comment = match second {
+            Some(second) => format!(
+                "# minimum spanning tree length {}\n\
+                 # second-best spanning tree length {}",
+                length, second
+            ),
+            None => format!(
+                "# minimum spanning tree length {}\n\
+                 # there is only one spanning tree",
+                length
+            ),
+        };
+        Some((replacements, comment))
+    } else {
+        None
+    };
+
+    // Add Steiner points to shorten the tree
+    let steiner_tree = if opts.rectilinear {
+        Some(rectilinear::rectilinear_steiner_tree(&points))
+    } else if !opts.steiner.is_empty() {
+        Some(steiner::steiner_tree(&points))
+    } else {
+        None
+    };
+    // The points and any added points and corners, for the output
+    let (vertices, tree, ends, comment) = match steiner_tree {
+        None => (points, tree, ends, None),
+        Some(Err(e)) => {
+            println!("{}", e);
+            std::process::exit(3);
+        }
+        Some(Ok(steiner_tree)) => {
+            let mst = if opts.rectilinear {
+                rectilinear::rectilinear_mst(&points)
+            } else {
+                tree
+            };
+            let length: f32 = mst.iter().map(|e| e.len()).sum();
+            if opts.verbose {
+                eprintln!(
+                    "Added {} Steiner points, length {} (minimum spanning tree {})",
+                    steiner_tree.steiner.len(),
+                    steiner_tree.len(),
+                    length
+                );
+            }
+            if !opts.steiner.is_empty() {
+                let path = Path::new(&*opts.steiner);
+                match File::create(path) {
+                    Ok(mut f) => {
+                        if opts.verbose {
+                            eprintln!(
+                                "Opened Steiner points '{}'",
+                                path.display()
+                            );
+                        }
+                        exhaust_points(&steiner_tree.steiner, &mut f);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to open '{}': {}", path.display(), e);
+                        std::process::exit(2);
+                    }
+                }
+            }
+            let mut vertices = points;
+            vertices.extend_from_slice(&steiner_tree.steiner);
+            if opts.rectilinear {
+                // Report the rectilinear MST for comparison
+                let comment = format!(
+                    "# rectilinear Steiner tree length {}\n\
+                     # rectilinear minimum spanning tree length {}",
+                    steiner_tree.len(),
+                    length
+                );
+                let (vertices, wires, ends) = rectilinear::indexed_segments(
+                    &vertices,
+                    &steiner_tree.ends,
+                );
+                (vertices, wires, Some(ends), Some(comment))
+            } else {
+                (vertices, steiner_tree.edges, Some(steiner_tree.ends), None)
+            }
+        }
+    };
+
+    let mut fout: Box<dyn Write> = if !opts.output.is_empty() {
         // Deref the Rc<String> to Ref the String
         let path = Path::new(&*opts.output);
-        match File::create(&path) {
-            Ok(mut f) => {
+        match File::create(path) {
+            Ok(f) => {
                 if opts.verbose {
                     eprintln!("Opened output '{}'", path.display());
                 }
-                exhaust(&tree, &mut f);
+                Box::new(f)
             }
             Err(e) => {
                 eprintln!("Failed to open '{}': {}", path.display(), e);
@@ -126,7 +530,51 @@ fn main() {
         if opts.verbose {
             eprintln!("Writing to stdout");
         }
-        let mut fout = io::stdout();
+        Box::new(io::stdout())
+    };
+
+    if let Some(tour) = tour {
+        exhaust_table(&table, &tour, None, &mut fout);
+    } else if let Some(links) = links {
+        exhaust_linkage(&links, &mut fout);
+    } else if let Some(labels) = labels {
+        exhaust_table(&table, &rows, Some(&labels), &mut fout);
+    } else if let Some((replacements, comment)) = replacements {
+        if let Err(e) = writeln!(fout, "{}", comment) {
+            eprintln!("calc: {}", e);
+            std::process::exit(2);
+        }
+        exhaust_replacements(&replacements, &mut fout);
+    } else if let Some(paths) = paths {
+        exhaust_paths(&paths, &mut fout);
+    } else if opts.indexed {
+        match ends.as_deref().and_then(rows_of) {
+            Some(ends) if extra => {
+                exhaust_edge_table(&table, &tree, &ends, true, &mut fout)
+            }
+            Some(ends) => exhaust_indexed(&tree, &ends, &mut fout),
+            None => {
+                println!("Steiner points have no index in the input");
+                std::process::exit(3);
+            }
+        }
+    } else if let Some(format) = opts
+        .format
+        .or_else(|| Format::from_path(&opts.output))
+        .filter(|&format| format != Format::Csv)
+    {
+        exhaust_tree(format, &vertices, &tree, ends.as_deref(), &mut fout);
+    } else if let Some(ends) =
+        ends.as_deref().filter(|_| extra).and_then(rows_of)
+    {
+        exhaust_edge_table(&table, &tree, &ends, false, &mut fout);
+    } else {
+        if let Some(comment) = comment {
+            if let Err(e) = writeln!(fout, "{}", comment) {
+                eprintln!("calc: {}", e);
+                std::process::exit(2);
+            }
+        }
         exhaust(&tree, &mut fout);
     }
 
@@ -135,6 +583,13 @@ fn main() {
     }
 }
 
+/// Construct the Minimum Spanning Tree of a set of points, with the indices
+/// of the end points of each edge, so repeated points stay distinct.
+fn spanning_tree(points: &[Vertex]) -> (Vec<Edge>, Vec<(usize, usize)>) {
+    let ends = mst::minimum_spanning_tree_indices(points);
+    (mst::index_edges(points, &ends), ends)
+}
+
 /// Get command line options.
 fn get_options() -> Option<Options> {
     let mut options = Options {
@@ -142,6 +597,26 @@ fn get_options() -> Option<Options> {
         verbose: false,
         input: Rc::new("".to_string()),
         output: Rc::new("".to_string()),
+        clusters: None,
+        cut_length: None,
+        linkage: false,
+        tour: Rc::new("".to_string()),
+        two_opt: false,
+        or_opt: false,
+        steiner: Rc::new("".to_string()),
+        rectilinear: false,
+        max_degree: None,
+        degree_limits: Rc::new("".to_string()),
+        required: Rc::new("".to_string()),
+        forbidden: Rc::new("".to_string()),
+        obstacles: Rc::new("".to_string()),
+        detour: false,
+        k: None,
+        subset: Rc::new("".to_string()),
+        sensitivity: false,
+        indexed: false,
+        delimiter: None,
+        format: None,
     };
 
     let matches = App::new("MST Calc")
@@ -171,6 +646,211 @@ fn get_options() -> Option<Options> {
                 .takes_value(true)
                 .help("Name of output file"),
         )
+        .arg(
+            Arg::with_name("clusters")
+                .short("c")
+                .long("clusters")
+                .takes_value(true)
+                .help("Write labels for N clusters instead of edges"),
+        )
+        .arg(
+            Arg::with_name("cut-length")
+                .short("d")
+                .long("cut-length")
+                .takes_value(true)
+                .conflicts_with("clusters")
+                .help("Write cluster labels, cutting edges longer than this"),
+        )
+        .arg(
+            Arg::with_name("linkage")
+                .short("l")
+                .long("linkage")
+                .conflicts_with_all(&["clusters", "cut-length"])
+                .help("Write the single-linkage matrix instead of edges"),
+        )
+        .arg(
+            Arg::with_name("tour")
+                .short("t")
+                .long("tour")
+                .takes_value(true)
+                .possible_values(&TOUR_METHODS)
+                .conflicts_with_all(&["clusters", "cut-length", "linkage"])
+                .help("Write a travelling salesman tour instead of edges"),
+        )
+        .arg(
+            Arg::with_name("two-opt")
+                .long("two-opt")
+                .requires("tour")
+                .help("Improve the tour with 2-opt"),
+        )
+        .arg(
+            Arg::with_name("or-opt")
+                .long("or-opt")
+                .requires("tour")
+                .help("Improve the tour with Or-opt"),
+        )
+        .arg(
+            Arg::with_name("steiner")
+                .short("s")
+                .long("steiner")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "clusters",
+                    "cut-length",
+                    "linkage",
+                    "tour",
+                ])
+                .help("Write a Steiner tree, and its Steiner points to FILE"),
+        )
+        .arg(
+            Arg::with_name("rectilinear")
+                .short("r")
+                .long("rectilinear")
+                .conflicts_with_all(&[
+                    "clusters",
+                    "cut-length",
+                    "linkage",
+                    "tour",
+                ])
+                .help("Write a rectilinear Steiner tree as axis-aligned wires"),
+        )
+        .arg(
+            Arg::with_name("max-degree")
+                .short("m")
+                .long("max-degree")
+                .takes_value(true)
+                .conflicts_with_all(&["steiner", "rectilinear"])
+                .help("Allow at most D edges at any vertex"),
+        )
+        .arg(
+            Arg::with_name("degree-limits")
+                .long("degree-limits")
+                .takes_value(true)
+                .conflicts_with_all(&["steiner", "rectilinear"])
+                .help("Read x, y, limit records of per vertex degree limits"),
+        )
+        .arg(
+            Arg::with_name("required")
+                .long("required")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "steiner",
+                    "rectilinear",
+                    "max-degree",
+                    "degree-limits",
+                ])
+                .help("Read edges the tree must include"),
+        )
+        .arg(
+            Arg::with_name("forbidden")
+                .long("forbidden")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "steiner",
+                    "rectilinear",
+                    "max-degree",
+                    "degree-limits",
+                ])
+                .help("Read edges the tree must not include"),
+        )
+        .arg(
+            Arg::with_name("obstacles")
+                .long("obstacles")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "steiner",
+                    "rectilinear",
+                    "max-degree",
+                    "degree-limits",
+                    "required",
+                    "forbidden",
+                ])
+                .help("Read polygons no edge may pass through"),
+        )
+        .arg(
+            Arg::with_name("detour")
+                .long("detour")
+                .requires("obstacles")
+                .help("Route blocked edges around the obstacles"),
+        )
+        .arg(
+            Arg::with_name("k")
+                .short("k")
+                .long("k")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "max-degree",
+                    "degree-limits",
+                    "required",
+                    "forbidden",
+                    "obstacles",
+                ])
+                .help("Find the shortest tree spanning N of the points"),
+        )
+        .arg(
+            Arg::with_name("subset")
+                .long("subset")
+                .takes_value(true)
+                .requires("k")
+                .help("Write the points chosen by --k to FILE"),
+        )
+        .arg(
+            Arg::with_name("sensitivity")
+                .long("sensitivity")
+                .conflicts_with_all(&[
+                    "clusters",
+                    "cut-length",
+                    "linkage",
+                    "tour",
+                    "steiner",
+                    "rectilinear",
+                    "max-degree",
+                    "degree-limits",
+                    "required",
+                    "forbidden",
+                    "obstacles",
+                ])
+                .help("Write each edge with its best replacement"),
+        )
+        .arg(
+            Arg::with_name("indexed")
+                .long("indexed")
+                .conflicts_with_all(&[
+                    "clusters",
+                    "cut-length",
+                    "linkage",
+                    "tour",
+                    "steiner",
+                    "rectilinear",
+                    "detour",
+                    "sensitivity",
+                ])
+                .help("Write edges as indices into the input points"),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .long("delimiter")
+                .takes_value(true)
+                .possible_values(csv::DELIMITER_NAMES)
+                .help("Input field delimiter"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(format::FORMAT_NAMES)
+                .conflicts_with_all(&[
+                    "clusters",
+                    "cut-length",
+                    "linkage",
+                    "tour",
+                    "detour",
+                    "sensitivity",
+                    "indexed",
+                ])
+                .help("Tree output format"),
+        )
         .get_matches();
 
     if matches.is_present("help") {
@@ -189,6 +869,122 @@ fn get_options() -> Option<Options> {
         *Rc::make_mut(&mut options.output) = s.to_string();
     }
 
+    if matches.is_present("linkage") {
+        options.linkage = true;
+    }
+
+    if let Some(s) = matches.value_of("tour") {
+        *Rc::make_mut(&mut options.tour) = s.to_string();
+    }
+
+    if matches.is_present("two-opt") {
+        options.two_opt = true;
+    }
+
+    if matches.is_present("or-opt") {
+        options.or_opt = true;
+    }
+
+    if let Some(s) = matches.value_of("max-degree") {
+        match s.parse::<usize>() {
+            Ok(value) if value > 0 => options.max_degree = Some(value),
+            _ => {
+                eprintln!("ERROR: invalid maximum degree '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("degree-limits") {
+        *Rc::make_mut(&mut options.degree_limits) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("required") {
+        *Rc::make_mut(&mut options.required) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("forbidden") {
+        *Rc::make_mut(&mut options.forbidden) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("obstacles") {
+        *Rc::make_mut(&mut options.obstacles) = s.to_string();
+    }
+
+    if matches.is_present("detour") {
+        options.detour = true;
+    }
+
+    if let Some(s) = matches.value_of("k") {
+        match s.parse::<usize>() {
+            Ok(value) if value > 0 => options.k = Some(value),
+            _ => {
+                eprintln!("ERROR: invalid number of points '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("subset") {
+        *Rc::make_mut(&mut options.subset) = s.to_string();
+    }
+
+    if matches.is_present("sensitivity") {
+        options.sensitivity = true;
+    }
+
+    if matches.is_present("indexed") {
+        options.indexed = true;
+    }
+
+    if let Some(s) = matches.value_of("format") {
+        match s.parse::<Format>() {
+            Ok(format) => options.format = Some(format),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("delimiter") {
+        match s.parse::<csv::Delimiter>() {
+            Ok(delimiter) => options.delimiter = Some(delimiter),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if matches.is_present("rectilinear") {
+        options.rectilinear = true;
+    }
+
+    if let Some(s) = matches.value_of("steiner") {
+        *Rc::make_mut(&mut options.steiner) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("clusters") {
+        match s.parse::<usize>() {
+            Ok(value) if value > 0 => options.clusters = Some(value),
+            _ => {
+                eprintln!("ERROR: invalid number of clusters '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("cut-length") {
+        match s.parse::<f32>() {
+            Ok(value) if value >= 0.0 => options.cut_length = Some(value),
+            _ => {
+                eprintln!("ERROR: invalid cut length '{}'", s);
+                return None;
+            }
+        }
+    }
+
     Some(options)
 }
 
@@ -199,15 +995,101 @@ fn print_help() {
 \tRead a set of points, find the minimum spanning tree of those points\n\
 \tas a completely connected undirected graph, then write out the set of\n\
 \tedges that form a minimum spanning tree.\n\n\
+\tPoints may have a header row, with the points in the columns named x\n\
+\tand y, or the first two.  Records without a point there are skipped\n\
+\twith a warning.  Other columns are written after each point when\n\
+\tpoints are written (clusters, tours and subsets), and after each edge\n\
+\tof a CSV or indexed tree, for its u then its v end, though not for\n\
+\tSteiner or rectilinear trees, obstacle paths or other formats.\n\
+\tPoints may also be JSON, GeoJSON, WKT (POINTs and MULTIPOINTs),\n\
+\tGraphML or binary, from the input file name or its contents.\n\n\
+\tA GraphML graph with edges is a weighted graph: the tree is its\n\
+\tminimum spanning tree (a forest if it is disconnected), with the edge\n\
+\tweights, or lengths if none, as the edge lengths.  Clusters, linkage,\n\
+\ttours and indexed output work on this tree; the options that find\n\
+\ttrees among all the pairs of points (-k, -m, -r, -s, --degree-limits,\n\
+\t--required, --forbidden, --obstacles, --sensitivity) take points.\n\n\
 USAGE\n\n\
 \tcalc -h\n\
 \tcalc -i data.csv -o tree.csv\n\
-\tcalc -v < data.csv > tree.csv\n\n\
+\tcalc -v < data.csv > tree.csv\n\
+\tcalc -c 5 -i data.csv -o labels.csv\n\
+\tcalc -l -i data.csv -o linkage.csv\n\
+\tcalc -t walk --two-opt --or-opt -i data.csv -o tour.csv\n\
+\tcalc -s steiner.csv -i data.csv -o tree.csv\n\
+\tcalc -r -i data.csv -o wires.csv\n\
+\tcalc -m 3 -i data.csv -o tree.csv\n\
+\tcalc --required keep.csv --forbidden avoid.csv -i data.csv\n\
+\tcalc --obstacles walls.csv --detour -i data.csv -o paths.csv\n\
+\tcalc --obstacles walls.csv --indexed -i data.csv -o tree.csv\n\
+\tcalc --obstacles walls.csv -f dot -i data.csv -o tree.gv\n\
+\tcalc -k 10 --subset chosen.csv -i data.csv -o tree.csv\n\
+\tcalc --sensitivity -i data.csv -o report.csv\n\
+\tcalc --indexed -i data.csv -o tree.csv\n\
+\tcalc --delimiter comma -c 5 -i sensors.csv -o labels.csv\n\
+\tcalc -i points.geojson -o tree.geojson\n\
+\tcalc -i graph.graphml -o tree.graphml\n\
+\tcalc -i points.wkt -o tree.wkt\n\n\
 OPTIONS\n\n\
 \t-h,--help                 Print usage an exit\n\
 \t-v,--verbose              Enable debug messages (to stderr)\n\
 \t-i,--input FILENAME       Input file name (Default: stdin)\n\
 \t-o,--output FILENAME      Output file name (Default: stdout)\n\
+\t-c,--clusters N           Cut the N - 1 longest edges and write each\n\
+\t                          point with its cluster label\n\
+\t-d,--cut-length LENGTH    Cut edges longer than LENGTH and write each\n\
+\t                          point with its cluster label\n\
+\t-l,--linkage              Write the single-linkage matrix (a, b,\n\
+\t                          distance, size) as used by SciPy\n\
+\t-t,--tour METHOD          Write the points in the order of a travelling\n\
+\t                          salesman tour.  Methods: walk (preorder walk\n\
+\t                          of the tree), christofides\n\
+\t--two-opt                 Improve the tour by reversing sections\n\
+\t--or-opt                  Improve the tour by moving short sections\n\
+\t-s,--steiner FILENAME     Add Steiner points to shorten the tree, write\n\
+\t                          the Steiner points to FILENAME\n\
+\t-r,--rectilinear          Write a rectilinear Steiner tree as horizontal\n\
+\t                          and vertical segments, with its length and the\n\
+\t                          rectilinear MST length as comments.  With -s\n\
+\t                          the Steiner points are written too\n\
+\t-m,--max-degree D         Allow at most D edges at any vertex, warn if\n\
+\t                          that can't be met\n\
+\t--degree-limits FILENAME  Read per vertex degree limits as x, y, limit\n\
+\t                          records (-1 for no limit).  Vertices not\n\
+\t                          listed use --max-degree, if given\n\
+\t--required FILENAME       Read edges (x0, y0, x1, y1, or any tree\n\
+\t                          format) the tree must include\n\
+\t--forbidden FILENAME      Read edges (x0, y0, x1, y1, or any tree\n\
+\t                          format) the tree must not include\n\
+\t--obstacles FILENAME      Read polygons (x0, y0, x1, y1, ... one per\n\
+\t                          line, or WKT or GeoJSON polygons) that edges\n\
+\t                          may not pass through\n\
+\t--detour                  Route edges blocked by obstacles along the\n\
+\t                          shortest path around them, writing the bends\n\
+\t                          after each edge (x0, y0, x1, y1, x, y, ...),\n\
+\t                          so only as CSV and not --indexed\n\
+\t-k,--k N                  Find a short tree spanning exactly N of the\n\
+\t                          points (optimal when there are few subsets\n\
+\t                          of N points), and use only those points\n\
+\t--subset FILENAME         Write the points chosen by --k to FILENAME\n\
+\t--sensitivity             Write each edge with the shortest edge that\n\
+\t                          replaces it and the increase in length, and\n\
+\t                          the second-best tree length as a comment\n\
+\t--indexed                 Write edges as u, v, length records, where u\n\
+\t                          and v are the positions of the end points in\n\
+\t                          the input (from 0), so repeated points stay\n\
+\t                          distinct.  Plot with 'plot --points'\n\
+\t--delimiter NAME          Field delimiter of the CSV files read (tab,\n\
+\t                          comma, semicolon, whitespace).  Default:\n\
+\t                          detected from the first record of each file\n\
+\t-f,--format NAME          Tree output format: csv, json (points, edges\n\
+\t                          and length), geojson (LineStrings with a\n\
+\t                          length), wkt (a MULTILINESTRING), dot or\n\
+\t                          graphml (nodes with a pos and edges with a\n\
+\t                          weight) or binary.  Steiner points, and the\n\
+\t                          corners of rectilinear wires, follow the\n\
+\t                          input points.  Default: from the output\n\
+\t                          file name, otherwise csv\n\
     "
     );
 }
@@ -215,54 +1097,236 @@ OPTIONS\n\n\
 /// Print options (to stderr).
 fn print_options(opts: &Options) {
     eprintln!("Options");
-    eprintln!("  print_help : {}", opts.print_help);
-    eprintln!("  verbose    : {}", opts.verbose);
-    eprintln!("  input      : {}", opts.input);
-    eprintln!("  output     : {}", opts.output);
+    eprintln!("  print_help    : {}", opts.print_help);
+    eprintln!("  verbose       : {}", opts.verbose);
+    eprintln!("  input         : {}", opts.input);
+    eprintln!("  output        : {}", opts.output);
+    eprintln!("  clusters      : {:?}", opts.clusters);
+    eprintln!("  cut_length    : {:?}", opts.cut_length);
+    eprintln!("  linkage       : {}", opts.linkage);
+    eprintln!("  tour          : {}", opts.tour);
+    eprintln!("  two_opt       : {}", opts.two_opt);
+    eprintln!("  or_opt        : {}", opts.or_opt);
+    eprintln!("  steiner       : {}", opts.steiner);
+    eprintln!("  rectilinear   : {}", opts.rectilinear);
+    eprintln!("  max_degree    : {:?}", opts.max_degree);
+    eprintln!("  degree_limits : {}", opts.degree_limits);
+    eprintln!("  required      : {}", opts.required);
+    eprintln!("  forbidden     : {}", opts.forbidden);
+    eprintln!("  obstacles     : {}", opts.obstacles);
+    eprintln!("  detour        : {}", opts.detour);
+    eprintln!("  k             : {:?}", opts.k);
+    eprintln!("  subset        : {}", opts.subset);
+    eprintln!("  sensitivity   : {}", opts.sensitivity);
+    eprintln!("  indexed       : {}", opts.indexed);
+    eprintln!("  delimiter     : {:?}", opts.delimiter);
+    eprintln!("  format        : {:?}", opts.format);
 }
 
-/// Read points from a Reader.
-fn ingest<R>(reader: R) -> io::Result<Vec<Vertex>>
-where
-    R: BufRead,
-{
-    let mut points = Vec::<Vertex>::new();
+/// Read edges from a file, none if no file name is given.
+fn read_edges(file_name: &str, delimiter: Option<csv::Delimiter>) -> Vec<Edge> {
+    if file_name.is_empty() {
+        return Vec::new();
+    }
+    let format = Format::from_path(file_name);
+    match File::open(file_name) {
+        Ok(f) => match format::read_edges_file(&f, format, delimiter) {
+            Ok(edges) => edges,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", file_name, e);
+            std::process::exit(2);
+        }
+    }
+}
 
-    for line_result in reader.lines() {
-        let line = line_result.unwrap_or("".to_string());
-        if line.len() < 3 || line.starts_with("#") {
-            continue;
+/// Read obstacles from a file.
+fn read_polygons(
+    file_name: &str,
+    delimiter: Option<csv::Delimiter>,
+) -> Vec<obstacles::Polygon> {
+    let format = Format::from_path(file_name);
+    match File::open(file_name) {
+        Ok(f) => match format::read_polygons_file(&f, format, delimiter) {
+            Ok(polygons) => polygons,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", file_name, e);
+            std::process::exit(2);
         }
-        let mut i = line.split(FSEP);
-        let x = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            continue; // malformed input line
-        };
-        let y = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            continue; // malformed input line
-        };
-        points.push(Vertex::new(x, y));
     }
+}
 
-    Ok(points)
+/// Get the degree limit of each point from the maximum degree and the
+/// degree limits file.
+fn degree_limits(opts: &Options, points: &[Vertex]) -> Vec<usize> {
+    let mut limits = vec![opts.max_degree.unwrap_or(usize::MAX); points.len()];
+    if opts.degree_limits.is_empty() {
+        return limits;
+    }
+    // Same layout as a labels file, negative for no limit
+    let (vertices, values) = match File::open(&*opts.degree_limits) {
+        Ok(f) => {
+            match csv::read_labels(io::BufReader::new(f), opts.delimiter) {
+                Ok(limits) => limits,
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(3);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", opts.degree_limits, e);
+            std::process::exit(2);
+        }
+    };
+    let index: HashMap<(i32, i32), usize> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| ((p.x, p.y), i))
+        .collect();
+    for (v, limit) in vertices.iter().zip(values) {
+        if let Some(&i) = index.get(&(v.x, v.y)) {
+            limits[i] = limit.unwrap_or(usize::MAX);
+        }
+    }
+    limits
 }
 
 /// Print edges to the a Writer.
-fn exhaust<W>(edges: &Vec<Edge>, writer: &mut W)
+fn exhaust<W>(edges: &[Edge], writer: &mut W)
 where
     W: Write,
 {
     for e in edges {
-        match writeln!(
+        if let Err(e) = writeln!(
             writer,
             "{}{}{}{}{}{}{}",
             e.u.x, FSEP, e.u.y, FSEP, e.v.x, FSEP, e.v.y
         ) {
-            Err(e) => eprintln!("calc::exhaust: {}", e),
-            _ => {}
+            eprintln!("calc::exhaust: {}", e);
+            std::process::exit(2);
         }
     }
 }
+
+/// Print a tree in a format to a Writer, with the indices of the end points
+/// of its edges when they are known.
+fn exhaust_tree<W>(
+    format: Format,
+    points: &[Vertex],
+    edges: &[Edge],
+    ends: Option<&[(usize, usize)]>,
+    writer: &mut W,
+) where
+    W: Write,
+{
+    let result = match ends {
+        Some(ends) => {
+            format::write_indexed_tree(writer, format, points, edges, ends)
+        }
+        None => format::write_tree(writer, format, points, edges),
+    };
+    if let Err(e) = result {
+        eprintln!("calc::exhaust_tree: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print edges as indices into the points to a Writer.
+fn exhaust_indexed<W>(tree: &[Edge], ends: &[(usize, usize)], writer: &mut W)
+where
+    W: Write,
+{
+    if let Err(e) = csv::write_indexed_edges(writer, tree, ends) {
+        eprintln!("calc::exhaust_indexed: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print edges with the other fields of the rows of their end points to a
+/// Writer.
+fn exhaust_edge_table<W>(
+    table: &csv::PointTable,
+    tree: &[Edge],
+    ends: &[(usize, usize)],
+    indexed: bool,
+    writer: &mut W,
+) where
+    W: Write,
+{
+    if let Err(e) = csv::write_edge_table(writer, table, tree, ends, indexed) {
+        eprintln!("calc::exhaust_edge_table: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print points to a Writer.
+fn exhaust_points<W>(points: &[Vertex], writer: &mut W)
+where
+    W: Write,
+{
+    if let Err(e) = csv::write_points(writer, points) {
+        eprintln!("calc::exhaust_points: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print paths around obstacles to a Writer.
+fn exhaust_paths<W>(paths: &[obstacles::Path], writer: &mut W)
+where
+    W: Write,
+{
+    if let Err(e) = csv::write_paths(writer, paths) {
+        eprintln!("calc::exhaust_paths: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print edges with their replacements to a Writer.
+fn exhaust_replacements<W>(
+    replacements: &[sensitivity::Replacement],
+    writer: &mut W,
+) where
+    W: Write,
+{
+    if let Err(e) = csv::write_replacements(writer, replacements) {
+        eprintln!("calc::exhaust_replacements: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print rows of the input points, with any labels and the other fields of
+/// their records, to a Writer.
+fn exhaust_table<W>(
+    table: &csv::PointTable,
+    rows: &[usize],
+    labels: Option<&[Option<usize>]>,
+    writer: &mut W,
+) where
+    W: Write,
+{
+    if let Err(e) = csv::write_point_table(writer, table, rows, labels) {
+        eprintln!("calc::exhaust_table: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// Print a linkage matrix to a Writer.
+fn exhaust_linkage<W>(links: &[Link], writer: &mut W)
+where
+    W: Write,
+{
+    if let Err(e) = csv::write_linkage(writer, links) {
+        eprintln!("calc::exhaust_linkage: {}", e);
+        std::process::exit(2);
+    }
+}
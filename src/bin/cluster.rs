@@ -0,0 +1,367 @@
+//! MST Cluster
+//!
+//! Find density based clusters in a set of points in a plane (HDBSCAN).
+//!
+//! Read a set of points, build the minimum spanning tree of the mutual
+//! reachability distance, extract the most stable clusters, and write each
+//! point with its cluster label (-1 for noise).
+//!
+//! cluster -i vertices.csv -o labels.csv
+//! cluster -k 5 -s 10 < vertices.csv > labels.csv
+//! cluster --delimiter comma -i sensors.csv -o labels.csv
+
+use clap::{App, Arg};
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+
+use mst::{self, csv, format, format::Format, hdbscan, Edge};
+
+/// Clustering options
+#[derive(Clone)]
+struct Options {
+    /// Print usage
+    print_help: bool,
+    /// Print extra messages
+    verbose: bool,
+    /// The input file name
+    input: Rc<String>,
+    /// The output file name
+    output: Rc<String>,
+    /// Core distance is to this nearest neighbour
+    min_samples: usize,
+    /// Smallest cluster
+    min_cluster_size: usize,
+    /// Write the mutual reachability tree instead of labels
+    tree: bool,
+    /// Input field delimiter, detected if not given
+    delimiter: Option<csv::Delimiter>,
+}
+
+/// Default neighbour for the core distance
+const DEFAULT_MIN_SAMPLES: usize = 5;
+/// Default smallest cluster
+const DEFAULT_MIN_CLUSTER_SIZE: usize = 5;
+
+/// Alias the field separator
+const FSEP: &str = mst::FIELD_SEPARATOR;
+
+/// Read command line options.
+/// Read a set of points from a file or stdin, in any format.
+/// Cluster the points with HDBSCAN.
+/// Write the points and their labels to a file or stdout as CSV.
+fn main() {
+    let opts = match get_options() {
+        Some(opts) => opts,
+        None => std::process::exit(1),
+    };
+
+    if opts.print_help {
+        print_help();
+        std::process::exit(0);
+    }
+
+    if opts.verbose {
+        eprintln!("MST Cluster");
+        print_options(&opts);
+    }
+
+    // Open input
+    let table = if !opts.input.is_empty() {
+        // Use the given input file
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                let format = Format::from_path(&opts.input);
+                match format::read_points_file(&f, format, opts.delimiter) {
+                    Ok(table) => table,
+                    Err(e) => {
+                        println!("{}", e);
+                        std::process::exit(3);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        match format::read_points(io::stdin().lock(), None, opts.delimiter) {
+            Ok(table) => table,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    };
+
+    let points = &table.points;
+
+    if table.skipped > 0 {
+        eprintln!("WARNING: skipped {} records without a point", table.skipped);
+    }
+    if opts.verbose {
+        eprintln!("Read {} points", points.len());
+    }
+
+    let mut fout: Box<dyn Write> = if !opts.output.is_empty() {
+        let path = Path::new(&*opts.output);
+        match File::create(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened output '{}'", path.display());
+                }
+                Box::new(f)
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Writing to stdout");
+        }
+        Box::new(io::stdout())
+    };
+
+    if opts.tree {
+        let tree = hdbscan::mutual_reachability_tree(points, opts.min_samples);
+        if opts.verbose {
+            eprintln!("Found {} edges", tree.len());
+        }
+        exhaust(&tree, &mut fout);
+    } else {
+        let labels = match hdbscan::hdbscan(
+            points,
+            opts.min_samples,
+            opts.min_cluster_size,
+        ) {
+            Ok(labels) => labels,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        if opts.verbose {
+            let clusters = labels.iter().flatten().max().map_or(0, |&l| l + 1);
+            let noise = labels.iter().filter(|l| l.is_none()).count();
+            eprintln!("Found {} clusters and {} noise points", clusters, noise);
+        }
+        exhaust_labels(&table, &labels, &mut fout);
+    }
+
+    if opts.verbose {
+        eprintln!("Done");
+    }
+}
+
+/// Get command line options.
+fn get_options() -> Option<Options> {
+    let mut options = Options {
+        print_help: false,
+        verbose: false,
+        input: Rc::new("".to_string()),
+        output: Rc::new("".to_string()),
+        min_samples: DEFAULT_MIN_SAMPLES,
+        min_cluster_size: DEFAULT_MIN_CLUSTER_SIZE,
+        tree: false,
+        delimiter: None,
+    };
+
+    let matches = App::new("MST Cluster")
+        .arg(
+            Arg::with_name("help")
+                .short("h")
+                .long("help")
+                .help("Print usage and exit"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Enable debug messages"),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("Name of input file"),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Name of output file"),
+        )
+        .arg(
+            Arg::with_name("min-samples")
+                .short("k")
+                .long("min-samples")
+                .takes_value(true)
+                .help("Core distance is to the K-th nearest neighbour"),
+        )
+        .arg(
+            Arg::with_name("min-cluster-size")
+                .short("s")
+                .long("min-cluster-size")
+                .takes_value(true)
+                .help("Smallest number of points in a cluster"),
+        )
+        .arg(
+            Arg::with_name("tree")
+                .short("t")
+                .long("tree")
+                .help("Write the mutual reachability tree instead of labels"),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .long("delimiter")
+                .takes_value(true)
+                .possible_values(csv::DELIMITER_NAMES)
+                .help("Input field delimiter"),
+        )
+        .get_matches();
+
+    if matches.is_present("help") {
+        options.print_help = true;
+    }
+
+    if matches.is_present("verbose") {
+        options.verbose = true;
+    }
+
+    if let Some(s) = matches.value_of("input-file") {
+        *Rc::make_mut(&mut options.input) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("output-file") {
+        *Rc::make_mut(&mut options.output) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("min-samples") {
+        match s.parse::<usize>() {
+            Ok(value) if value > 0 => options.min_samples = value,
+            _ => {
+                eprintln!("ERROR: invalid number of samples '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("min-cluster-size") {
+        match s.parse::<usize>() {
+            Ok(value) if value >= 2 => options.min_cluster_size = value,
+            _ => {
+                eprintln!("ERROR: invalid cluster size (< 2) '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if matches.is_present("tree") {
+        options.tree = true;
+    }
+
+    if let Some(s) = matches.value_of("delimiter") {
+        match s.parse::<csv::Delimiter>() {
+            Ok(delimiter) => options.delimiter = Some(delimiter),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    Some(options)
+}
+
+/// Print a usage message.
+fn print_help() {
+    println!(
+        "\nMST Cluster\n\n\
+\tRead a set of points and find density based clusters (HDBSCAN) using\n\
+\tthe minimum spanning tree of the mutual reachability distance.  Write\n\
+\teach point with its cluster label, -1 for noise, followed by any other\n\
+\tcolumns of its input record.  The input may have a header row, with\n\
+\tthe points in the columns named x and y, or the first two, or be in\n\
+\tany format 'convert' reads (JSON, WKT, binary, ...).\n\n\
+USAGE\n\n\
+\tcluster -h\n\
+\tcluster -i data.csv -o labels.csv\n\
+\tcluster -v -k 5 -s 10 < data.csv > labels.csv\n\
+\tcluster -t -i data.csv -o tree.csv\n\
+\tcluster --delimiter comma -i sensors.csv -o labels.csv\n\n\
+OPTIONS\n\n\
+\t-h,--help                 Print usage an exit\n\
+\t-v,--verbose              Enable debug messages (to stderr)\n\
+\t-i,--input FILENAME       Input file name (Default: stdin)\n\
+\t-o,--output FILENAME      Output file name (Default: stdout)\n\
+\t-k,--min-samples K        Core distance is to the K-th nearest\n\
+\t                          neighbour (Default: {})\n\
+\t-s,--min-cluster-size N   Smallest number of points in a cluster\n\
+\t                          (Default: {})\n\
+\t-t,--tree                 Write the edges of the mutual reachability\n\
+\t                          tree instead of labels\n\
+\t--delimiter NAME          Input field delimiter (tab, comma, semicolon,\n\
+\t                          whitespace).  Default: detected\n\
+    ",
+        DEFAULT_MIN_SAMPLES, DEFAULT_MIN_CLUSTER_SIZE
+    );
+}
+
+/// Print options (to stderr).
+fn print_options(opts: &Options) {
+    eprintln!("Options");
+    eprintln!("  print_help       : {}", opts.print_help);
+    eprintln!("  verbose          : {}", opts.verbose);
+    eprintln!("  input            : {}", opts.input);
+    eprintln!("  output           : {}", opts.output);
+    eprintln!("  min_samples      : {}", opts.min_samples);
+    eprintln!("  min_cluster_size : {}", opts.min_cluster_size);
+    eprintln!("  tree             : {}", opts.tree);
+    eprintln!("  delimiter        : {:?}", opts.delimiter);
+}
+
+/// Print edges to the a Writer.
+fn exhaust<W>(edges: &[Edge], writer: &mut W)
+where
+    W: Write,
+{
+    for e in edges {
+        if let Err(e) = writeln!(
+            writer,
+            "{}{}{}{}{}{}{}",
+            e.u.x, FSEP, e.u.y, FSEP, e.v.x, FSEP, e.v.y
+        ) {
+            eprintln!("cluster::exhaust: {}", e)
+        }
+    }
+}
+
+/// Print points with their cluster labels and the other fields of their
+/// records to a Writer.
+fn exhaust_labels<W>(
+    table: &csv::PointTable,
+    labels: &[Option<usize>],
+    writer: &mut W,
+) where
+    W: Write,
+{
+    let rows: Vec<usize> = (0..table.points.len()).collect();
+    if let Err(e) = csv::write_point_table(writer, table, &rows, Some(labels)) {
+        eprintln!("cluster::exhaust_labels: {}", e)
+    }
+}
@@ -0,0 +1,327 @@
+//! MST Convert
+//!
+//! Convert a set of points or a tree between file formats.
+//!
+//! Read points (or, with --edges, a tree) in any format the tools read and
+//! write them in another, most often between text and the compact binary
+//! format for very large inputs.
+//!
+//! convert -i points.csv -o points.mstb
+//! convert -e -i tree.mstb -o tree.csv
+//! convert -f geojson < points.csv > points.geojson
+
+use clap::{App, Arg};
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+
+use mst::{self, binary, format, format::Format, Edge, Vertex};
+
+/// Convert options
+#[derive(Clone)]
+struct Options {
+    /// Print usage
+    print_help: bool,
+    /// Print extra messages
+    verbose: bool,
+    /// Convert a tree rather than points
+    edges: bool,
+    /// Input format, from the input file name or contents if not given
+    from: Option<Format>,
+    /// Output format, from the output file name if not given
+    format: Option<Format>,
+    /// The input file name
+    input: Rc<String>,
+    /// The output file name
+    output: Rc<String>,
+}
+
+/// Read command line options.
+/// Read points or a tree from a file or stdin.
+/// Write them to a file or stdout in another format.
+fn main() {
+    let opts = match get_options() {
+        Some(opts) => opts,
+        None => std::process::exit(1),
+    };
+
+    if opts.print_help {
+        print_help();
+        std::process::exit(0);
+    }
+
+    if opts.verbose {
+        eprintln!("MST Convert");
+        print_options(&opts);
+    }
+
+    // Read the input, mapping a file into memory where it can be
+    let input: Box<dyn AsRef<[u8]>> = if !opts.input.is_empty() {
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                match binary::map(&f) {
+                    Ok(map) => Box::new(map),
+                    Err(_) => Box::new(slurp(f)),
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        Box::new(slurp(io::stdin().lock()))
+    };
+    let bytes = (*input).as_ref();
+
+    let from = opts
+        .from
+        .or_else(|| Format::from_path(&opts.input))
+        .unwrap_or_else(|| Format::detect_bytes(bytes));
+    let to = opts
+        .format
+        .or_else(|| Format::from_path(&opts.output))
+        .unwrap_or(if from == Format::Binary {
+            Format::Csv
+        } else {
+            Format::Binary
+        });
+    if opts.verbose {
+        eprintln!("Converting {:?} to {:?}", from, to);
+    }
+
+    let mut fout: Box<dyn Write> = if !opts.output.is_empty() {
+        let path = Path::new(&*opts.output);
+        match File::create(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened output '{}'", path.display());
+                }
+                Box::new(io::BufWriter::new(f))
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Writing to stdout");
+        }
+        Box::new(io::BufWriter::new(io::stdout()))
+    };
+
+    let result = if opts.edges {
+        let edges = match format::read_edges_from(bytes, Some(from), None) {
+            Ok(edges) => edges,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        let points = vertices(&edges);
+        if opts.verbose {
+            eprintln!("Read {} points and {} edges", points.len(), edges.len());
+        }
+        format::write_tree(&mut fout, to, &points, &edges)
+    } else {
+        let table = match format::read_points_from(bytes, Some(from), None) {
+            Ok(table) => table,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        };
+        if opts.verbose {
+            eprintln!("Read {} points", table.points.len());
+        }
+        format::write_points(&mut fout, to, &table.points)
+    };
+    if let Err(e) = result.and_then(|_| fout.flush()) {
+        eprintln!("convert::exhaust: {}", e);
+    }
+
+    if opts.verbose {
+        eprintln!("Done");
+    }
+}
+
+/// Read all of a reader.
+fn slurp<R>(mut reader: R) -> Vec<u8>
+where
+    R: Read,
+{
+    let mut bytes = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut bytes) {
+        println!("{}", e);
+        std::process::exit(3);
+    }
+    bytes
+}
+
+/// Find the distinct end points of a set of edges, in order.
+fn vertices(edges: &[Edge]) -> Vec<Vertex> {
+    let mut seen = HashSet::new();
+    edges
+        .iter()
+        .flat_map(|e| [e.u, e.v])
+        .filter(|p| seen.insert((p.x, p.y)))
+        .collect()
+}
+
+/// Get command line options.
+fn get_options() -> Option<Options> {
+    let mut options = Options {
+        print_help: false,
+        verbose: false,
+        edges: false,
+        from: None,
+        format: None,
+        input: Rc::new("".to_string()),
+        output: Rc::new("".to_string()),
+    };
+
+    let matches = App::new("MST Convert")
+        .arg(
+            Arg::with_name("help")
+                .short("h")
+                .long("help")
+                .help("Print usage and exit"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Enable debug messages"),
+        )
+        .arg(
+            Arg::with_name("edges")
+                .short("e")
+                .long("edges")
+                .help("Convert a tree rather than points"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .possible_values(format::FORMAT_NAMES)
+                .help("Input format"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(format::FORMAT_NAMES)
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("Name of input file"),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Name of output file"),
+        )
+        .get_matches();
+
+    if matches.is_present("help") {
+        options.print_help = true;
+    }
+
+    if matches.is_present("verbose") {
+        options.verbose = true;
+    }
+
+    if matches.is_present("edges") {
+        options.edges = true;
+    }
+
+    if let Some(s) = matches.value_of("from") {
+        match s.parse::<Format>() {
+            Ok(format) => options.from = Some(format),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("format") {
+        match s.parse::<Format>() {
+            Ok(format) => options.format = Some(format),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("input-file") {
+        *Rc::make_mut(&mut options.input) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("output-file") {
+        *Rc::make_mut(&mut options.output) = s.to_string();
+    }
+
+    Some(options)
+}
+
+/// Print a usage message.
+fn print_help() {
+    println!(
+        "\nMST Convert\n\n\
+\tRead a set of points, or with -e a tree, and write it in another\n\
+\tformat: csv, json, geojson, wkt, dot, graphml or binary.  Binary is a\n\
+\tcompact little-endian format for very large inputs, read through a\n\
+\tmemory map.  A tree is written with the distinct end points of its\n\
+\tedges as its points.\n\n\
+USAGE\n\n\
+\tconvert -h\n\
+\tconvert -i points.csv -o points.mstb\n\
+\tconvert -e -i tree.mstb -o tree.csv\n\
+\tconvert -v -f geojson < points.csv > points.geojson\n\n\
+OPTIONS\n\n\
+\t-h,--help                 Print usage an exit\n\
+\t-v,--verbose              Enable debug messages (to stderr)\n\
+\t-e,--edges                Convert a tree rather than points\n\
+\t--from NAME               Input format (Default: from the input file\n\
+\t                          name or contents)\n\
+\t-f,--format NAME          Output format (Default: from the output file\n\
+\t                          name, otherwise binary, or csv when the input\n\
+\t                          is binary)\n\
+\t-i,--input FILENAME       Input file name (Default: stdin)\n\
+\t-o,--output FILENAME      Output file name (Default: stdout)\n\
+    "
+    );
+}
+
+/// Print options (to stderr).
+fn print_options(opts: &Options) {
+    eprintln!("Options");
+    eprintln!("  print_help : {}", opts.print_help);
+    eprintln!("  verbose    : {}", opts.verbose);
+    eprintln!("  edges      : {}", opts.edges);
+    eprintln!("  from       : {:?}", opts.from);
+    eprintln!("  format     : {:?}", opts.format);
+    eprintln!("  input      : {}", opts.input);
+    eprintln!("  output     : {}", opts.output);
+}
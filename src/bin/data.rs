@@ -3,14 +3,15 @@
 //! Generate a random set of points in a plane.
 //!
 //! data -n N -m M -w W -h H -O 0,0 -o output_file | -c config_file
+//! data -f geojson -o points.geojson
 //!
 //! Read the values from the configuration file, then from the command line
 //! with command line values overriding configuration file values.
 
 use clap::{App, Arg};
-use mst::{self, generate, Vertex};
+use mst::{self, format, format::Format, generate, Vertex};
 use std::fs::File;
-use std::io::prelude::*;
+use std::io;
 use std::path::Path;
 use std::rc::Rc;
 
@@ -35,6 +36,8 @@ struct Options {
     height: i32,
     /// The output file name
     output: Rc<String>,
+    /// The output format, from the output file name if not given
+    format: Option<Format>,
 }
 
 /// Default number of points to generate
@@ -50,9 +53,6 @@ const DEFAULT_WIDTH: i32 = 200;
 /// Default graph area height
 const DEFAULT_HEIGHT: i32 = 200;
 
-/// Alias the field separator
-const FSEP: &str = mst::FIELD_SEPARATOR;
-
 /// Read command line options.
 /// Generate a set of random points.
 /// Write the points to a file.
@@ -91,10 +91,14 @@ fn main() {
         eprintln!("Generated {} points", points.len());
     }
 
-    if opts.output.len() > 0 {
-        vwrite(&points, &opts.output);
+    let format = opts
+        .format
+        .or_else(|| Format::from_path(&opts.output))
+        .unwrap_or(Format::Csv);
+    if !opts.output.is_empty() {
+        vwrite(&points, format, &opts.output);
     } else {
-        vprint(&points);
+        vprint(&points, format);
     }
 
     if opts.verbose {
@@ -114,6 +118,7 @@ fn get_options() -> Option<Options> {
         width: DEFAULT_WIDTH,
         height: DEFAULT_HEIGHT,
         output: Rc::new("".to_string()),
+        format: None,
     };
 
     let matches = App::new("MST Data")
@@ -171,6 +176,14 @@ fn get_options() -> Option<Options> {
                 .takes_value(true)
                 .help("Name of output file"),
         )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(format::FORMAT_NAMES)
+                .help("Output format"),
+        )
         .get_matches();
 
     if matches.is_present("help") {
@@ -245,6 +258,16 @@ fn get_options() -> Option<Options> {
         *Rc::make_mut(&mut options.output) = s.to_string();
     }
 
+    if let Some(s) = matches.value_of("format") {
+        match s.parse::<Format>() {
+            Ok(format) => options.format = Some(format),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
     Some(options)
 }
 
@@ -263,6 +286,9 @@ OPTIONS\n\n\
 \t-w,--width N          Width of the graph area\n\
 \t-h,--height N         Height of the graph area\n\
 \t-o,--output FILENAME  Output file name\n\
+\t-f,--format NAME      Output format: csv, json, geojson, wkt, dot,\n\
+\t                      graphml or binary (Default: from the output\n\
+\t                      file name, otherwise csv)\n\
     "
     );
 }
@@ -279,19 +305,20 @@ fn print_options(opts: &Options) {
     eprintln!("  width        : {}", opts.width);
     eprintln!("  height       : {}", opts.height);
     eprintln!("  output       : {}", opts.output);
+    eprintln!("  format       : {:?}", opts.format);
 }
 
 /// Print vertices to the console.
-fn vprint(points: &Vec<Vertex>) {
-    for p in points {
-        println!("{}{}{}", p.x, FSEP, p.y);
+fn vprint(points: &[Vertex], format: Format) {
+    if let Err(e) = format::write_points(&mut io::stdout(), format, points) {
+        eprintln!("Failed to write: {}", e)
     }
 }
 
 /// Print vertices to  a file.
-fn vwrite(points: &Vec<Vertex>, output_file: &str) {
+fn vwrite(points: &[Vertex], format: Format, output_file: &str) {
     let path = Path::new(output_file);
-    let mut f = match File::create(&path) {
+    let mut f = match File::create(path) {
         Err(e) => {
             eprintln!("Failed to open file '{}': {}", path.display(), e);
             return;
@@ -299,13 +326,7 @@ fn vwrite(points: &Vec<Vertex>, output_file: &str) {
         Ok(file) => file,
     };
 
-    for p in points {
-        let line = format!("{}{}{}\n", p.x, FSEP, p.y);
-        match f.write_all(line.as_bytes()) {
-            Err(e) => {
-                eprintln!("Failed to write to file '{}': {}", path.display(), e)
-            }
-            Ok(_) => {}
-        }
+    if let Err(e) = format::write_points(&mut f, format, points) {
+        eprintln!("Failed to write to file '{}': {}", path.display(), e)
     }
 }
@@ -4,12 +4,13 @@
 //! spanning tree from those points, and plot the result.
 //!
 //! demo -n N -m M -w W -h H -O 0,0 -o output_file | -c config_file
+//! demo -a -o demo.gif     # animate Prim's algorithm
 //!
 //! Read the values from the configuration file, then from the command line
 //! with command line values overriding configuration file values.
 
 use clap::{App, Arg};
-use mst::{self, generate, minimum_spanning_tree, plot, Edge, Vertex};
+use mst::{self, animate, generate, minimum_spanning_tree, plot, Edge, Vertex};
 use std::rc::Rc;
 
 /// Graph creation options
@@ -35,6 +36,8 @@ struct Options {
     height: i32,
     /// The output file name
     output: Rc<String>,
+    /// Write an animated GIF of the tree growing
+    animate: bool,
 }
 
 /// Default number of points to generate
@@ -114,11 +117,21 @@ fn main() {
         eprint(&tree);
     }
 
-    if !opts.quiet {
-        println!("Plotting the minimum spanning tree and writing to a file");
+    if opts.animate {
+        if !opts.quiet {
+            println!(
+                "Animating the minimum spanning tree and writing to a file"
+            );
+        }
+        animate(&points, &opts.output).expect("write image failed");
+    } else {
+        if !opts.quiet {
+            println!(
+                "Plotting the minimum spanning tree and writing to a file"
+            );
+        }
+        plot(&tree, &opts.output).expect("write image failed");
     }
-
-    plot(&tree, &opts.output).expect("write image failed");
 }
 
 /// Get program options
@@ -134,6 +147,7 @@ fn get_options() -> Option<Options> {
         width: DEFAULT_WIDTH,
         height: DEFAULT_HEIGHT,
         output: Rc::new("demo.png".to_string()),
+        animate: false,
     };
 
     let matches = App::new("MST Demo")
@@ -205,6 +219,12 @@ fn get_options() -> Option<Options> {
                 .takes_value(true)
                 .help("Name of output file"),
         )
+        .arg(
+            Arg::with_name("animate")
+                .short("a")
+                .long("animate")
+                .help("Write an animated GIF of the tree being built"),
+        )
         .get_matches();
 
     if matches.is_present("help") {
@@ -281,6 +301,11 @@ fn get_options() -> Option<Options> {
         }
     }
 
+    if matches.is_present("animate") {
+        options.animate = true;
+        *Rc::make_mut(&mut options.output) = "demo.gif".to_string();
+    }
+
     if let Some(s) = matches.value_of("output-file") {
         *Rc::make_mut(&mut options.output) = s.to_string();
     }
@@ -306,6 +331,8 @@ OPTIONS\n\n\
 \t-h,--height N         Height of the graph area\n\
 \t-o,--output FILENAME  Output file name\n\
 \t-c,--config FILENAME  Configuration file from which to read these values\n\
+\t-a,--animate          Write an animated GIF of Prim's algorithm building\n\
+\t                      the tree (Default output: demo.gif)\n\
     ",
         VERSION
     );
@@ -323,10 +350,11 @@ fn print_options(opts: &Options) {
     println!("width        : {}", opts.width);
     println!("height       : {}", opts.height);
     println!("output       : {}", opts.output);
+    println!("animate      : {}", opts.animate);
 }
 
 /// Print edges
-fn eprint(edges: &Vec<Edge>) {
+fn eprint(edges: &[Edge]) {
     for e in edges {
         println!(
             "({},{}) -> ({},{}) [{:.2}]",
@@ -340,7 +368,7 @@ fn eprint(edges: &Vec<Edge>) {
 }
 
 /// Print vertices
-fn vprint(points: &Vec<Vertex>) {
+fn vprint(points: &[Vertex]) {
     for p in points {
         println!("( {:2}, {:2} )", p.x, p.y);
     }
@@ -3,10 +3,27 @@
 //! Plot a set of graph edges to a PNG file.
 //!
 //! plot [ -v ] -i tree.csv -o mst.png
+//! plot -c viridis -i tree.csv -o mst.png   # colour edges by length
+//! plot -t 12.5 -i tree.csv -o mst.png      # highlight edges longer than 12.5
+//! plot -a -i tree.csv -o mst.gif           # animate Prim's algorithm
+//! plot -l labels.csv -i tree.csv           # colour clusters
+//! plot -d -i linkage.csv -o dendrogram.png # single-linkage dendrogram
+//! plot --tour tour.csv -i tree.csv         # tour over the tree
+//! plot -s steiner.csv -i tree.csv          # mark Steiner points
+//! plot -r -i wires.csv                     # rectilinear wires
+//! plot --obstacles walls.csv -i paths.csv  # paths around obstacles
+//! plot -p data.csv -i tree.csv         # edges as indices into points
+//! plot -i tree.geojson                 # JSON, GeoJSON, WKT or GraphML
 //!
 
 use clap::{App, Arg};
-use mst::{self, plot, Edge};
+use mst::{
+    self, animate, csv, edge_indices, format, format::Format, plot,
+    plot_by_length, plot_clusters, plot_dendrogram, plot_obstacles,
+    plot_rectilinear, plot_steiner, plot_threshold, plot_tour, Colormap, Edge,
+    Vertex,
+};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io;
 use std::path::Path;
@@ -23,11 +40,30 @@ struct Options {
     input: Rc<String>,
     /// The output file name
     output: Rc<String>,
+    /// Colour edges by length with this colour map
+    colormap: Option<Colormap>,
+    /// Highlight edges longer than this
+    threshold: Option<f32>,
+    /// Write an animated GIF of the tree growing
+    animate: bool,
+    /// Cluster labels file name
+    labels: Rc<String>,
+    /// Read a linkage matrix and plot a dendrogram
+    dendrogram: bool,
+    /// Travelling salesman tour file name
+    tour: Rc<String>,
+    /// Steiner points file name
+    steiner: Rc<String>,
+    /// Draw edges as L-shaped wires
+    rectilinear: bool,
+    /// Obstacles file name
+    obstacles: Rc<String>,
+    /// Points file name, the edges are indices into its points
+    points: Rc<String>,
+    /// The input format, from the input file name or contents if not given
+    format: Option<Format>,
 }
 
-/// Alias the MST field seaprator
-const FSEP: &str = mst::FIELD_SEPARATOR;
-
 /// Read command line options.  Read a set of line segments (graph edges)
 /// from a file or stdin. Plot the line segements and write to a PNG file.
 fn main() {
@@ -46,38 +82,125 @@ fn main() {
         print_options(&opts);
     }
 
-    // Open input
-    let edges = if opts.input.len() > 0 {
-        // Use the given input file
-        let path = Path::new(&*opts.input);
-        match File::open(&path) {
-            Ok(f) => {
-                if opts.verbose {
-                    eprintln!("Opened input '{}'", path.display());
+    if !opts.tour.is_empty() {
+        let tour = match File::open(&*opts.tour) {
+            Ok(f) => match csv::read_points(io::BufReader::new(f)) {
+                Ok(tour) => tour,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", opts.tour, e);
+                std::process::exit(1);
+            }
+        };
+        // Only overlay the tree when one is given, don't wait on stdin
+        let edges = if opts.input.is_empty() {
+            Vec::new()
+        } else {
+            match read_tree(&opts, open_input(&opts)) {
+                Ok(edges) => edges,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
                 }
-                match ingest(io::BufReader::new(f)) {
-                    Ok(edges) => edges,
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        std::process::exit(1);
-                    }
+            }
+        };
+        if opts.verbose {
+            eprintln!(
+                "Read {} tour points and {} edges",
+                tour.len(),
+                edges.len()
+            );
+            eprintln!("Plotting tour");
+        }
+        if tour.len() < 2 {
+            eprintln!("Nothing to plot");
+        } else {
+            plot_tour(&tour, &edges, &opts.output).expect("write image failed");
+        }
+        if opts.verbose {
+            eprintln!("Done");
+        }
+        return;
+    }
+
+    // Open input
+    let reader = open_input(&opts);
+
+    if !opts.obstacles.is_empty() {
+        let format = Format::from_path(&opts.obstacles);
+        let obstacles = match File::open(&*opts.obstacles) {
+            Ok(f) => match format::read_polygons_file(&f, format, None) {
+                Ok(obstacles) => obstacles,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
                 }
+            },
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", opts.obstacles, e);
+                std::process::exit(1);
             }
+        };
+        let paths = match csv::read_paths(reader, None) {
+            Ok(paths) => paths,
             Err(e) => {
-                eprintln!("Failed to open '{}': {}", path.display(), e);
+                println!("Error: {}", e);
                 std::process::exit(1);
             }
+        };
+        if opts.verbose {
+            eprintln!(
+                "Read {} paths and {} obstacles",
+                paths.len(),
+                obstacles.len()
+            );
+            eprintln!("Plotting paths around obstacles");
+        }
+        if paths.is_empty() && obstacles.is_empty() {
+            eprintln!("Nothing to plot");
+        } else {
+            plot_obstacles(&paths, &obstacles, &opts.output)
+                .expect("write image failed");
         }
-    } else {
         if opts.verbose {
-            eprintln!("Reading from stdin");
+            eprintln!("Done");
         }
-        match ingest(io::stdin().lock()) {
-            Ok(edges) => edges,
+        return;
+    }
+
+    if opts.dendrogram {
+        let links = match csv::read_linkage(reader, None) {
+            Ok(links) => links,
             Err(e) => {
                 println!("Error: {}", e);
                 std::process::exit(1);
             }
+        };
+        if opts.verbose {
+            eprintln!("Read {} merges", links.len());
+            eprintln!("Plotting dendrogram");
+        }
+        if links.is_empty() {
+            eprintln!("Nothing to plot");
+        } else if let Err(e) = plot_dendrogram(&links, &opts.output) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        if opts.verbose {
+            eprintln!("Done");
+        }
+        return;
+    }
+
+    let edges = match read_tree(&opts, reader) {
+        Ok(edges) => edges,
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
         }
     };
 
@@ -85,11 +208,76 @@ fn main() {
         eprintln!("Read {} edges", edges.len());
     }
 
-    if edges.len() > 0 {
+    if !edges.is_empty() && opts.animate {
+        if opts.verbose {
+            eprintln!("Animating graph");
+        }
+        animate(&vertices(&edges), &opts.output).expect("write image failed");
+    } else if !edges.is_empty() && !opts.labels.is_empty() {
+        let (points, labels) = match File::open(&*opts.labels) {
+            Ok(f) => match csv::read_labels(io::BufReader::new(f), None) {
+                Ok(labels) => labels,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", opts.labels, e);
+                std::process::exit(1);
+            }
+        };
+        if opts.verbose {
+            eprintln!("Read {} labels", labels.len());
+            eprintln!("Plotting clusters");
+        }
+        // Label the ends of the edges by the rows of the labels file
+        let ends = match edge_indices(&points, &edges) {
+            Ok(ends) => ends,
+            Err(e) => {
+                println!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        plot_clusters(&edges, &ends, &labels, &opts.output)
+            .expect("write image failed");
+    } else if !edges.is_empty() && !opts.steiner.is_empty() {
+        let steiner = match File::open(&*opts.steiner) {
+            Ok(f) => match csv::read_points(io::BufReader::new(f)) {
+                Ok(steiner) => steiner,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", opts.steiner, e);
+                std::process::exit(1);
+            }
+        };
+        if opts.verbose {
+            eprintln!("Read {} Steiner points", steiner.len());
+            eprintln!("Plotting Steiner tree");
+        }
+        plot_steiner(&edges, &steiner, &opts.output)
+            .expect("write image failed");
+    } else if !edges.is_empty() && opts.rectilinear {
+        if opts.verbose {
+            eprintln!("Plotting rectilinear wires");
+        }
+        plot_rectilinear(&edges, &opts.output).expect("write image failed");
+    } else if !edges.is_empty() {
         if opts.verbose {
             eprintln!("Plotting graph");
         }
-        plot(&edges, &opts.output).expect("write image failed");
+        if let Some(threshold) = opts.threshold {
+            plot_threshold(&edges, &opts.output, threshold)
+        } else if let Some(colormap) = opts.colormap {
+            plot_by_length(&edges, &opts.output, colormap)
+        } else {
+            plot(&edges, &opts.output)
+        }
+        .expect("write image failed");
     } else {
         eprintln!("Nothing to plot");
     }
@@ -99,6 +287,56 @@ fn main() {
     }
 }
 
+/// Read the edges, as indices into the points if a points file is given.
+fn read_tree<R>(opts: &Options, reader: R) -> io::Result<Vec<Edge>>
+where
+    R: io::BufRead,
+{
+    if opts.points.is_empty() {
+        let format = opts.format.or_else(|| Format::from_path(&opts.input));
+        return format::read_edges(reader, format, None);
+    }
+    let points = match File::open(&*opts.points) {
+        Ok(f) => {
+            let format = Format::from_path(&opts.points);
+            format::read_points_file(&f, format, None)?.points
+        }
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", opts.points, e);
+            std::process::exit(1);
+        }
+    };
+    if opts.verbose {
+        eprintln!("Read {} points", points.len());
+    }
+    csv::read_indexed_edges(reader, &points, None)
+}
+
+/// Open the input file, or stdin if no input file is given.
+fn open_input(opts: &Options) -> Box<dyn io::BufRead> {
+    if !opts.input.is_empty() {
+        // Use the given input file
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                Box::new(io::BufReader::new(f))
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        Box::new(io::BufReader::new(io::stdin()))
+    }
+}
+
 /// Get program options
 fn get_options() -> Option<Options> {
     let mut options = Options {
@@ -106,6 +344,17 @@ fn get_options() -> Option<Options> {
         verbose: false,
         input: Rc::new("".to_string()),
         output: Rc::new("mst.png".to_string()),
+        colormap: None,
+        threshold: None,
+        animate: false,
+        labels: Rc::new("".to_string()),
+        dendrogram: false,
+        tour: Rc::new("".to_string()),
+        steiner: Rc::new("".to_string()),
+        rectilinear: false,
+        obstacles: Rc::new("".to_string()),
+        points: Rc::new("".to_string()),
+        format: None,
     };
 
     let matches = App::new("MST Plot")
@@ -123,6 +372,114 @@ fn get_options() -> Option<Options> {
                 .long("output")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("colormap")
+                .short("c")
+                .long("colormap")
+                .takes_value(true)
+                .possible_values(mst::colormap::COLORMAP_NAMES),
+        )
+        .arg(
+            Arg::with_name("threshold")
+                .short("t")
+                .long("threshold")
+                .takes_value(true)
+                .conflicts_with("colormap"),
+        )
+        .arg(
+            Arg::with_name("animate")
+                .short("a")
+                .long("animate")
+                .conflicts_with_all(&["colormap", "threshold"]),
+        )
+        .arg(
+            Arg::with_name("labels")
+                .short("l")
+                .long("labels")
+                .takes_value(true)
+                .conflicts_with_all(&["colormap", "threshold", "animate"]),
+        )
+        .arg(
+            Arg::with_name("dendrogram")
+                .short("d")
+                .long("dendrogram")
+                .conflicts_with_all(&[
+                    "colormap",
+                    "threshold",
+                    "animate",
+                    "labels",
+                ]),
+        )
+        .arg(
+            Arg::with_name("tour")
+                .long("tour")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "colormap",
+                    "threshold",
+                    "animate",
+                    "labels",
+                    "dendrogram",
+                ]),
+        )
+        .arg(
+            Arg::with_name("steiner")
+                .short("s")
+                .long("steiner")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "colormap",
+                    "threshold",
+                    "animate",
+                    "labels",
+                    "dendrogram",
+                    "tour",
+                ]),
+        )
+        .arg(
+            Arg::with_name("rectilinear")
+                .short("r")
+                .long("rectilinear")
+                .conflicts_with_all(&[
+                    "colormap",
+                    "threshold",
+                    "animate",
+                    "labels",
+                    "dendrogram",
+                    "tour",
+                    "steiner",
+                ]),
+        )
+        .arg(
+            Arg::with_name("obstacles")
+                .long("obstacles")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "colormap",
+                    "threshold",
+                    "animate",
+                    "labels",
+                    "dendrogram",
+                    "tour",
+                    "steiner",
+                    "rectilinear",
+                ]),
+        )
+        .arg(
+            Arg::with_name("points")
+                .short("p")
+                .long("points")
+                .takes_value(true)
+                .conflicts_with_all(&["dendrogram", "obstacles"]),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(format::FORMAT_NAMES)
+                .conflicts_with_all(&["dendrogram", "obstacles", "points"]),
+        )
         .get_matches();
 
     if matches.is_present("help") {
@@ -137,10 +494,74 @@ fn get_options() -> Option<Options> {
         *Rc::make_mut(&mut options.input) = s.to_string();
     }
 
+    if matches.is_present("animate") {
+        options.animate = true;
+        *Rc::make_mut(&mut options.output) = "mst.gif".to_string();
+    }
+
+    if matches.is_present("dendrogram") {
+        options.dendrogram = true;
+        *Rc::make_mut(&mut options.output) = "dendrogram.png".to_string();
+    }
+
     if let Some(s) = matches.value_of("output-file") {
         *Rc::make_mut(&mut options.output) = s.to_string();
     }
 
+    if let Some(s) = matches.value_of("tour") {
+        *Rc::make_mut(&mut options.tour) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("obstacles") {
+        *Rc::make_mut(&mut options.obstacles) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("points") {
+        *Rc::make_mut(&mut options.points) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("format") {
+        match s.parse::<Format>() {
+            Ok(format) => options.format = Some(format),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if matches.is_present("rectilinear") {
+        options.rectilinear = true;
+    }
+
+    if let Some(s) = matches.value_of("steiner") {
+        *Rc::make_mut(&mut options.steiner) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("labels") {
+        *Rc::make_mut(&mut options.labels) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("colormap") {
+        match s.parse::<Colormap>() {
+            Ok(colormap) => options.colormap = Some(colormap),
+            Err(e) => {
+                eprintln!("ERROR: {} '{}'", e, s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("threshold") {
+        match s.parse::<f32>() {
+            Ok(value) if value >= 0.0 => options.threshold = Some(value),
+            _ => {
+                eprintln!("ERROR: invalid threshold '{}'", s);
+                return None;
+            }
+        }
+    }
+
     Some(options)
 }
 
@@ -153,59 +574,52 @@ fn print_help() {
 \tWrite the image to a PNG file.\n\n\
 USAGE\n\n\
 \tplot [ -v ] -i tree.csv -o mst.png\n\
-\tplot -v < tree.png        # Image in default 'mst.png'\n\n\
+\tplot -v < tree.png        # Image in default 'mst.png'\n\
+\tplot -c magma -i tree.csv  # Colour edges by length\n\
+\tplot -t 12 -i tree.csv     # Highlight edges longer than 12\n\
+\tplot -a -i tree.csv        # Animation in default 'mst.gif'\n\
+\tplot -l labels.csv -i tree.csv  # Colour clusters from 'calc -c'\n\
+\tplot -d -i linkage.csv     # Dendrogram in default 'dendrogram.png'\n\
+\tplot --tour tour.csv -i tree.csv  # Tour from 'calc -t' over the tree\n\
+\tplot -s steiner.csv -i tree.csv   # Steiner tree from 'calc -s'\n\
+\tplot -r -i wires.csv       # Wires from 'calc -r'\n\
+\tplot --obstacles walls.csv -i paths.csv  # Paths from 'calc --obstacles'\n\
+\tplot -p data.csv -i tree.csv  # Edges from 'calc --indexed'\n\
+\tplot -i tree.geojson       # Edges from 'calc -f geojson'\n\n\
 OPTIONS\n\n\
 \t-h,--help                 Print usage an exit\n\
 \t-v,--verbose              Enable extra messages\n\
 \t-i,--input FILENAME       Input file name (Default: stdin)\n\
 \t-o,--output FILENAME      Output file name (Default: mst.png)\n\
+\t-c,--colormap NAME        Colour edges by length and draw a legend\n\
+\t                          (viridis, magma, grayscale, diverging)\n\
+\t-t,--threshold LENGTH     Draw edges longer than LENGTH in red\n\
+\t-a,--animate              Write an animated GIF of Prim's algorithm\n\
+\t                          building the tree (Default output: mst.gif)\n\
+\t-l,--labels FILENAME      Colour each cluster in a labels file written\n\
+\t                          by 'calc --clusters' or 'calc --cut-length'\n\
+\t-d,--dendrogram           Read a linkage matrix written by\n\
+\t                          'calc --linkage' and plot a dendrogram\n\
+\t                          (Default output: dendrogram.png)\n\
+\t--tour FILENAME           Draw the closed tour in a file written by\n\
+\t                          'calc --tour', over the tree from the input\n\
+\t                          file if one is given with -i\n\
+\t-s,--steiner FILENAME     Mark the Steiner points in a file written by\n\
+\t                          'calc --steiner' with red squares\n\
+\t-r,--rectilinear          Draw each edge as an L-shaped horizontal and\n\
+\t                          vertical wire\n\
+\t--obstacles FILENAME      Fill the polygons in FILENAME and draw the\n\
+\t                          bent paths written by 'calc --obstacles'\n\
+\t-p,--points FILENAME      Read the input as u, v, length records written\n\
+\t                          by 'calc --indexed', where u and v are the\n\
+\t                          positions of the end points in FILENAME\n\
+\t-f,--format NAME          Input format: csv, json, geojson, wkt,\n\
+\t                          graphml or binary (Default: from the input\n\
+\t                          file name or contents)\n\
     "
     );
 }
 
-/// Read points from a Reader.
-fn ingest<R>(reader: R) -> io::Result<Vec<Edge>>
-where
-    R: io::BufRead,
-{
-    let mut edges = Vec::<Edge>::new();
-
-    for line_result in reader.lines() {
-        let line = line_result.unwrap_or("".to_string());
-        if line.len() < 3 || line.starts_with("#") {
-            continue;
-        }
-        let mut i = line.split(FSEP);
-        let x0 = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            eprintln!("malformed input line");
-            continue;
-        };
-        let y0 = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            eprintln!("malformed input line");
-            continue;
-        };
-        let x1 = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            eprintln!("malformed input line");
-            continue;
-        };
-        let y1 = if let Some(s) = i.next() {
-            s.parse::<i32>().unwrap_or(0)
-        } else {
-            eprintln!("malformed input line");
-            continue;
-        };
-        edges.push(Edge::new(x0, y0, x1, y1));
-    }
-
-    Ok(edges)
-}
-
 /// Print options
 fn print_options(opts: &Options) {
     eprintln!("Options");
@@ -213,4 +627,29 @@ fn print_options(opts: &Options) {
     eprintln!("  verbose    : {}", opts.verbose);
     eprintln!("  input      : {}", opts.input);
     eprintln!("  output     : {}", opts.output);
+    eprintln!("  colormap   : {:?}", opts.colormap);
+    eprintln!("  threshold  : {:?}", opts.threshold);
+    eprintln!("  animate    : {}", opts.animate);
+    eprintln!("  labels     : {}", opts.labels);
+    eprintln!("  dendrogram : {}", opts.dendrogram);
+    eprintln!("  tour       : {}", opts.tour);
+    eprintln!("  steiner    : {}", opts.steiner);
+    eprintln!("  rectilinear: {}", opts.rectilinear);
+    eprintln!("  obstacles  : {}", opts.obstacles);
+    eprintln!("  points     : {}", opts.points);
+    eprintln!("  format     : {:?}", opts.format);
+}
+
+/// Get the distinct vertices of a set of edges in the order they appear.
+fn vertices(edges: &[Edge]) -> Vec<Vertex> {
+    let mut seen = HashSet::new();
+    let mut points = Vec::new();
+    for e in edges {
+        for v in [e.u, e.v] {
+            if seen.insert((v.x, v.y)) {
+                points.push(v);
+            }
+        }
+    }
+    points
 }
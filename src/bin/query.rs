@@ -0,0 +1,323 @@
+//! MST Query
+//!
+//! Answer bottleneck (minimax) path queries on a minimum spanning tree.
+//!
+//! Read a tree and a set of vertex pairs, and for each pair write the
+//! longest edge on the tree path between them, the bottleneck every route
+//! between the two points must cross, and the length of the tree path.
+//! Every pair has a row, in order: a pair of copies of a point has itself
+//! as a zero bottleneck, and a pair with a point not in the tree has `NA`
+//! answers.
+//!
+//! query -t tree.csv -i pairs.csv -o answers.csv
+//! query -t tree.csv < pairs.csv > answers.csv
+
+use clap::{App, Arg};
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+
+use mst::{
+    self, bottleneck::Bottleneck, format, format::Format, tree::MstTree, Vertex,
+};
+
+/// Query options
+#[derive(Clone)]
+struct Options {
+    /// Print usage
+    print_help: bool,
+    /// Print extra messages
+    verbose: bool,
+    /// The tree file name
+    tree: Rc<String>,
+    /// The input (pairs) file name
+    input: Rc<String>,
+    /// The output file name
+    output: Rc<String>,
+}
+
+/// Alias the field separator
+const FSEP: &str = mst::FIELD_SEPARATOR;
+
+/// Read command line options.
+/// Read a tree from a file and vertex pairs from a file or stdin, in any
+/// format.
+/// Find the bottleneck edge and path length between each pair.
+/// Write the answers to a file or stdout as CSV.
+fn main() {
+    let opts = match get_options() {
+        Some(opts) => opts,
+        None => std::process::exit(1),
+    };
+
+    if opts.print_help {
+        print_help();
+        std::process::exit(0);
+    }
+
+    if opts.verbose {
+        eprintln!("MST Query");
+        print_options(&opts);
+    }
+
+    // Read the tree
+    let path = Path::new(&*opts.tree);
+    let edges = match File::open(path) {
+        Ok(f) => {
+            if opts.verbose {
+                eprintln!("Opened tree '{}'", path.display());
+            }
+            let format = Format::from_path(&opts.tree);
+            match format::read_edges_file(&f, format, None) {
+                Ok(edges) => edges,
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(3);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+    let tree = match MstTree::from_edges(&edges) {
+        Ok(tree) => tree,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(3);
+        }
+    };
+
+    if opts.verbose {
+        eprintln!("Read tree of {} vertices", tree.num_vertices());
+    }
+
+    // Read the pairs
+    let pairs = if !opts.input.is_empty() {
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                let format = Format::from_path(&opts.input);
+                match format::read_pairs_file(&f, format, None) {
+                    Ok(pairs) => pairs,
+                    Err(e) => {
+                        println!("{}", e);
+                        std::process::exit(3);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        match format::read_pairs(io::stdin().lock(), None, None) {
+            Ok(pairs) => pairs,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    };
+
+    if opts.verbose {
+        eprintln!("Read {} pairs", pairs.len());
+    }
+
+    let mut fout: Box<dyn Write> = if !opts.output.is_empty() {
+        let path = Path::new(&*opts.output);
+        match File::create(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened output '{}'", path.display());
+                }
+                Box::new(f)
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Writing to stdout");
+        }
+        Box::new(io::stdout())
+    };
+
+    let query = Bottleneck::new(&tree);
+    for &(u, v) in &pairs {
+        let (a, b) = match (tree.find(&u), tree.find(&v)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                eprintln!(
+                    "WARNING: pair ({}, {}) - ({}, {}) not in the tree",
+                    u.x, u.y, v.x, v.y
+                );
+                exhaust((u, v), None, &mut fout);
+                continue;
+            }
+        };
+        // Copies of a point have no path between them, so no bottleneck
+        let answer = match query.bottleneck(a, b) {
+            Some(k) => {
+                let e = &tree.edges()[k];
+                (e.u, e.v, e.len(), query.distance(a, b))
+            }
+            None => (u, u, 0.0, 0.0),
+        };
+        exhaust((u, v), Some(answer), &mut fout);
+    }
+
+    if opts.verbose {
+        eprintln!("Done");
+    }
+}
+
+/// Get command line options.
+fn get_options() -> Option<Options> {
+    let mut options = Options {
+        print_help: false,
+        verbose: false,
+        tree: Rc::new("".to_string()),
+        input: Rc::new("".to_string()),
+        output: Rc::new("".to_string()),
+    };
+
+    let matches = App::new("MST Query")
+        .arg(
+            Arg::with_name("help")
+                .short("h")
+                .long("help")
+                .help("Print usage and exit"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Enable debug messages"),
+        )
+        .arg(
+            Arg::with_name("tree")
+                .short("t")
+                .long("tree")
+                .takes_value(true)
+                .required_unless("help")
+                .help("Name of tree file"),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("Name of pairs file"),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Name of output file"),
+        )
+        .get_matches();
+
+    if matches.is_present("help") {
+        options.print_help = true;
+    }
+
+    if matches.is_present("verbose") {
+        options.verbose = true;
+    }
+
+    if let Some(s) = matches.value_of("tree") {
+        *Rc::make_mut(&mut options.tree) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("input-file") {
+        *Rc::make_mut(&mut options.input) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("output-file") {
+        *Rc::make_mut(&mut options.output) = s.to_string();
+    }
+
+    Some(options)
+}
+
+/// Print a usage message.
+fn print_help() {
+    println!(
+        "\nMST Query\n\n\
+\tRead a minimum spanning tree and pairs of its vertices (x0, y0, x1, y1\n\
+\trecords).  For each pair write the pair, the longest edge on the tree\n\
+\tpath between them (the bottleneck), its length, and the length of the\n\
+\tpath.  Every pair has a row, in order.  A pair of copies of a point has\n\
+\tthe point as its bottleneck, with length 0; a pair with a point not in\n\
+\tthe tree has NA in place of the answers, with a warning.  A malformed\n\
+\tCSV pair is an error, though the first line may be a header.\n\
+\tThe tree and pairs may also be JSON, GeoJSON, WKT, GraphML or binary.\n\n\
+USAGE\n\n\
+\tquery -h\n\
+\tquery -t tree.csv -i pairs.csv -o answers.csv\n\
+\tquery -v -t tree.csv < pairs.csv > answers.csv\n\n\
+OPTIONS\n\n\
+\t-h,--help                 Print usage an exit\n\
+\t-v,--verbose              Enable debug messages (to stderr)\n\
+\t-t,--tree FILENAME        Tree file name (Required)\n\
+\t-i,--input FILENAME       Pairs file name (Default: stdin)\n\
+\t-o,--output FILENAME      Output file name (Default: stdout)\n\
+    "
+    );
+}
+
+/// Print options (to stderr).
+fn print_options(opts: &Options) {
+    eprintln!("Options");
+    eprintln!("  print_help : {}", opts.print_help);
+    eprintln!("  verbose    : {}", opts.verbose);
+    eprintln!("  tree       : {}", opts.tree);
+    eprintln!("  input      : {}", opts.input);
+    eprintln!("  output     : {}", opts.output);
+}
+
+/// Print a pair and its answer, the ends of the bottleneck edge, the
+/// bottleneck length and the path length, or NA for no answer, to a Writer.
+fn exhaust<W>(
+    pair: (Vertex, Vertex),
+    answer: Option<(Vertex, Vertex, f32, f32)>,
+    writer: &mut W,
+) where
+    W: Write,
+{
+    let (p, q) = pair;
+    let answer = match answer {
+        Some((u, v, length, distance)) => [
+            u.x.to_string(),
+            u.y.to_string(),
+            v.x.to_string(),
+            v.y.to_string(),
+            length.to_string(),
+            distance.to_string(),
+        ]
+        .join(FSEP),
+        None => ["NA"; 6].join(FSEP),
+    };
+    if let Err(e) = writeln!(
+        writer,
+        "{}{}{}{}{}{}{}{}{}",
+        p.x, FSEP, p.y, FSEP, q.x, FSEP, q.y, FSEP, answer
+    ) {
+        eprintln!("query::exhaust: {}", e)
+    }
+}
@@ -0,0 +1,262 @@
+//! MST Stats
+//!
+//! Report summary statistics of a minimum spanning tree.
+//!
+//! Read a tree and write its total length, the distribution of its edge
+//! lengths and vertex degrees, its leaf count, diameter and centre, as text
+//! or as JSON.
+//!
+//! stats -i tree.csv -o stats.txt
+//! stats -j -b 20 < tree.csv > stats.json
+
+use clap::{App, Arg};
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+
+use mst::{self, format, format::Format, stats, tree::MstTree};
+
+/// Stats options
+#[derive(Clone)]
+struct Options {
+    /// Print usage
+    print_help: bool,
+    /// Print extra messages
+    verbose: bool,
+    /// Write JSON rather than text
+    json: bool,
+    /// Number of edge length histogram bins
+    bins: usize,
+    /// The input (tree) file name
+    input: Rc<String>,
+    /// The output file name
+    output: Rc<String>,
+}
+
+/// Read command line options.
+/// Read a tree from a file or stdin, in any format.
+/// Find its statistics.
+/// Write them to a file or stdout as text or JSON.
+fn main() {
+    let opts = match get_options() {
+        Some(opts) => opts,
+        None => std::process::exit(1),
+    };
+
+    if opts.print_help {
+        print_help();
+        std::process::exit(0);
+    }
+
+    if opts.verbose {
+        eprintln!("MST Stats");
+        print_options(&opts);
+    }
+
+    // Read the tree
+    let edges = if !opts.input.is_empty() {
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                let format = Format::from_path(&opts.input);
+                match format::read_edges_file(&f, format, None) {
+                    Ok(edges) => edges,
+                    Err(e) => {
+                        println!("{}", e);
+                        std::process::exit(3);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        match format::read_edges(io::stdin().lock(), None, None) {
+            Ok(edges) => edges,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    };
+    let tree = match MstTree::from_edges(&edges) {
+        Ok(tree) => tree,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(3);
+        }
+    };
+
+    if opts.verbose {
+        eprintln!("Read tree of {} vertices", tree.num_vertices());
+    }
+
+    let mut fout: Box<dyn Write> = if !opts.output.is_empty() {
+        let path = Path::new(&*opts.output);
+        match File::create(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened output '{}'", path.display());
+                }
+                Box::new(f)
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Writing to stdout");
+        }
+        Box::new(io::stdout())
+    };
+
+    let stats = stats::tree_stats(&tree, opts.bins);
+    let result = if opts.json {
+        writeln!(fout, "{}", stats.to_json())
+    } else {
+        write!(fout, "{}", stats)
+    };
+    if let Err(e) = result {
+        eprintln!("stats::exhaust: {}", e);
+    }
+
+    if opts.verbose {
+        eprintln!("Done");
+    }
+}
+
+/// Get command line options.
+fn get_options() -> Option<Options> {
+    let mut options = Options {
+        print_help: false,
+        verbose: false,
+        json: false,
+        bins: stats::DEFAULT_BINS,
+        input: Rc::new("".to_string()),
+        output: Rc::new("".to_string()),
+    };
+
+    let matches = App::new("MST Stats")
+        .arg(
+            Arg::with_name("help")
+                .short("h")
+                .long("help")
+                .help("Print usage and exit"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Enable debug messages"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .short("j")
+                .long("json")
+                .help("Write JSON"),
+        )
+        .arg(
+            Arg::with_name("bins")
+                .short("b")
+                .long("bins")
+                .takes_value(true)
+                .help("Number of edge length histogram bins"),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("Name of tree file"),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Name of output file"),
+        )
+        .get_matches();
+
+    if matches.is_present("help") {
+        options.print_help = true;
+    }
+
+    if matches.is_present("verbose") {
+        options.verbose = true;
+    }
+
+    if matches.is_present("json") {
+        options.json = true;
+    }
+
+    if let Some(s) = matches.value_of("bins") {
+        match s.parse::<usize>() {
+            Ok(value) if value > 0 => options.bins = value,
+            _ => {
+                eprintln!("ERROR: invalid number of bins '{}'", s);
+                return None;
+            }
+        }
+    }
+
+    if let Some(s) = matches.value_of("input-file") {
+        *Rc::make_mut(&mut options.input) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("output-file") {
+        *Rc::make_mut(&mut options.output) = s.to_string();
+    }
+
+    Some(options)
+}
+
+/// Print a usage message.
+fn print_help() {
+    println!(
+        "\nMST Stats\n\n\
+\tRead a minimum spanning tree (x0, y0, x1, y1 records) and report its\n\
+\tvertex and edge counts, total length, mean, median, shortest and\n\
+\tlongest edge lengths, a histogram of edge lengths, the number of\n\
+\tvertices of each degree, the number of leaves, the diameter in edges\n\
+\tand in length, and the centre vertex of the diameter.  The tree may\n\
+\talso be JSON, GeoJSON, WKT, GraphML or binary.  Vertices are told\n\
+\tapart by their coordinates, so repeated points count as one vertex.\n\n\
+USAGE\n\n\
+\tstats -h\n\
+\tstats -i tree.csv -o stats.txt\n\
+\tstats -v -j -b 20 < tree.csv > stats.json\n\n\
+OPTIONS\n\n\
+\t-h,--help                 Print usage an exit\n\
+\t-v,--verbose              Enable debug messages (to stderr)\n\
+\t-j,--json                 Write JSON rather than text\n\
+\t-b,--bins N               Edge length histogram bins (Default: 10)\n\
+\t-i,--input FILENAME       Tree file name (Default: stdin)\n\
+\t-o,--output FILENAME      Output file name (Default: stdout)\n\
+    "
+    );
+}
+
+/// Print options (to stderr).
+fn print_options(opts: &Options) {
+    eprintln!("Options");
+    eprintln!("  print_help : {}", opts.print_help);
+    eprintln!("  verbose    : {}", opts.verbose);
+    eprintln!("  json       : {}", opts.json);
+    eprintln!("  bins       : {}", opts.bins);
+    eprintln!("  input      : {}", opts.input);
+    eprintln!("  output     : {}", opts.output);
+}
@@ -0,0 +1,226 @@
+//! MST Verify
+//!
+//! Check that a set of edges is a minimum spanning tree of a set of points.
+//!
+//! Read a set of points and a set of edges, such as a tree written by
+//! another tool, and check that the edges join the points without a cycle,
+//! reach every point, and that no other edge is shorter than the tree path
+//! between its ends.  Print the first violation and exit non-zero.
+//!
+//! verify -p vertices.csv -i edges.csv
+//! verify -p vertices.csv < edges.csv
+
+use clap::{App, Arg};
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use mst::{format, format::Format, verify};
+
+/// Verification options
+#[derive(Clone)]
+struct Options {
+    /// Print usage
+    print_help: bool,
+    /// Print extra messages
+    verbose: bool,
+    /// The points file name
+    points: Rc<String>,
+    /// The edges file name
+    input: Rc<String>,
+}
+
+/// Exit status when the edges are not a minimum spanning tree
+const EXIT_VIOLATION: i32 = 4;
+
+/// Read command line options.
+/// Read a set of points and a set of edges from files or stdin, in any
+/// format.
+/// Check the edges are a minimum spanning tree of the points.
+/// Print the first violation, if any.
+fn main() {
+    let opts = match get_options() {
+        Some(opts) => opts,
+        None => std::process::exit(1),
+    };
+
+    if opts.print_help {
+        print_help();
+        std::process::exit(0);
+    }
+
+    if opts.verbose {
+        eprintln!("MST Verify");
+        print_options(&opts);
+    }
+
+    // Read the points
+    let path = Path::new(&*opts.points);
+    let points = match File::open(path) {
+        Ok(f) => {
+            if opts.verbose {
+                eprintln!("Opened points '{}'", path.display());
+            }
+            let format = Format::from_path(&opts.points);
+            match format::read_points_file(&f, format, None) {
+                Ok(table) => table.points,
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(3);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to open '{}': {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    // Read the edges
+    let edges = if !opts.input.is_empty() {
+        let path = Path::new(&*opts.input);
+        match File::open(path) {
+            Ok(f) => {
+                if opts.verbose {
+                    eprintln!("Opened input '{}'", path.display());
+                }
+                let format = Format::from_path(&opts.input);
+                match format::read_edges_file(&f, format, None) {
+                    Ok(edges) => edges,
+                    Err(e) => {
+                        println!("{}", e);
+                        std::process::exit(3);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to open '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        if opts.verbose {
+            eprintln!("Reading from stdin");
+        }
+        match format::read_edges(io::stdin().lock(), None, None) {
+            Ok(edges) => edges,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(3);
+            }
+        }
+    };
+
+    if opts.verbose {
+        eprintln!("Read {} points and {} edges", points.len(), edges.len());
+    }
+
+    match verify::verify(&points, &edges) {
+        Ok(()) => {
+            let length: f32 = edges.iter().map(|e| e.len()).sum();
+            println!("OK: minimum spanning tree of length {}", length);
+        }
+        Err(violation) => {
+            println!("FAIL: {}", violation);
+            std::process::exit(EXIT_VIOLATION);
+        }
+    }
+
+    if opts.verbose {
+        eprintln!("Done");
+    }
+}
+
+/// Get command line options.
+fn get_options() -> Option<Options> {
+    let mut options = Options {
+        print_help: false,
+        verbose: false,
+        points: Rc::new("".to_string()),
+        input: Rc::new("".to_string()),
+    };
+
+    let matches = App::new("MST Verify")
+        .arg(
+            Arg::with_name("help")
+                .short("h")
+                .long("help")
+                .help("Print usage and exit"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Enable debug messages"),
+        )
+        .arg(
+            Arg::with_name("points")
+                .short("p")
+                .long("points")
+                .takes_value(true)
+                .required_unless("help")
+                .help("Name of points file"),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .short("i")
+                .long("input")
+                .takes_value(true)
+                .help("Name of edges file"),
+        )
+        .get_matches();
+
+    if matches.is_present("help") {
+        options.print_help = true;
+    }
+
+    if matches.is_present("verbose") {
+        options.verbose = true;
+    }
+
+    if let Some(s) = matches.value_of("points") {
+        *Rc::make_mut(&mut options.points) = s.to_string();
+    }
+
+    if let Some(s) = matches.value_of("input-file") {
+        *Rc::make_mut(&mut options.input) = s.to_string();
+    }
+
+    Some(options)
+}
+
+/// Print a usage message.
+fn print_help() {
+    println!(
+        "\nMST Verify\n\n\
+\tRead a set of points and a set of edges, and check that the edges are\n\
+\ta minimum spanning tree of the points: every edge joins two of the\n\
+\tpoints, there is no cycle, every point is reached, and no other edge\n\
+\tis shorter than the tree path between its ends.  Print the first\n\
+\tviolation and exit with status {}.  Repeated points count as one\n\
+\tvertex.  Either file may be in any format 'convert' reads, from its\n\
+\tname or contents.\n\n\
+USAGE\n\n\
+\tverify -h\n\
+\tverify -p data.csv -i tree.csv\n\
+\tverify -v -p data.csv < tree.csv\n\n\
+OPTIONS\n\n\
+\t-h,--help                 Print usage an exit\n\
+\t-v,--verbose              Enable debug messages (to stderr)\n\
+\t-p,--points FILENAME      Points file name (Required)\n\
+\t-i,--input FILENAME       Edges file name (Default: stdin)\n\
+    ",
+        EXIT_VIOLATION
+    );
+}
+
+/// Print options (to stderr).
+fn print_options(opts: &Options) {
+    eprintln!("Options");
+    eprintln!("  print_help : {}", opts.print_help);
+    eprintln!("  verbose    : {}", opts.verbose);
+    eprintln!("  points     : {}", opts.points);
+    eprintln!("  input      : {}", opts.input);
+}
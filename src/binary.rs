@@ -0,0 +1,342 @@
+//! Binary Files
+//!
+//! A compact, versioned, little-endian format for very large sets of points
+//! and trees, read without parsing text and, from a file, through a memory
+//! map.  A file is a 32 byte header then the data:
+//!
+//! - bytes 0-3: the magic number `MSTB`
+//! - bytes 4-5: the format version (`u16`, now 1)
+//! - byte 6: the number of coordinates of each point (at least 2)
+//! - byte 7: the coordinate type (1 `i32`, 2 `f32`, 3 `f64`)
+//! - bytes 8-15: the number of points (`u64`)
+//! - bytes 16-23: the number of edges (`u64`), 0 for a set of points
+//! - bytes 24-31: reserved, zero
+//!
+//! The points follow, their coordinates packed, then each edge as the pair
+//! of indices (`u32`) of its end points.  Points are written as `i32` pairs;
+//! when read, coordinates after the first two are ignored and floating
+//! point coordinates are rounded to the nearest integer.
+
+use crate::{edge_indices, pair_edges, Edge, Vertex};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Magic number at the start of a binary file
+pub const MAGIC: &[u8; 4] = b"MSTB";
+/// Version of the format written
+pub const VERSION: u16 = 1;
+/// Size of the header in bytes
+pub const HEADER_SIZE: usize = 32;
+
+/// Types of coordinate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coordinate {
+    I32 = 1,
+    F32 = 2,
+    F64 = 3,
+}
+
+impl Coordinate {
+    /// Get the coordinate type with a type code.
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Coordinate::I32),
+            2 => Some(Coordinate::F32),
+            3 => Some(Coordinate::F64),
+            _ => None,
+        }
+    }
+
+    /// Get the size of a coordinate in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Coordinate::I32 | Coordinate::F32 => 4,
+            Coordinate::F64 => 8,
+        }
+    }
+
+    /// Read a coordinate, rounded to the nearest integer.
+    fn read(&self, bytes: &[u8]) -> i32 {
+        match self {
+            Coordinate::I32 => {
+                i32::from_le_bytes(bytes[..4].try_into().unwrap())
+            }
+            Coordinate::F32 => {
+                f32::from_le_bytes(bytes[..4].try_into().unwrap()).round()
+                    as i32
+            }
+            Coordinate::F64 => {
+                f64::from_le_bytes(bytes[..8].try_into().unwrap()).round()
+                    as i32
+            }
+        }
+    }
+}
+
+/// A binary file header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Format version
+    pub version: u16,
+    /// Number of coordinates of each point
+    pub dimensions: u8,
+    /// Type of the coordinates
+    pub coordinate: Coordinate,
+    /// Number of points
+    pub points: u64,
+    /// Number of edges
+    pub edges: u64,
+}
+
+/// Make an invalid data error.
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Header {
+    /// Create a header for points and edges written by this version.
+    pub fn new(points: usize, edges: usize) -> Self {
+        Self {
+            version: VERSION,
+            dimensions: 2,
+            coordinate: Coordinate::I32,
+            points: points as u64,
+            edges: edges as u64,
+        }
+    }
+
+    /// Read and check the header of a binary file.
+    pub fn read(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_SIZE || !is_binary(bytes) {
+            return Err(invalid("not a binary points file"));
+        }
+        let u64_at =
+            |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        let header = Self {
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            dimensions: bytes[6],
+            coordinate: Coordinate::from_code(bytes[7])
+                .ok_or_else(|| invalid("unknown binary coordinate type"))?,
+            points: u64_at(8),
+            edges: u64_at(16),
+        };
+        if header.version == 0 || header.version > VERSION {
+            return Err(invalid("unsupported binary format version"));
+        }
+        if header.dimensions < 2 {
+            return Err(invalid("binary points have fewer than 2 coordinates"));
+        }
+        match header.size() {
+            Some(size) if size <= bytes.len() => Ok(header),
+            _ => Err(invalid("binary file is truncated")),
+        }
+    }
+
+    /// Get the size in bytes of a point.
+    fn point_size(&self) -> usize {
+        self.dimensions as usize * self.coordinate.size()
+    }
+
+    /// Get the size in bytes of the header and data, if it fits in memory.
+    fn size(&self) -> Option<usize> {
+        let points = usize::try_from(self.points).ok()?;
+        let edges = usize::try_from(self.edges).ok()?;
+        points
+            .checked_mul(self.point_size())?
+            .checked_add(edges.checked_mul(8)?)?
+            .checked_add(HEADER_SIZE)
+    }
+
+    /// Write the header.
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(MAGIC);
+        buffer.extend_from_slice(&self.version.to_le_bytes());
+        buffer.push(self.dimensions);
+        buffer.push(self.coordinate as u8);
+        buffer.extend_from_slice(&self.points.to_le_bytes());
+        buffer.extend_from_slice(&self.edges.to_le_bytes());
+        buffer.extend_from_slice(&[0; 8]);
+    }
+}
+
+/// Check whether bytes start with the binary magic number.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Map a file into memory to read it.
+pub fn map(file: &File) -> io::Result<Mmap> {
+    // SAFETY: the map is only read, and every read is checked against the
+    // header and the size of the map.  The file must not be truncated or
+    // written while it is mapped; the tools never write a file they read.
+    unsafe { Mmap::map(file) }
+}
+
+/// Write points as a binary file.
+pub fn write_points<W>(writer: &mut W, points: &[Vertex]) -> io::Result<()>
+where
+    W: Write,
+{
+    write_indexed_tree(writer, points, &[])
+}
+
+/// Write a set of points and edges between them as a binary file.  The
+/// edges are matched to the points by their coordinates, see
+/// `write_indexed_tree` for edges whose end indices are known.
+pub fn write_tree<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    edges: &[Edge],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let indices = edge_indices(points, edges)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    write_indexed_tree(writer, points, &indices)
+}
+
+/// Write a set of points and edges between them, given by the indices of
+/// their end points, as a binary file.
+pub fn write_indexed_tree<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    indices: &[(usize, usize)],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+    if u32::try_from(points.len()).is_err() {
+        return Err(invalid("too many points for binary edge indices"));
+    }
+    if indices.iter().any(|&(u, v)| u.max(v) >= points.len()) {
+        return Err(invalid("edge index out of range"));
+    }
+    let header = Header::new(points.len(), indices.len());
+    let mut buffer = Vec::with_capacity(header.size().unwrap_or(0));
+    header.write(&mut buffer);
+    for p in points {
+        buffer.extend_from_slice(&p.x.to_le_bytes());
+        buffer.extend_from_slice(&p.y.to_le_bytes());
+    }
+    for &(u, v) in indices {
+        buffer.extend_from_slice(&(u as u32).to_le_bytes());
+        buffer.extend_from_slice(&(v as u32).to_le_bytes());
+    }
+    writer.write_all(&buffer)
+}
+
+/// Read the points of a binary file.
+pub fn read_points(bytes: &[u8]) -> io::Result<Vec<Vertex>> {
+    let header = Header::read(bytes)?;
+    let size = header.point_size();
+    let width = header.coordinate.size();
+    Ok(bytes[HEADER_SIZE..]
+        .chunks_exact(size)
+        .take(header.points as usize)
+        .map(|p| {
+            let c = header.coordinate;
+            Vertex::new(c.read(p), c.read(&p[width..]))
+        })
+        .collect())
+}
+
+/// Read the end points of every edge of a binary file, copies of a point
+/// included.
+pub fn read_pairs(bytes: &[u8]) -> io::Result<Vec<(Vertex, Vertex)>> {
+    let header = Header::read(bytes)?;
+    let points = read_points(bytes)?;
+    let start = HEADER_SIZE + points.len() * header.point_size();
+    let mut pairs = Vec::with_capacity(header.edges as usize);
+    for pair in bytes[start..].chunks_exact(8).take(header.edges as usize) {
+        let u = u32::from_le_bytes(pair[..4].try_into().unwrap()) as usize;
+        let v = u32::from_le_bytes(pair[4..].try_into().unwrap()) as usize;
+        match (points.get(u), points.get(v)) {
+            (Some(&u), Some(&v)) => pairs.push((u, v)),
+            _ => return Err(invalid("binary edge index out of range")),
+        }
+    }
+    Ok(pairs)
+}
+
+/// Read the edges of a binary file.  Edges between copies of a point are
+/// skipped.
+pub fn read_edges(bytes: &[u8]) -> io::Result<Vec<Edge>> {
+    read_pairs(bytes).map(pair_edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, -4), Vertex::new(3, -4)];
+        let edges = vec![Edge::new(0, 0, 3, -4), Edge::new(3, -4, 3, 0)];
+        let mut buffer = Vec::new();
+        write_points(&mut buffer, &points).unwrap();
+        assert_eq!(buffer.len(), HEADER_SIZE + 3 * 8);
+        assert_eq!(&buffer[..8], b"MSTB\x01\x00\x02\x01");
+        let again = read_points(&buffer).unwrap();
+        assert_eq!((again.len(), again[1].x, again[1].y), (3, 3, -4));
+        assert!(read_edges(&buffer).unwrap().is_empty());
+
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, -4), Vertex::new(3, 0)];
+        let mut buffer = Vec::new();
+        write_tree(&mut buffer, &points, &edges).unwrap();
+        let header = Header::read(&buffer).unwrap();
+        assert_eq!(header, Header::new(3, 2));
+        let again = read_edges(&buffer).unwrap();
+        let lengths: Vec<f32> = again.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [5.0, 4.0]);
+
+        // Known indices keep copies of a point apart
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, -4), Vertex::new(3, -4)];
+        let mut buffer = Vec::new();
+        write_indexed_tree(&mut buffer, &points, &[(1, 0), (2, 0)]).unwrap();
+        assert_eq!(
+            &buffer[HEADER_SIZE + 24..],
+            [1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        let mut buffer = Vec::new();
+        assert!(write_indexed_tree(&mut buffer, &points, &[(3, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_read_other_types() {
+        // Three dimensional f64 points
+        let mut buffer = Vec::new();
+        let mut header = Header::new(2, 1);
+        header.dimensions = 3;
+        header.coordinate = Coordinate::F64;
+        header.write(&mut buffer);
+        for c in [1.4f64, -2.6, 9.0, 4.0, 1.0, 0.0] {
+            buffer.extend_from_slice(&c.to_le_bytes());
+        }
+        buffer.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        let points = read_points(&buffer).unwrap();
+        assert_eq!((points[0].x, points[0].y), (1, -3));
+        let edges = read_edges(&buffer).unwrap();
+        assert_eq!(edges[0].len(), 5.0);
+
+        // Errors
+        assert!(read_points(b"1\t2\n").is_err());
+        assert!(read_points(&buffer[..buffer.len() - 1]).is_err());
+        let n = buffer.len();
+        buffer[n - 8] = 5;
+        assert!(read_edges(&buffer).is_err());
+        buffer[8] = 3;
+        assert!(read_points(&buffer).is_err());
+        let mut bad = buffer.clone();
+        bad[4] = 2;
+        assert!(read_points(&bad).is_err());
+        let mut bad = buffer.clone();
+        bad[7] = 9;
+        assert!(read_points(&bad).is_err());
+    }
+}
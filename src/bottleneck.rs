@@ -0,0 +1,181 @@
+//! Bottleneck
+//!
+//! The path between two points in the minimum spanning tree is a minimax
+//! path: no other path between them has a shorter longest edge.  So the
+//! longest tree edge on the path, the bottleneck, is the longest link any
+//! route between the points must use.
+//!
+//! Queries use binary lifting over the rooted tree: each vertex keeps its
+//! 2^j-th ancestor and the longest edge on the way to it, so the lowest
+//! common ancestor and the bottleneck of any path are found in O(log n)
+//! after O(n log n) preparation.
+
+use crate::tree::MstTree;
+
+/// Bottleneck and path length queries over a tree
+pub struct Bottleneck<'a> {
+    /// The tree, rooted
+    tree: &'a MstTree,
+    /// up[j][i] is the 2^j-th ancestor of i, the root above the root
+    up: Vec<Vec<usize>>,
+    /// longest[j][i] is the longest edge on the way to up[j][i]
+    longest: Vec<Vec<Option<usize>>>,
+    /// Length of the path from the root to each vertex
+    height: Vec<f32>,
+}
+
+impl<'a> Bottleneck<'a> {
+    /// Prepare to answer queries over a tree.
+    pub fn new(tree: &'a MstTree) -> Self {
+        let n = tree.num_vertices();
+        let levels = (usize::BITS - n.leading_zeros()).max(1) as usize;
+        let mut up = Vec::with_capacity(levels);
+        let mut longest = Vec::with_capacity(levels);
+        up.push((0..n).map(|i| tree.parent(i).unwrap_or(i)).collect());
+        longest.push((0..n).map(|i| tree.parent_edge(i)).collect());
+        for j in 1..levels {
+            let (prev_up, prev_longest): (&Vec<usize>, &Vec<Option<usize>>) =
+                (&up[j - 1], &longest[j - 1]);
+            let mut next_up = vec![0; n];
+            let mut next_longest = vec![None; n];
+            for i in 0..n {
+                let mid = prev_up[i];
+                next_up[i] = prev_up[mid];
+                next_longest[i] =
+                    longer(tree, prev_longest[i], prev_longest[mid]);
+            }
+            up.push(next_up);
+            longest.push(next_longest);
+        }
+
+        let mut height = vec![0.0; n];
+        for v in tree.bfs() {
+            if let (Some(p), Some(k)) = (tree.parent(v), tree.parent_edge(v)) {
+                height[v] = height[p] + tree.edges()[k].len();
+            }
+        }
+
+        Self {
+            tree,
+            up,
+            longest,
+            height,
+        }
+    }
+
+    /// Find the lowest common ancestor of two vertices, and the longest edge
+    /// on the path between them.
+    fn climb(&self, a: usize, b: usize) -> (usize, Option<usize>) {
+        let tree = self.tree;
+        let (mut a, mut b) = (a, b);
+        if tree.depth(a) < tree.depth(b) {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let mut best = None;
+        let mut rise = tree.depth(a) - tree.depth(b);
+        let mut j = 0;
+        while rise > 0 {
+            if rise & 1 == 1 {
+                best = longer(tree, best, self.longest[j][a]);
+                a = self.up[j][a];
+            }
+            rise >>= 1;
+            j += 1;
+        }
+        if a == b {
+            return (a, best);
+        }
+        for j in (0..self.up.len()).rev() {
+            if self.up[j][a] != self.up[j][b] {
+                best = longer(tree, best, self.longest[j][a]);
+                best = longer(tree, best, self.longest[j][b]);
+                a = self.up[j][a];
+                b = self.up[j][b];
+            }
+        }
+        best = longer(tree, best, self.longest[0][a]);
+        best = longer(tree, best, self.longest[0][b]);
+        (self.up[0][a], best)
+    }
+
+    /// Find the lowest common ancestor of two vertices.
+    pub fn lca(&self, a: usize, b: usize) -> usize {
+        self.climb(a, b).0
+    }
+
+    /// Find the index of the longest edge on the tree path between two
+    /// vertices, None if they are the same vertex.
+    pub fn bottleneck(&self, a: usize, b: usize) -> Option<usize> {
+        self.climb(a, b).1
+    }
+
+    /// Find the length of the tree path between two vertices.
+    pub fn distance(&self, a: usize, b: usize) -> f32 {
+        let top = self.lca(a, b);
+        self.height[a] + self.height[b] - 2.0 * self.height[top]
+    }
+}
+
+/// Pick the longer of two tree edges by index.
+fn longer(tree: &MstTree, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (None, _) => b,
+        (_, None) => a,
+        (Some(i), Some(j)) => {
+            let edges = tree.edges();
+            if edges[j].len() > edges[i].len() {
+                b
+            } else {
+                a
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, Vertex};
+
+    #[test]
+    fn test_bottleneck_small() {
+        // 0 - 1 - 2 with 1 - 3 - 4 hanging below
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(13, 0),
+            Vertex::new(10, 20),
+            Vertex::new(15, 20),
+        ];
+        let tree = MstTree::from_points(&points).unwrap();
+        let query = Bottleneck::new(&tree);
+        let length = |k: Option<usize>| k.map(|k| tree.edges()[k].len());
+        assert_eq!(length(query.bottleneck(0, 2)), Some(10.0));
+        assert_eq!(length(query.bottleneck(2, 4)), Some(20.0));
+        assert_eq!(length(query.bottleneck(3, 4)), Some(5.0));
+        assert_eq!(query.bottleneck(3, 3), None);
+        assert_eq!(query.lca(2, 4), 1);
+        assert_eq!(query.distance(0, 4), 35.0);
+        assert_eq!(query.distance(4, 4), 0.0);
+    }
+
+    #[test]
+    fn test_bottleneck_path() {
+        let points = generate(80, 1.0, 0, 0, 200, 200).unwrap();
+        let mut tree = MstTree::from_points(&points).unwrap();
+        tree.root_at(17);
+        let query = Bottleneck::new(&tree);
+        for (a, b) in [(0, 79), (5, 40), (17, 3), (60, 61)] {
+            // Compare with walking the path
+            let path = tree.path_edges(a, b);
+            let lengths: Vec<f32> =
+                path.iter().map(|&k| tree.edges()[k].len()).collect();
+            let max = lengths.iter().cloned().fold(0.0, f32::max);
+            let k = query.bottleneck(a, b).unwrap();
+            assert_eq!(tree.edges()[k].len(), max);
+            let total: f32 = lengths.iter().sum();
+            assert!((query.distance(a, b) - total).abs() < 1e-2);
+            assert_eq!(query.lca(a, b), tree.lca(a, b));
+        }
+    }
+}
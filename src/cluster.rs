@@ -0,0 +1,235 @@
+//! Clustering
+//!
+//! Single-linkage clustering of a set of points by cutting the longest edges
+//! of their minimum spanning tree.  Each connected piece of the remaining
+//! forest is a cluster.
+//!
+//! The full single-linkage hierarchy follows from merging the tree edges in
+//! order of length, giving a SciPy style linkage matrix for dendrograms.
+
+use crate::disjoint_set::DisjointSet;
+use crate::tree::MstTree;
+use crate::{edge_indices, Edge, Vertex};
+
+/// One merge in a single-linkage hierarchy, a row of a linkage matrix.
+///
+/// Clusters 0 to n - 1 are the original points; the cluster formed by the
+/// i-th merge is n + i.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Link {
+    /// The smaller id of the two clusters merged
+    pub a: usize,
+    /// The larger id of the two clusters merged
+    pub b: usize,
+    /// Distance between the clusters (the tree edge length)
+    pub distance: f32,
+    /// Number of points in the merged cluster
+    pub size: usize,
+}
+
+/// Build the single-linkage hierarchy of a set of points from their minimum
+/// spanning tree.  Returns n - 1 merges ordered by distance.
+pub fn linkage(
+    points: &[Vertex],
+    tree: &[Edge],
+) -> Result<Vec<Link>, &'static str> {
+    let tree = MstTree::new(points, tree)?;
+    let edges: Vec<(usize, usize, f32)> = tree
+        .ends()
+        .iter()
+        .zip(tree.edges())
+        .map(|(&(u, v), e)| (u, v, e.len()))
+        .collect();
+    linkage_indexed(points.len(), &edges)
+}
+
+/// Build the single-linkage hierarchy of n vertices from the edges of a tree
+/// given as (u, v, length) with vertex indices.
+pub(crate) fn linkage_indexed(
+    n: usize,
+    edges: &[(usize, usize, f32)],
+) -> Result<Vec<Link>, &'static str> {
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|&a, &b| edges[a].2.total_cmp(&edges[b].2));
+
+    let mut sets = DisjointSet::new(n);
+    // Cluster id of the set with each root
+    let mut id: Vec<usize> = (0..n).collect();
+    let mut links = Vec::with_capacity(edges.len());
+    for i in order {
+        let (u, v, distance) = edges[i];
+        let (ru, rv) = (sets.find(u), sets.find(v));
+        if !sets.union(ru, rv) {
+            return Err("tree edges form a cycle");
+        }
+        let (a, b) = (id[ru].min(id[rv]), id[ru].max(id[rv]));
+        let root = sets.find(u);
+        id[root] = n + links.len();
+        links.push(Link {
+            a,
+            b,
+            distance,
+            size: sets.size(root),
+        });
+    }
+    Ok(links)
+}
+
+/// Cluster points by removing the k longest edges of their minimum spanning
+/// tree, leaving k + 1 clusters.  Returns a cluster label for each point.
+/// Labels are numbered from 0 in the order the clusters first appear in the
+/// list of points.
+pub fn cut_longest(
+    points: &[Vertex],
+    tree: &[Edge],
+    k: usize,
+) -> Result<Vec<usize>, &'static str> {
+    if k > tree.len() {
+        return Err("more edges to cut than in the tree");
+    }
+    let mut order: Vec<usize> = (0..tree.len()).collect();
+    order.sort_by(|&a, &b| tree[b].len().total_cmp(&tree[a].len()));
+    let mut keep = vec![true; tree.len()];
+    for &i in &order[..k] {
+        keep[i] = false;
+    }
+    label(points, tree, |i, _| keep[i])
+}
+
+/// Cluster points by removing all edges of their minimum spanning tree longer
+/// than the given length.  Returns a cluster label for each point.  Labels
+/// are numbered from 0 in the order the clusters first appear in the list of
+/// points.
+pub fn cut_length(
+    points: &[Vertex],
+    tree: &[Edge],
+    max_length: f32,
+) -> Result<Vec<usize>, &'static str> {
+    label(points, tree, |_, e| e.len() <= max_length)
+}
+
+/// Count the clusters in a set of labels.
+pub fn num_clusters(labels: &[usize]) -> usize {
+    labels.iter().max().map_or(0, |&m| m + 1)
+}
+
+/// Label the connected components of the tree edges accepted by keep.
+fn label<F>(
+    points: &[Vertex],
+    tree: &[Edge],
+    keep: F,
+) -> Result<Vec<usize>, &'static str>
+where
+    F: Fn(usize, &Edge) -> bool,
+{
+    let indices = edge_indices(points, tree)?;
+    let mut sets = DisjointSet::new(points.len());
+    for (i, e) in tree.iter().enumerate() {
+        if keep(i, e) {
+            sets.union(indices[i].0, indices[i].1);
+        }
+    }
+    let mut root_label = vec![usize::MAX; points.len()];
+    let mut labels = Vec::with_capacity(points.len());
+    let mut next = 0;
+    for i in 0..points.len() {
+        let root = sets.find(i);
+        if root_label[root] == usize::MAX {
+            root_label[root] = next;
+            next += 1;
+        }
+        labels.push(root_label[root]);
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minimum_spanning_tree;
+
+    /// Two groups of three points far apart
+    fn two_groups() -> Vec<Vertex> {
+        vec![
+            Vertex::new(0, 0),
+            Vertex::new(50, 50),
+            Vertex::new(1, 0),
+            Vertex::new(51, 50),
+            Vertex::new(0, 2),
+            Vertex::new(50, 52),
+        ]
+    }
+
+    #[test]
+    fn test_cut_longest() {
+        let points = two_groups();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let labels = cut_longest(&points, &tree, 1).unwrap();
+        assert_eq!(labels, vec![0, 1, 0, 1, 0, 1]);
+        assert_eq!(num_clusters(&labels), 2);
+        let labels = cut_longest(&points, &tree, 0).unwrap();
+        assert_eq!(num_clusters(&labels), 1);
+        let labels = cut_longest(&points, &tree, 5).unwrap();
+        assert_eq!(labels, vec![0, 1, 2, 3, 4, 5]);
+        assert!(cut_longest(&points, &tree, 6).is_err());
+    }
+
+    #[test]
+    fn test_cut_length() {
+        let points = two_groups();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let labels = cut_length(&points, &tree, 10.0).unwrap();
+        assert_eq!(labels, vec![0, 1, 0, 1, 0, 1]);
+        let labels = cut_length(&points, &tree, 1.5).unwrap();
+        assert_eq!(num_clusters(&labels), 4);
+    }
+
+    #[test]
+    fn test_linkage() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(1, 0),
+            Vertex::new(10, 0),
+            Vertex::new(12, 0),
+        ];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let links = linkage(&points, &tree).unwrap();
+        let expected = vec![
+            Link {
+                a: 0,
+                b: 1,
+                distance: 1.0,
+                size: 2,
+            },
+            Link {
+                a: 2,
+                b: 3,
+                distance: 2.0,
+                size: 2,
+            },
+            Link {
+                a: 4,
+                b: 5,
+                distance: 9.0,
+                size: 4,
+            },
+        ];
+        assert_eq!(links, expected);
+    }
+
+    #[test]
+    fn test_linkage_not_a_tree() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(1, 0), Vertex::new(2, 0)];
+        let cycle = vec![Edge::new(0, 0, 1, 0), Edge::new(1, 0, 0, 0)];
+        assert!(linkage(&points, &cycle).is_err());
+        assert!(linkage(&points, &cycle[..1]).is_err());
+    }
+
+    #[test]
+    fn test_cut_unknown_vertex() {
+        let points = two_groups();
+        let tree = vec![Edge::new(0, 0, 7, 7)];
+        assert!(cut_length(&points, &tree, 1.0).is_err());
+    }
+}
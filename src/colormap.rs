@@ -0,0 +1,171 @@
+//! Colour maps
+//!
+//! Map a value in [0, 1] to a colour for plotting edges by length, and pick
+//! distinct colours for plotting clusters.
+
+use image::Rgb;
+use std::str::FromStr;
+
+/// Names accepted by `Colormap::from_str`.
+pub const COLORMAP_NAMES: &[&str] =
+    &["viridis", "magma", "grayscale", "diverging"];
+
+/// Viridis control points, evenly spaced from 0 to 1
+const VIRIDIS: &[[u8; 3]] = &[
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// Magma control points, evenly spaced from 0 to 1
+const MAGMA: &[[u8; 3]] = &[
+    [0, 0, 4],
+    [28, 16, 68],
+    [79, 18, 123],
+    [129, 37, 129],
+    [181, 54, 122],
+    [229, 80, 100],
+    [251, 135, 97],
+    [254, 194, 135],
+    [252, 253, 191],
+];
+
+/// Grayscale control points, dark to light
+const GRAYSCALE: &[[u8; 3]] = &[[0, 0, 0], [200, 200, 200]];
+
+/// Diverging (blue - white - red) control points
+const DIVERGING: &[[u8; 3]] = &[
+    [33, 102, 172],
+    [103, 169, 207],
+    [209, 229, 240],
+    [247, 247, 247],
+    [253, 219, 199],
+    [239, 138, 98],
+    [178, 24, 43],
+];
+
+/// Distinct colours for categories (e.g. clusters)
+const PALETTE: &[[u8; 3]] = &[
+    [31, 119, 180],
+    [255, 127, 14],
+    [44, 160, 44],
+    [214, 39, 40],
+    [148, 103, 189],
+    [140, 86, 75],
+    [227, 119, 194],
+    [127, 127, 127],
+    [188, 189, 34],
+    [23, 190, 207],
+];
+
+/// Get a distinct colour for category i.  Colours repeat after ten
+/// categories.
+pub fn palette(i: usize) -> Rgb<u8> {
+    Rgb(PALETTE[i % PALETTE.len()])
+}
+
+/// A continuous colour map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Grayscale,
+    Diverging,
+}
+
+impl Colormap {
+    /// Get the colour for a value in [0, 1].  Values outside that range are
+    /// clamped.
+    pub fn color(&self, t: f32) -> Rgb<u8> {
+        let stops = match self {
+            Colormap::Viridis => VIRIDIS,
+            Colormap::Magma => MAGMA,
+            Colormap::Grayscale => GRAYSCALE,
+            Colormap::Diverging => DIVERGING,
+        };
+        interpolate(stops, t)
+    }
+
+    /// Get the colour for a value in [min, max].
+    pub fn scaled(&self, value: f32, min: f32, max: f32) -> Rgb<u8> {
+        if max > min {
+            self.color((value - min) / (max - min))
+        } else {
+            self.color(0.5)
+        }
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "viridis" => Ok(Colormap::Viridis),
+            "magma" => Ok(Colormap::Magma),
+            "grayscale" | "greyscale" | "gray" | "grey" => {
+                Ok(Colormap::Grayscale)
+            }
+            "diverging" => Ok(Colormap::Diverging),
+            _ => Err("unknown colormap"),
+        }
+    }
+}
+
+/// Linearly interpolate between evenly spaced colour stops.
+fn interpolate(stops: &[[u8; 3]], t: f32) -> Rgb<u8> {
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    let s = t * (stops.len() - 1) as f32;
+    let i = (s.floor() as usize).min(stops.len() - 2);
+    let f = s - i as f32;
+    let mut c = [0u8; 3];
+    for (k, item) in c.iter_mut().enumerate() {
+        let a = stops[i][k] as f32;
+        let b = stops[i + 1][k] as f32;
+        *item = (a + (b - a) * f).round() as u8;
+    }
+    Rgb(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colormap_endpoints() {
+        assert_eq!(Colormap::Viridis.color(0.0), Rgb([68, 1, 84]));
+        assert_eq!(Colormap::Viridis.color(1.0), Rgb([253, 231, 37]));
+        assert_eq!(Colormap::Grayscale.color(-1.0), Rgb([0, 0, 0]));
+        assert_eq!(Colormap::Grayscale.color(2.0), Rgb([200, 200, 200]));
+    }
+
+    #[test]
+    fn test_colormap_scaled() {
+        let c = Colormap::Grayscale.scaled(5.0, 0.0, 10.0);
+        assert_eq!(c, Rgb([100, 100, 100]));
+        // Degenerate range picks the middle
+        let c = Colormap::Grayscale.scaled(3.0, 3.0, 3.0);
+        assert_eq!(c, Rgb([100, 100, 100]));
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_ne!(palette(0), palette(1));
+        assert_eq!(palette(3), palette(13));
+    }
+
+    #[test]
+    fn test_colormap_from_str() {
+        for name in COLORMAP_NAMES {
+            assert!(name.parse::<Colormap>().is_ok());
+        }
+        assert_eq!("Magma".parse::<Colormap>(), Ok(Colormap::Magma));
+        assert!("rainbow".parse::<Colormap>().is_err());
+    }
+}
@@ -0,0 +1,1030 @@
+//! CSV Files
+//!
+//! Read and write the files passed between the MST tools.  Records are one
+//! per line.  Files are written with fields separated by `FIELD_SEPARATOR`,
+//! and read with the delimiter given, or else the delimiter of the first
+//! record detected: tab, comma, semicolon or whitespace.  Fields may be
+//! quoted with double quotes.  Blank lines are skipped and lines starting
+//! with '#' are comments.
+//!
+//! Points files may also start with a header row naming the columns, with
+//! no field that is a number.  The points are taken from the columns named
+//! x and y, or the first two, and any other columns are kept with each
+//! point so they can be written out again.
+
+use crate::cluster::Link;
+use crate::obstacles::{Path, Polygon};
+use crate::sensitivity::Replacement;
+use crate::{Edge, Vertex, FIELD_SEPARATOR, RECORD_SEPARATOR};
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// Alias the field separator
+const FSEP: &str = FIELD_SEPARATOR;
+/// Alias the record separator
+const RSEP: &str = RECORD_SEPARATOR;
+
+/// Delimiter names accepted by `Delimiter::from_str`
+pub const DELIMITER_NAMES: &[&str] =
+    &["tab", "comma", "semicolon", "whitespace"];
+
+/// Field delimiters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    Tab,
+    Comma,
+    Semicolon,
+    /// Any run of spaces and tabs
+    Whitespace,
+}
+
+impl FromStr for Delimiter {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tab" | "\t" => Ok(Delimiter::Tab),
+            "comma" | "," => Ok(Delimiter::Comma),
+            "semicolon" | ";" => Ok(Delimiter::Semicolon),
+            "whitespace" | "space" | " " => Ok(Delimiter::Whitespace),
+            _ => Err("unknown delimiter"),
+        }
+    }
+}
+
+impl Delimiter {
+    /// Guess the delimiter of a record: the first of tab, comma or
+    /// semicolon found outside quotes, otherwise whitespace.
+    pub fn detect(line: &str) -> Self {
+        let mut quoted = false;
+        let (mut comma, mut semicolon) = (false, false);
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                '\t' if !quoted => return Delimiter::Tab,
+                ',' if !quoted => comma = true,
+                ';' if !quoted => semicolon = true,
+                _ => (),
+            }
+        }
+        if comma {
+            Delimiter::Comma
+        } else if semicolon {
+            Delimiter::Semicolon
+        } else {
+            Delimiter::Whitespace
+        }
+    }
+
+    /// Is the character a field separator?
+    fn separates(self, c: char) -> bool {
+        match self {
+            Delimiter::Tab => c == '\t',
+            Delimiter::Comma => c == ',',
+            Delimiter::Semicolon => c == ';',
+            Delimiter::Whitespace => c == ' ' || c == '\t',
+        }
+    }
+
+    /// Split a record into fields.  Unquoted fields are trimmed.  In a
+    /// quoted field a doubled quote stands for a quote.
+    pub fn split(self, line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let (mut quoted, mut was_quoted) = (false, false);
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if quoted {
+                if c != '"' {
+                    field.push(c);
+                } else if chars.peek() == Some(&'"') {
+                    field.push(chars.next().unwrap());
+                } else {
+                    quoted = false;
+                }
+            } else if self.separates(c) {
+                if self != Delimiter::Whitespace
+                    || was_quoted
+                    || !field.is_empty()
+                {
+                    fields.push(finish(field, was_quoted));
+                }
+                field = String::new();
+                was_quoted = false;
+            } else if c == '"' && field.trim().is_empty() && !was_quoted {
+                field.clear();
+                quoted = true;
+                was_quoted = true;
+            } else if !was_quoted {
+                field.push(c);
+            }
+        }
+        if self != Delimiter::Whitespace || was_quoted || !field.is_empty() {
+            fields.push(finish(field, was_quoted));
+        }
+        fields
+    }
+}
+
+/// Trim a field unless it was quoted.
+fn finish(field: String, quoted: bool) -> String {
+    if quoted {
+        field
+    } else {
+        field.trim().to_string()
+    }
+}
+
+/// Read the records of a file as their line numbers and fields, skipping
+/// blank lines and comments.  The delimiter is detected from the first
+/// record unless one is given.
+fn records<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> impl Iterator<Item = io::Result<(usize, Vec<String>)>>
+where
+    R: BufRead,
+{
+    let mut delimiter = delimiter;
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(move |(n, line_result)| {
+            let line = match line_result {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let d = *delimiter.get_or_insert_with(|| Delimiter::detect(&line));
+            Some(Ok((n + 1, d.split(&line))))
+        })
+}
+
+/// Check if a record is a header: none of its fields is a number.
+fn is_header(fields: &[String]) -> bool {
+    !fields.iter().any(|f| f.trim().parse::<f64>().is_ok())
+}
+
+/// Quote a field for writing if it holds a delimiter, a quote, or space at
+/// either end.
+pub fn quote(field: &str) -> String {
+    let special = |c: char| matches!(c, '\t' | ',' | ';' | '"');
+    if field.contains(special) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Points read with the other fields of their records
+#[derive(Clone, Default)]
+pub struct PointTable {
+    /// Names of the other columns, if the file has a header
+    pub names: Option<Vec<String>>,
+    /// The points, in order
+    pub points: Vec<Vertex>,
+    /// The other fields of each point's record, in column order
+    pub extra: Vec<Vec<String>>,
+    /// Number of records skipped for not holding a point
+    pub skipped: usize,
+}
+
+impl From<Vec<Vertex>> for PointTable {
+    fn from(points: Vec<Vertex>) -> Self {
+        Self {
+            names: None,
+            extra: vec![Vec::new(); points.len()],
+            points,
+            skipped: 0,
+        }
+    }
+}
+
+/// Read points from x, y records with any other fields, and an optional
+/// header row.  The delimiter is detected from the first record unless one
+/// is given.  A first record with no numbers in it is a header: the points
+/// are in the columns named x and y, or the first two columns.  Records
+/// without a point, a malformed first record included, are skipped, and
+/// counted so the tools can warn of them.
+pub fn read_point_table<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<PointTable>
+where
+    R: BufRead,
+{
+    let mut table = PointTable::default();
+    let (mut x_col, mut y_col) = (0, 1);
+    let mut first = true;
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let coordinate =
+            |i: usize| fields.get(i).and_then(|s| s.trim().parse::<i32>().ok());
+        if first {
+            first = false;
+            if is_header(&fields) {
+                let column = |name: &str| {
+                    fields
+                        .iter()
+                        .position(|f| f.trim().eq_ignore_ascii_case(name))
+                };
+                if let (Some(x), Some(y)) = (column("x"), column("y")) {
+                    (x_col, y_col) = (x, y);
+                }
+                table.names = Some(others(&fields, x_col, y_col));
+                continue;
+            }
+        }
+        if let (Some(x), Some(y)) = (coordinate(x_col), coordinate(y_col)) {
+            table.points.push(Vertex::new(x, y));
+            table.extra.push(others(&fields, x_col, y_col));
+        } else {
+            table.skipped += 1;
+        }
+    }
+
+    Ok(table)
+}
+
+/// Get the fields of a record other than the x and y columns.
+fn others(fields: &[String], x_col: usize, y_col: usize) -> Vec<String> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != x_col && i != y_col)
+        .map(|(_, f)| f.clone())
+        .collect()
+}
+
+/// Read points from x, y records.  Malformed records are skipped.
+pub fn read_points<R>(reader: R) -> io::Result<Vec<Vertex>>
+where
+    R: BufRead,
+{
+    Ok(read_point_table(reader, None)?.points)
+}
+
+/// Write the rows of a point table as x, y records, with a label after the
+/// point if labels are given (-1 for noise), then the other fields.  A
+/// header row is written first if the table has one.
+pub fn write_point_table<W>(
+    writer: &mut W,
+    table: &PointTable,
+    rows: &[usize],
+    labels: Option<&[Option<usize>]>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    if let Some(names) = &table.names {
+        write!(writer, "x{}y", FSEP)?;
+        if labels.is_some() {
+            write!(writer, "{}label", FSEP)?;
+        }
+        for name in names {
+            write!(writer, "{}{}", FSEP, quote(name))?;
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    for (k, &r) in rows.iter().enumerate() {
+        let p = table.points[r];
+        write!(writer, "{}{}{}", p.x, FSEP, p.y)?;
+        if let Some(labels) = labels {
+            match labels[k] {
+                Some(l) => write!(writer, "{}{}", FSEP, l)?,
+                None => write!(writer, "{}-1", FSEP)?,
+            }
+        }
+        for field in &table.extra[r] {
+            write!(writer, "{}{}", FSEP, quote(field))?;
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    Ok(())
+}
+
+/// Write edges between rows of a point table as x0, y0, x1, y1 records, or
+/// if indexed as u, v, length records, then the other fields of the u row
+/// and of the v row.  A header row is written first if the table has one,
+/// with the other columns' names ending 0 for u and 1 for v.
+pub fn write_edge_table<W>(
+    writer: &mut W,
+    table: &PointTable,
+    edges: &[Edge],
+    rows: &[(usize, usize)],
+    indexed: bool,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    if let Some(names) = &table.names {
+        if indexed {
+            write!(writer, "u{}v{}length", FSEP, FSEP)?;
+        } else {
+            write!(writer, "x0{}y0{}x1{}y1", FSEP, FSEP, FSEP)?;
+        }
+        for end in ["0", "1"] {
+            for name in names {
+                write!(writer, "{}{}", FSEP, quote(&(name.clone() + end)))?;
+            }
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    for (e, &(u, v)) in edges.iter().zip(rows) {
+        if indexed {
+            write!(writer, "{}{}{}{}{}", u, FSEP, v, FSEP, e.len())?;
+        } else {
+            write!(
+                writer,
+                "{}{}{}{}{}{}{}",
+                e.u.x, FSEP, e.u.y, FSEP, e.v.x, FSEP, e.v.y
+            )?;
+        }
+        for field in table.extra[u].iter().chain(&table.extra[v]) {
+            write!(writer, "{}{}", FSEP, quote(field))?;
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    Ok(())
+}
+
+/// Read edges from x0, y0, x1, y1 records.  Malformed records and edges
+/// from a point to itself are skipped.
+pub fn read_edges<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Edge>>
+where
+    R: BufRead,
+{
+    let mut edges = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let fields: Vec<i32> = fields
+            .iter()
+            .take(4)
+            .filter_map(|s| s.trim().parse::<i32>().ok())
+            .collect();
+        if let [x0, y0, x1, y1] = fields[..] {
+            if (x0, y0) != (x1, y1) {
+                edges.push(Edge::new(x0, y0, x1, y1));
+            }
+        }
+    }
+
+    Ok(edges)
+}
+
+/// Read pairs of points from x0, y0, x1, y1 records, keeping every record,
+/// pairs of copies of a point included.  The first record may be a header,
+/// with no numbers in it; any other malformed record is an error.
+pub fn read_pairs<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: BufRead,
+{
+    let mut pairs = Vec::new();
+    let mut first = true;
+
+    for record in records(reader, delimiter) {
+        let (n, fields) = record?;
+        let header = first && is_header(&fields);
+        first = false;
+        let coordinates: Vec<i32> = fields
+            .iter()
+            .take(4)
+            .filter_map(|s| s.trim().parse::<i32>().ok())
+            .collect();
+        match coordinates[..] {
+            [x0, y0, x1, y1] => {
+                pairs.push((Vertex::new(x0, y0), Vertex::new(x1, y1)))
+            }
+            _ if header => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid pair on line {}", n),
+                ))
+            }
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// Write edges as x0, y0, x1, y1 records.
+pub fn write_edges<W>(writer: &mut W, edges: &[Edge]) -> io::Result<()>
+where
+    W: Write,
+{
+    for e in edges {
+        let (u, v) = (e.u, e.v);
+        write!(
+            writer,
+            "{}{}{}{}{}{}{}{}",
+            u.x, FSEP, u.y, FSEP, v.x, FSEP, v.y, RSEP
+        )?;
+    }
+    Ok(())
+}
+
+/// Write edges as u, v, length records, where u and v are the indices of
+/// the end points of each edge in the set of points, so repeated points
+/// stay distinct.
+pub fn write_indexed_edges<W>(
+    writer: &mut W,
+    edges: &[Edge],
+    indices: &[(usize, usize)],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    for (e, &(u, v)) in edges.iter().zip(indices) {
+        write!(writer, "{}{}{}{}{}{}", u, FSEP, v, FSEP, e.len(), RSEP)?;
+    }
+    Ok(())
+}
+
+/// Read edges from u, v, length records, looking up the end points by
+/// index in the set of points.  Malformed records and edges between copies
+/// of a point are skipped.  The length is not read, the edge is measured.
+/// An index past the end of the points is an error.
+pub fn read_indexed_edges<R>(
+    reader: R,
+    points: &[Vertex],
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Edge>>
+where
+    R: BufRead,
+{
+    let mut edges = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let mut i = fields.into_iter();
+        let u = i.next().and_then(|s| s.trim().parse::<usize>().ok());
+        let v = i.next().and_then(|s| s.trim().parse::<usize>().ok());
+        if let (Some(u), Some(v)) = (u, v) {
+            match (points.get(u), points.get(v)) {
+                (Some(&a), Some(&b)) => {
+                    if (a.x, a.y) != (b.x, b.y) {
+                        edges.push(Edge::from_vertices(a, b));
+                    }
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("edge {} - {} has no such point", u, v),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(edges)
+}
+
+/// Write paths as x0, y0, x1, y1 records followed by the x, y of each bend
+/// in order from (x0, y0).  Readers of plain edges see a straight edge.
+pub fn write_paths<W>(writer: &mut W, paths: &[Path]) -> io::Result<()>
+where
+    W: Write,
+{
+    for p in paths {
+        let (u, v) = (p.edge.u, p.edge.v);
+        write!(
+            writer,
+            "{}{}{}{}{}{}{}",
+            u.x, FSEP, u.y, FSEP, v.x, FSEP, v.y
+        )?;
+        for b in &p.bends {
+            write!(writer, "{}{}{}{}", FSEP, b.x, FSEP, b.y)?;
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    Ok(())
+}
+
+/// Read paths from x0, y0, x1, y1 records followed by any bends.
+/// Malformed records are skipped.
+pub fn read_paths<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Path>>
+where
+    R: BufRead,
+{
+    let mut paths = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let fields: Option<Vec<i32>> = fields
+            .iter()
+            .map(|s| s.trim().parse::<i32>().ok())
+            .collect();
+        match fields {
+            Some(f) if f.len() >= 4 && f.len() % 2 == 0 => {
+                let bends =
+                    f[4..].chunks(2).map(|b| Vertex::new(b[0], b[1])).collect();
+                paths.push(Path::new(
+                    Vertex::new(f[0], f[1]),
+                    Vertex::new(f[2], f[3]),
+                    bends,
+                ));
+            }
+            _ => continue,
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Read polygons, one per line as x, y of each vertex in order.
+/// Records with fewer than three vertices are skipped.
+pub fn read_polygons<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Polygon>>
+where
+    R: BufRead,
+{
+    let mut polygons = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let fields: Option<Vec<i32>> = fields
+            .iter()
+            .map(|s| s.trim().parse::<i32>().ok())
+            .collect();
+        if let Some(f) = fields.filter(|f| f.len() % 2 == 0) {
+            let vertices =
+                f.chunks(2).map(|p| Vertex::new(p[0], p[1])).collect();
+            if let Ok(polygon) = Polygon::new(vertices) {
+                polygons.push(polygon);
+            }
+        }
+    }
+
+    Ok(polygons)
+}
+
+/// Write points as x, y records, in order.
+pub fn write_points<W>(writer: &mut W, points: &[Vertex]) -> io::Result<()>
+where
+    W: Write,
+{
+    for p in points {
+        write!(writer, "{}{}{}{}", p.x, FSEP, p.y, RSEP)?;
+    }
+    Ok(())
+}
+
+/// Write points with their cluster labels as x, y, label records.
+/// Noise points (no label) are written with label -1.
+pub fn write_labels<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    labels: &[Option<usize>],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    for (p, label) in points.iter().zip(labels) {
+        match label {
+            Some(l) => {
+                write!(writer, "{}{}{}{}{}{}", p.x, FSEP, p.y, FSEP, l, RSEP)?
+            }
+            None => write!(writer, "{}{}{}{}-1{}", p.x, FSEP, p.y, FSEP, RSEP)?,
+        }
+    }
+    Ok(())
+}
+
+/// Read points with their cluster labels from x, y, label records.
+/// Negative labels are noise.  Malformed records are skipped.
+pub fn read_labels<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<(Vec<Vertex>, Vec<Option<usize>>)>
+where
+    R: BufRead,
+{
+    let mut points = Vec::new();
+    let mut labels = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let mut i = fields.into_iter();
+        let x = i.next().and_then(|s| s.trim().parse::<i32>().ok());
+        let y = i.next().and_then(|s| s.trim().parse::<i32>().ok());
+        let label = i.next().and_then(|s| s.trim().parse::<i64>().ok());
+        if let (Some(x), Some(y), Some(label)) = (x, y, label) {
+            points.push(Vertex::new(x, y));
+            labels.push(usize::try_from(label).ok());
+        }
+    }
+
+    Ok((points, labels))
+}
+
+/// Write a linkage matrix as a, b, distance, size records.
+pub fn write_linkage<W>(writer: &mut W, links: &[Link]) -> io::Result<()>
+where
+    W: Write,
+{
+    for l in links {
+        write!(
+            writer,
+            "{}{}{}{}{}{}{}{}",
+            l.a, FSEP, l.b, FSEP, l.distance, FSEP, l.size, RSEP
+        )?;
+    }
+    Ok(())
+}
+
+/// Write replacements as x0, y0, x1, y1 of the tree edge, x0, y0, x1, y1 of
+/// its replacement and the increase in length.  Edges that can't be
+/// replaced are written alone.
+pub fn write_replacements<W>(
+    writer: &mut W,
+    replacements: &[Replacement],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    for r in replacements {
+        let (u, v) = (r.edge.u, r.edge.v);
+        write!(
+            writer,
+            "{}{}{}{}{}{}{}",
+            u.x, FSEP, u.y, FSEP, v.x, FSEP, v.y
+        )?;
+        if let (Some(e), Some(delta)) = (r.replacement, r.delta()) {
+            write!(
+                writer,
+                "{}{}{}{}{}{}{}{}{}{}",
+                FSEP, e.u.x, FSEP, e.u.y, FSEP, e.v.x, FSEP, e.v.y, FSEP, delta
+            )?;
+        }
+        write!(writer, "{}", RSEP)?;
+    }
+    Ok(())
+}
+
+/// Read a linkage matrix from a, b, distance, size records.
+/// Malformed records are skipped.
+pub fn read_linkage<R>(
+    reader: R,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Link>>
+where
+    R: BufRead,
+{
+    let mut links = Vec::new();
+
+    for record in records(reader, delimiter) {
+        let (_, fields) = record?;
+        let mut i = fields.into_iter();
+        let a = i.next().and_then(|s| s.trim().parse::<usize>().ok());
+        let b = i.next().and_then(|s| s.trim().parse::<usize>().ok());
+        let distance = i.next().and_then(|s| s.trim().parse::<f32>().ok());
+        let size = i.next().and_then(|s| s.trim().parse::<usize>().ok());
+        if let (Some(a), Some(b), Some(distance), Some(size)) =
+            (a, b, distance, size)
+        {
+            links.push(Link {
+                a,
+                b,
+                distance,
+                size,
+            });
+        }
+    }
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_round_trip() {
+        let points = vec![Vertex::new(1, 2), Vertex::new(-3, 4)];
+        let labels = vec![Some(0), None];
+        let mut buffer = Vec::new();
+        write_labels(&mut buffer, &points, &labels).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "1\t2\t0\n-3\t4\t-1\n");
+
+        let (p, l) = read_labels(&buffer[..], None).unwrap();
+        assert_eq!(p.len(), 2);
+        assert_eq!((p[1].x, p[1].y), (-3, 4));
+        assert_eq!(l, labels);
+    }
+
+    #[test]
+    fn test_linkage_round_trip() {
+        let links = vec![
+            Link {
+                a: 0,
+                b: 1,
+                distance: 1.5,
+                size: 2,
+            },
+            Link {
+                a: 2,
+                b: 3,
+                distance: 4.0,
+                size: 3,
+            },
+        ];
+        let mut buffer = Vec::new();
+        write_linkage(&mut buffer, &links).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "0\t1\t1.5\t2\n2\t3\t4\t3\n"
+        );
+        assert_eq!(read_linkage(&buffer[..], None).unwrap(), links);
+    }
+
+    #[test]
+    fn test_read_labels_skips_malformed() {
+        let text = "# x\ty\tlabel\n1\t2\n1\t2\tx\n5\t6\t7\n";
+        let (p, l) = read_labels(text.as_bytes(), None).unwrap();
+        assert_eq!(p.len(), 1);
+        assert_eq!(l, vec![Some(7)]);
+    }
+
+    #[test]
+    fn test_points_round_trip() {
+        let points = vec![Vertex::new(1, 2), Vertex::new(-3, 4)];
+        let mut buffer = Vec::new();
+        write_points(&mut buffer, &points).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), "1\t2\n-3\t4\n");
+        let p = read_points(&buffer[..]).unwrap();
+        assert_eq!((p[1].x, p[1].y), (-3, 4));
+    }
+
+    #[test]
+    fn test_write_edges() {
+        let edges = vec![Edge::new(1, 2, 3, 4), Edge::new(-1, 0, 0, 5)];
+        let mut buffer = Vec::new();
+        write_edges(&mut buffer, &edges).unwrap();
+        assert_eq!(buffer, b"1\t2\t3\t4\n-1\t0\t0\t5\n");
+        assert_eq!(read_edges(buffer.as_slice(), None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_read_edges() {
+        let text = "# x0\ty0\tx1\ty1\n1\t2\t3\t4\n1\t2\t1\t2\n5\t6\t7\n";
+        let edges = read_edges(text.as_bytes(), None).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!((edges[0].v.x, edges[0].v.y), (3, 4));
+    }
+
+    #[test]
+    fn test_read_pairs() {
+        let text = "x0,y0,x1,y1\n1,2,3,4\n\n# copies\n1,2,1,2\n";
+        let pairs = read_pairs(text.as_bytes(), None).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!((pairs[1].0.x, pairs[1].1.y), (1, 2));
+        let text = "1,2,3,4\n5,6,7\n";
+        let e = read_pairs(text.as_bytes(), None).err().unwrap();
+        assert_eq!(e.to_string(), "invalid pair on line 2");
+    }
+
+    #[test]
+    fn test_indexed_edges_round_trip() {
+        // The first and last points are the same
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(3, 4),
+            Vertex::new(6, 8),
+            Vertex::new(0, 0),
+        ];
+        let indices = vec![(1, 0), (2, 1), (3, 0)];
+        let mut buffer = Vec::new();
+        let edges = crate::index_edges(&points, &indices);
+        write_indexed_edges(&mut buffer, &edges, &indices).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(text, "1\t0\t5\n2\t1\t5\n3\t0\t0\n");
+        let edges =
+            read_indexed_edges(buffer.as_slice(), &points, None).unwrap();
+        assert_eq!(edges.len(), 2);
+        assert_eq!((edges[1].u.x, edges[1].v.x), (6, 3));
+        assert_eq!(edges[1].len(), 5.0);
+        assert!(
+            read_indexed_edges("0\t4\t1\n".as_bytes(), &points, None).is_err()
+        );
+    }
+
+    #[test]
+    fn test_paths_round_trip() {
+        let paths = vec![
+            Path::new(Vertex::new(0, 0), Vertex::new(10, 0), vec![]),
+            Path::new(
+                Vertex::new(0, 0),
+                Vertex::new(20, 0),
+                vec![Vertex::new(10, 5)],
+            ),
+        ];
+        let mut buffer = Vec::new();
+        write_paths(&mut buffer, &paths).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "0\t0\t10\t0\n0\t0\t20\t0\t10\t5\n"
+        );
+        let read = read_paths(&buffer[..], None).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].bends.len(), 1);
+        assert_eq!(read[1].edge.len(), paths[1].edge.len());
+        // Plain edge readers see the end points
+        assert_eq!(read_edges(&buffer[..], None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_write_replacements() {
+        let replacements = vec![
+            Replacement {
+                edge: Edge::new(0, 0, 3, 4),
+                replacement: Some(Edge::new(0, 0, 6, 8)),
+            },
+            Replacement {
+                edge: Edge::new(3, 4, 6, 8),
+                replacement: None,
+            },
+        ];
+        let mut buffer = Vec::new();
+        write_replacements(&mut buffer, &replacements).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "0\t0\t3\t4\t0\t0\t6\t8\t5\n3\t4\t6\t8\n"
+        );
+        // Plain edge readers see the tree edges
+        assert_eq!(read_edges(&buffer[..], None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_read_polygons() {
+        let text = "# wall\n0\t0\t10\t0\t10\t10\n0\t0\t1\t1\n1\t2\t3\n";
+        let polygons = read_polygons(text.as_bytes(), None).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].vertices.len(), 3);
+    }
+
+    #[test]
+    fn test_delimiters() {
+        assert_eq!(Delimiter::detect("1\t2,3"), Delimiter::Tab);
+        assert_eq!(Delimiter::detect("\"a\tb\",1"), Delimiter::Comma);
+        assert_eq!(Delimiter::detect("1;2"), Delimiter::Semicolon);
+        assert_eq!(Delimiter::detect(" 1  2 "), Delimiter::Whitespace);
+        for name in DELIMITER_NAMES {
+            assert!(name.parse::<Delimiter>().is_ok());
+        }
+        assert_eq!(",".parse::<Delimiter>(), Ok(Delimiter::Comma));
+        assert!("pipe".parse::<Delimiter>().is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let split = |d: Delimiter, line: &str| d.split(line);
+        assert_eq!(split(Delimiter::Comma, " 1, 2 ,,x"), ["1", "2", "", "x"]);
+        assert_eq!(
+            split(Delimiter::Comma, "\"a, \"\"b\"\"\",  \" c \" ,d"),
+            ["a, \"b\"", " c ", "d"]
+        );
+        assert_eq!(
+            split(Delimiter::Whitespace, "  1 \t 2   \"\" "),
+            ["1", "2", ""]
+        );
+        assert_eq!(split(Delimiter::Semicolon, "1;2"), ["1", "2"]);
+        assert_eq!(split(Delimiter::Tab, ""), [""]);
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote("a,b"), "\"a,b\"");
+        assert_eq!(quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote(" pad"), "\" pad\"");
+    }
+
+    #[test]
+    fn test_point_table() {
+        let text = "# sensors\nid,y,x,\"name\"\n7,2,1,\"north, east\"\n\
+                    8,bad,3,skip\n9,4,3,south\n";
+        let table = read_point_table(text.as_bytes(), None).unwrap();
+        let names = table.names.clone().unwrap();
+        assert_eq!(names, ["id", "name"]);
+        let xy: Vec<(i32, i32)> =
+            table.points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(xy, [(1, 2), (3, 4)]);
+        assert_eq!(table.extra[0], ["7", "north, east"]);
+        assert_eq!(table.skipped, 1);
+
+        let mut buffer = Vec::new();
+        write_point_table(&mut buffer, &table, &[1, 0], Some(&[None, Some(2)]))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            "x\ty\tlabel\tid\tname\n3\t4\t-1\t9\tsouth\n\
+             1\t2\t2\t7\t\"north, east\"\n"
+        );
+        // Written tables read back, and plain readers skip the header
+        let again = read_point_table(buffer.as_slice(), None).unwrap();
+        assert_eq!(again.extra[1], ["2", "7", "north, east"]);
+        let (points, labels) = read_labels(buffer.as_slice(), None).unwrap();
+        assert_eq!((points.len(), labels[1]), (2, Some(2)));
+
+        // No header, forced delimiter
+        let text = "1 2 a\n3 4 b\n";
+        let table =
+            read_point_table(text.as_bytes(), Some(Delimiter::Whitespace))
+                .unwrap();
+        assert!(table.names.is_none());
+        assert_eq!(table.extra[1], ["b"]);
+        let mut buffer = Vec::new();
+        write_point_table(&mut buffer, &table, &[0, 1], None).unwrap();
+        assert_eq!(buffer, b"1\t2\ta\n3\t4\tb\n");
+    }
+
+    #[test]
+    fn test_edge_table() {
+        let text = "x,y,id\n0,0,a\n3,4,b\n";
+        let table = read_point_table(text.as_bytes(), None).unwrap();
+        let edges = crate::index_edges(&table.points, &[(1, 0)]);
+        let mut buffer = Vec::new();
+        write_edge_table(&mut buffer, &table, &edges, &[(1, 0)], false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            "x0\ty0\tx1\ty1\tid0\tid1\n3\t4\t0\t0\tb\ta\n"
+        );
+        assert_eq!(read_edges(buffer.as_slice(), None).unwrap().len(), 1);
+        let mut buffer = Vec::new();
+        write_edge_table(&mut buffer, &table, &edges, &[(1, 0)], true).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            "u\tv\tlength\tid0\tid1\n1\t0\t5\tb\ta\n"
+        );
+        let again = read_indexed_edges(buffer.as_slice(), &table.points, None);
+        assert_eq!(again.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_read_other_delimiters() {
+        for text in ["1, 2, 3, 4\n", "1;2;3;4\n", "1 2  3 4\n"] {
+            let edges = read_edges(text.as_bytes(), None).unwrap();
+            assert_eq!((edges[0].u.x, edges[0].v.y), (1, 4));
+        }
+        let points = read_points("x,y\n1,2\n\"3\",\"4\"\n".as_bytes()).unwrap();
+        assert_eq!((points.len(), points[1].y), (2, 4));
+
+        // One delimiter for the whole file, from its first record or given
+        let text = "1,2,3,4\n5 6 7 8\n";
+        assert_eq!(read_edges(text.as_bytes(), None).unwrap().len(), 1);
+        let text = "1 2 3 4\n5 6 7 8\n";
+        let edges =
+            read_edges(text.as_bytes(), Some(Delimiter::Comma)).unwrap();
+        assert!(edges.is_empty());
+        let links =
+            read_linkage("0;1;2;2\n".as_bytes(), Some(Delimiter::Semicolon));
+        assert_eq!(links.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_short_records() {
+        // Records are read however short they are
+        let edges = read_edges("0,0,1,1\n".as_bytes(), None).unwrap();
+        assert_eq!(edges.len(), 1);
+        let (points, labels) = read_labels("1,2,3\n".as_bytes(), None).unwrap();
+        assert_eq!((points.len(), labels[0]), (1, Some(3)));
+        let links = read_linkage("0,1,2,2\n".as_bytes(), None).unwrap();
+        assert_eq!(links.len(), 1);
+        let points = [Vertex::new(0, 0), Vertex::new(1, 1)];
+        let edges = read_indexed_edges("0,1\n".as_bytes(), &points, None);
+        assert_eq!(edges.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_first_record() {
+        // A first record with a number in it is a point, not a header
+        let table = read_point_table("1,x\n3,4\n".as_bytes(), None).unwrap();
+        assert!(table.names.is_none());
+        assert_eq!((table.points.len(), table.skipped), (1, 1));
+        let e = read_pairs("1,2,3\n1,2,3,4\n".as_bytes(), None)
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "invalid pair on line 1");
+    }
+
+    #[test]
+    fn test_read_points() {
+        let text = "# x\ty\n1\t2\n\n3\n-4\t5\textra\nx\ty\n";
+        let points = read_points(text.as_bytes()).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[1].x, points[1].y), (-4, 5));
+    }
+}
@@ -0,0 +1,74 @@
+//! Disjoint Set
+//!
+//! Union-find over vertex indices, used to track connected components while
+//! building or cutting trees.
+
+/// Disjoint set forest with union by size and path halving
+#[derive(Clone)]
+pub(crate) struct DisjointSet {
+    /// Parent of each element, roots are their own parent
+    parent: Vec<usize>,
+    /// Number of elements in the set, only valid for roots
+    size: Vec<usize>,
+}
+
+impl DisjointSet {
+    /// Create n singleton sets.
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    /// Find the root of the set containing i.
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Merge the sets containing a and b.
+    /// Returns false if they were already in the same set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let mut a = self.find(a);
+        let mut b = self.find(b);
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+
+    /// Number of elements in the set containing i.
+    pub fn size(&mut self, i: usize) -> usize {
+        let root = self.find(i);
+        self.size[root]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disjoint_set() {
+        let mut sets = DisjointSet::new(5);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(3, 4));
+        assert!(!sets.union(1, 0));
+        assert_eq!(sets.find(0), sets.find(1));
+        assert_ne!(sets.find(1), sets.find(3));
+        assert_eq!(sets.size(4), 2);
+        assert_eq!(sets.size(2), 1);
+        assert!(sets.union(1, 4));
+        assert_eq!(sets.find(0), sets.find(3));
+        assert_eq!(sets.size(0), 4);
+    }
+}
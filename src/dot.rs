@@ -0,0 +1,75 @@
+//! Graphviz DOT Files
+//!
+//! Write points and trees as undirected DOT graphs for Graphviz and the
+//! tools that read it.  Each point is a node named by its position in the
+//! set of points, with its coordinates as a pinned `pos` attribute for
+//! `neato -n` and `fdp`, and each edge has its length as its `weight`.
+
+use crate::{edge_indices, Edge, Vertex};
+use std::io::{self, Write};
+
+/// Write a set of points and edges between them as a DOT graph.  The edges
+/// are matched to the points by their coordinates, see
+/// `write_indexed_graph` for edges whose end indices are known.
+pub fn write_graph<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    edges: &[Edge],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let indices = edge_indices(points, edges)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    write_indexed_graph(writer, points, edges, &indices)
+}
+
+/// Write a set of points and edges between them as a DOT graph, with the
+/// indices of the end points of each edge.
+pub fn write_indexed_graph<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    edges: &[Edge],
+    indices: &[(usize, usize)],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    writeln!(writer, "graph mst {{")?;
+    writeln!(writer, "  node [shape=point];")?;
+    for (i, p) in points.iter().enumerate() {
+        writeln!(writer, "  {} [pos=\"{},{}!\"];", i, p.x, p.y)?;
+    }
+    for (e, &(u, v)) in edges.iter().zip(indices) {
+        writeln!(writer, "  {} -- {} [weight={}];", u, v, e.len())?;
+    }
+    writeln!(writer, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_graph() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(3, 4, 0, 0)];
+        let mut buffer = Vec::new();
+        write_graph(&mut buffer, &points, &edges).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("graph mst {\n"));
+        assert!(text.contains("  1 [pos=\"3,4!\"];\n"));
+        assert!(text.contains("  1 -- 0 [weight=5];\n"));
+        assert!(text.ends_with("}\n"));
+
+        let stray = vec![Edge::new(0, 0, 1, 1)];
+        assert!(write_graph(&mut Vec::new(), &points, &stray).is_err());
+
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(3, 4)];
+        let mut buffer = Vec::new();
+        write_indexed_graph(&mut buffer, &points, &edges, &[(2, 0)]).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("  2 -- 0 [weight=5];\n"));
+    }
+}
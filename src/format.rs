@@ -0,0 +1,586 @@
+//! File Formats
+//!
+//! Points and trees are read and written as CSV (see `csv`), plain JSON or
+//! GeoJSON (see `json`), WKT (see `wkt`), GraphML (see `graphml`) and
+//! binary (see `binary`), and written as Graphviz DOT (see `dot`).  The
+//! tools take the format from a `--format` option, then from the file name
+//! extension, and when reading from the content: binary starts with its
+//! magic number, JSON with `[` or `{`, GeoJSON is a `FeatureCollection`, WKT
+//! starts with a geometry tag followed by `(`, `EMPTY` or a `Z` or `M`, or
+//! with `SRID=`, GraphML with `<` and DOT with `graph`, `digraph` or
+//! `strict`.  So a CSV header starting `point,` is still CSV.
+//!
+//! Files are read through a memory map where they can be, which saves
+//! copying large binary files.
+//!
+//! Obstacles are polygons, read from CSV, WKT or GeoJSON.
+//!
+//! Of the formats read, only GraphML holds a graph, with edges between its
+//! points to find a tree among; `read_graph` reads points with the edges of
+//! a GraphML graph, and no edges from the other formats.
+
+use crate::binary;
+use crate::csv::{self, Delimiter, PointTable};
+use crate::dot;
+use crate::graphml;
+use crate::json::{self, Tree};
+use crate::obstacles::Polygon;
+use crate::wkt;
+use crate::{Edge, GraphEdge, Vertex};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format names accepted by `Format::from_str`
+pub const FORMAT_NAMES: &[&str] =
+    &["csv", "json", "geojson", "wkt", "dot", "graphml", "binary"];
+
+/// Geometry tags that start WKT text
+const WKT_TAGS: &[&str] = &[
+    "POINT",
+    "MULTIPOINT",
+    "LINESTRING",
+    "MULTILINESTRING",
+    "POLYGON",
+    "MULTIPOLYGON",
+    "GEOMETRYCOLLECTION",
+    "SRID",
+];
+
+/// File formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+    GeoJson,
+    Wkt,
+    Dot,
+    GraphMl,
+    Binary,
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" | "tsv" | "txt" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "geojson" => Ok(Format::GeoJson),
+            "wkt" => Ok(Format::Wkt),
+            "dot" | "gv" => Ok(Format::Dot),
+            "graphml" => Ok(Format::GraphMl),
+            "binary" | "mstb" => Ok(Format::Binary),
+            _ => Err("unknown format"),
+        }
+    }
+}
+
+impl Format {
+    /// Get the format named by a file name extension, if there is one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| e.parse().ok())
+    }
+
+    /// Guess the format of file contents, binary or text.
+    pub fn detect_bytes(bytes: &[u8]) -> Self {
+        if binary::is_binary(bytes) {
+            Format::Binary
+        } else {
+            Format::detect(&String::from_utf8_lossy(bytes))
+        }
+    }
+
+    /// Guess the format of text file contents.
+    pub fn detect(text: &str) -> Self {
+        let text = text.trim_start();
+        if text.starts_with('[') {
+            Format::Json
+        } else if text.starts_with('{') {
+            if text.contains("\"FeatureCollection\"") {
+                Format::GeoJson
+            } else {
+                Format::Json
+            }
+        } else if is_wkt(text) {
+            Format::Wkt
+        } else if text.starts_with('<') {
+            Format::GraphMl
+        } else if ["graph", "digraph", "strict"]
+            .iter()
+            .any(|k| text.starts_with(k))
+        {
+            Format::Dot
+        } else {
+            Format::Csv
+        }
+    }
+}
+
+/// Whether text starts with a WKT geometry tag followed by what may follow
+/// a tag, rather than, say, a CSV header naming a column after one.
+fn is_wkt(text: &str) -> bool {
+    let end = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (tag, rest) = text.split_at(end);
+    let tag = tag.to_uppercase();
+    if tag == "SRID" {
+        return rest.trim_start().starts_with('=');
+    }
+    if !WKT_TAGS.contains(&tag.as_str()) {
+        return false;
+    }
+    let rest = rest.trim_start().to_uppercase();
+    let rest = ["ZM", "Z", "M"]
+        .iter()
+        .find_map(|d| rest.strip_prefix(d))
+        .map_or(rest.as_str(), |r| r.trim_start());
+    rest.starts_with('(') || rest.starts_with("EMPTY")
+}
+
+/// Read all of a reader.
+fn slurp<R>(mut reader: R) -> io::Result<Vec<u8>>
+where
+    R: Read,
+{
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The error reading a format that is only written.
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "DOT files are not read")
+}
+
+/// Read points from file contents in a format, detected if not given.
+/// Only CSV points have other fields; the delimiter applies to CSV.
+pub fn read_points_from(
+    bytes: &[u8],
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<PointTable> {
+    match format.unwrap_or_else(|| Format::detect_bytes(bytes)) {
+        Format::Csv => csv::read_point_table(bytes, delimiter),
+        Format::Json => json::read_points(bytes).map(PointTable::from),
+        Format::GeoJson => json::read_geo_points(bytes).map(PointTable::from),
+        Format::Wkt => wkt::read_points(bytes).map(PointTable::from),
+        Format::GraphMl => graphml::read_points(bytes).map(PointTable::from),
+        Format::Binary => binary::read_points(bytes).map(PointTable::from),
+        Format::Dot => Err(unsupported()),
+    }
+}
+
+/// Read edges from file contents in a format, detected if not given.  The
+/// delimiter applies to CSV.
+pub fn read_edges_from(
+    bytes: &[u8],
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Edge>> {
+    match format.unwrap_or_else(|| Format::detect_bytes(bytes)) {
+        Format::Csv => csv::read_edges(bytes, delimiter),
+        Format::Json => json::read_edges(bytes),
+        Format::GeoJson => json::read_geo_edges(bytes),
+        Format::Wkt => wkt::read_edges(bytes),
+        Format::GraphMl => graphml::read_edges(bytes),
+        Format::Binary => binary::read_edges(bytes),
+        Format::Dot => Err(unsupported()),
+    }
+}
+
+/// Read obstacles from file contents in a format, detected if not given.
+/// Only CSV, WKT and GeoJSON hold polygons.
+pub fn read_polygons_from(
+    bytes: &[u8],
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Polygon>> {
+    match format.unwrap_or_else(|| Format::detect_bytes(bytes)) {
+        Format::Csv => csv::read_polygons(bytes, delimiter),
+        Format::GeoJson => json::read_geo_polygons(bytes),
+        Format::Wkt => wkt::read_polygons(bytes),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "polygons are read from CSV, WKT or GeoJSON",
+        )),
+    }
+}
+
+/// Read points, and the edges between them if the contents are a GraphML
+/// graph, from file contents in a format, detected if not given.  Each edge
+/// is the indices of its end points and its weight.
+pub fn read_graph_from(
+    bytes: &[u8],
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<(PointTable, Vec<GraphEdge>)> {
+    match format.unwrap_or_else(|| Format::detect_bytes(bytes)) {
+        Format::GraphMl => graphml::read_weighted(bytes)
+            .map(|(points, graph)| (PointTable::from(points), graph)),
+        format => read_points_from(bytes, Some(format), delimiter)
+            .map(|table| (table, Vec::new())),
+    }
+}
+
+/// Read pairs of points from file contents in a format, detected if not
+/// given.  Unlike edges, every pair is kept, pairs of copies of a point
+/// included, and a malformed CSV record is an error rather than skipped.
+pub fn read_pairs_from(
+    bytes: &[u8],
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<(Vertex, Vertex)>> {
+    match format.unwrap_or_else(|| Format::detect_bytes(bytes)) {
+        Format::Csv => csv::read_pairs(bytes, delimiter),
+        Format::Json => json::read_pairs(bytes),
+        Format::GeoJson => json::read_geo_pairs(bytes),
+        Format::Wkt => wkt::read_pairs(bytes),
+        Format::GraphMl => graphml::read_pairs(bytes),
+        Format::Binary => binary::read_pairs(bytes),
+        Format::Dot => Err(unsupported()),
+    }
+}
+
+/// Read points in a format, detected if not given.
+pub fn read_points<R>(
+    reader: R,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<PointTable>
+where
+    R: Read,
+{
+    read_points_from(&slurp(reader)?, format, delimiter)
+}
+
+/// Read edges in a format, detected if not given.
+pub fn read_edges<R>(
+    reader: R,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Edge>>
+where
+    R: Read,
+{
+    read_edges_from(&slurp(reader)?, format, delimiter)
+}
+
+/// Read points, and the edges between them if the input is a GraphML graph,
+/// in a format, detected if not given.
+pub fn read_graph<R>(
+    reader: R,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<(PointTable, Vec<GraphEdge>)>
+where
+    R: Read,
+{
+    read_graph_from(&slurp(reader)?, format, delimiter)
+}
+
+/// Read obstacles in a format, detected if not given.
+pub fn read_polygons<R>(
+    reader: R,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Polygon>>
+where
+    R: Read,
+{
+    read_polygons_from(&slurp(reader)?, format, delimiter)
+}
+
+/// Read pairs of points in a format, detected if not given.
+pub fn read_pairs<R>(
+    reader: R,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: Read,
+{
+    read_pairs_from(&slurp(reader)?, format, delimiter)
+}
+
+/// Read points from a file in a format, detected if not given, through a
+/// memory map if the file can be mapped.
+pub fn read_points_file(
+    file: &File,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<PointTable> {
+    match binary::map(file) {
+        Ok(map) => read_points_from(&map, format, delimiter),
+        Err(_) => read_points(file, format, delimiter),
+    }
+}
+
+/// Read edges from a file in a format, detected if not given, through a
+/// memory map if the file can be mapped.
+pub fn read_edges_file(
+    file: &File,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Edge>> {
+    match binary::map(file) {
+        Ok(map) => read_edges_from(&map, format, delimiter),
+        Err(_) => read_edges(file, format, delimiter),
+    }
+}
+
+/// Read points, and the edges between them if the file is a GraphML graph,
+/// from a file in a format, detected if not given, through a memory map if
+/// the file can be mapped.
+pub fn read_graph_file(
+    file: &File,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<(PointTable, Vec<GraphEdge>)> {
+    match binary::map(file) {
+        Ok(map) => read_graph_from(&map, format, delimiter),
+        Err(_) => read_graph(file, format, delimiter),
+    }
+}
+
+/// Read obstacles from a file in a format, detected if not given, through a
+/// memory map if the file can be mapped.
+pub fn read_polygons_file(
+    file: &File,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<Polygon>> {
+    match binary::map(file) {
+        Ok(map) => read_polygons_from(&map, format, delimiter),
+        Err(_) => read_polygons(file, format, delimiter),
+    }
+}
+
+/// Read pairs of points from a file in a format, detected if not given,
+/// through a memory map if the file can be mapped.
+pub fn read_pairs_file(
+    file: &File,
+    format: Option<Format>,
+    delimiter: Option<Delimiter>,
+) -> io::Result<Vec<(Vertex, Vertex)>> {
+    match binary::map(file) {
+        Ok(map) => read_pairs_from(&map, format, delimiter),
+        Err(_) => read_pairs(file, format, delimiter),
+    }
+}
+
+/// Write points in a format.  DOT and GraphML points are a graph without
+/// edges.
+pub fn write_points<W>(
+    writer: &mut W,
+    format: Format,
+    points: &[Vertex],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    match format {
+        Format::Csv => csv::write_points(writer, points),
+        Format::Json => json::write_points(writer, points),
+        Format::GeoJson => json::write_geo_points(writer, points),
+        Format::Wkt => wkt::write_points(writer, points),
+        Format::Dot => dot::write_graph(writer, points, &[]),
+        Format::GraphMl => graphml::write_graph(writer, points, &[]),
+        Format::Binary => binary::write_points(writer, points),
+    }
+}
+
+/// Write a tree of a set of points in a format.  CSV, GeoJSON and WKT hold
+/// only the edges.
+pub fn write_tree<W>(
+    writer: &mut W,
+    format: Format,
+    points: &[Vertex],
+    edges: &[Edge],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    match format {
+        Format::Csv => csv::write_edges(writer, edges),
+        Format::Json => json::write_tree(writer, &Tree::new(points, edges)),
+        Format::GeoJson => json::write_geo_edges(writer, edges),
+        Format::Wkt => wkt::write_edges(writer, edges),
+        Format::Dot => dot::write_graph(writer, points, edges),
+        Format::GraphMl => graphml::write_graph(writer, points, edges),
+        Format::Binary => binary::write_tree(writer, points, edges),
+    }
+}
+
+/// Write a tree of a set of points in a format, with the indices of the end
+/// points of each edge, which DOT, GraphML and binary write in place of
+/// matching the edges to the points.
+pub fn write_indexed_tree<W>(
+    writer: &mut W,
+    format: Format,
+    points: &[Vertex],
+    edges: &[Edge],
+    indices: &[(usize, usize)],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    match format {
+        Format::Dot => dot::write_indexed_graph(writer, points, edges, indices),
+        Format::GraphMl => {
+            graphml::write_indexed_graph(writer, points, edges, indices)
+        }
+        Format::Binary => binary::write_indexed_tree(writer, points, indices),
+        _ => write_tree(writer, format, points, edges),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_names() {
+        for name in FORMAT_NAMES {
+            assert!(name.parse::<Format>().is_ok());
+        }
+        assert!("xml".parse::<Format>().is_err());
+        assert_eq!(Format::from_path("a/tree.GeoJSON"), Some(Format::GeoJson));
+        assert_eq!(Format::from_path("data.tsv"), Some(Format::Csv));
+        assert_eq!(Format::from_path("mst.png"), None);
+        assert_eq!(Format::from_path("data"), None);
+        assert_eq!(Format::from_path("mst.gv"), Some(Format::Dot));
+        assert_eq!(Format::from_path("big.mstb"), Some(Format::Binary));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Format::detect("1\t2\n"), Format::Csv);
+        assert_eq!(Format::detect("  [{\"x\": 1, \"y\": 2}]"), Format::Json);
+        assert_eq!(Format::detect("{\"points\": []}"), Format::Json);
+        let geo = "{\"type\": \"FeatureCollection\", \"features\": []}";
+        assert_eq!(Format::detect(geo), Format::GeoJson);
+        assert_eq!(Format::detect("<?xml version=\"1.0\"?>"), Format::GraphMl);
+        assert_eq!(Format::detect("graph mst {}"), Format::Dot);
+        assert_eq!(Format::detect("multipoint((1 2))"), Format::Wkt);
+        assert_eq!(Format::detect("SRID=4326;POINT(1 2)"), Format::Wkt);
+        assert_eq!(Format::detect("x,y\n1,2\n"), Format::Csv);
+        assert_eq!(Format::detect("POINT Z (1 2 3)"), Format::Wkt);
+        assert_eq!(Format::detect("polygon empty"), Format::Wkt);
+        assert_eq!(Format::detect("point,x,y\na,1,2\n"), Format::Csv);
+        assert_eq!(Format::detect("Polygon\tx\ty\n"), Format::Csv);
+        assert_eq!(Format::detect("srid,x,y\n4326,1,2\n"), Format::Csv);
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(0, 0, 3, 4)];
+        for format in [
+            Format::Csv,
+            Format::Json,
+            Format::GeoJson,
+            Format::Wkt,
+            Format::GraphMl,
+            Format::Binary,
+        ] {
+            let mut buffer = Vec::new();
+            write_points(&mut buffer, format, &points).unwrap();
+            let table = read_points(buffer.as_slice(), None, None).unwrap();
+            assert_eq!(table.points.len(), 2);
+            assert_eq!(table.extra.len(), 2);
+            let mut buffer = Vec::new();
+            write_tree(&mut buffer, format, &points, &edges).unwrap();
+            let again = read_edges(buffer.as_slice(), None, None).unwrap();
+            assert_eq!((again.len(), again[0].len()), (1, 5.0));
+        }
+    }
+
+    #[test]
+    fn test_read_file() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(0, 0, 3, 4)];
+        let mut buffer = Vec::new();
+        write_tree(&mut buffer, Format::Binary, &points, &edges).unwrap();
+        assert_eq!(Format::detect_bytes(&buffer), Format::Binary);
+        assert_eq!(Format::detect_bytes(b"POINT(1 2)"), Format::Wkt);
+
+        let path = std::env::temp_dir().join("mst-format-test.mstb");
+        std::fs::write(&path, &buffer).unwrap();
+        let file = File::open(&path).unwrap();
+        let table = read_points_file(&file, None, None).unwrap();
+        let again = read_edges_file(&file, None, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(table.points.len(), 2);
+        assert_eq!((again.len(), again[0].len()), (1, 5.0));
+    }
+
+    #[test]
+    fn test_read_pairs_keeps_copies() {
+        let wkt = "LINESTRING (0 0, 0 0, 3 4)";
+        assert_eq!(read_pairs(wkt.as_bytes(), None, None).unwrap().len(), 2);
+        assert_eq!(read_edges(wkt.as_bytes(), None, None).unwrap().len(), 1);
+        let json = r#"[{"u":{"x":1,"y":2},"v":{"x":1,"y":2}}]"#;
+        assert_eq!(read_pairs(json.as_bytes(), None, None).unwrap().len(), 1);
+        assert!(read_edges(json.as_bytes(), None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_polygons() {
+        let csv = "0,0,4,0,4,4\n";
+        let wkt = "POLYGON((0 0, 4 0, 4 4, 0 0), (1 1, 2 1, 2 2, 1 1))";
+        let geo = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "MultiPolygon",
+             "coordinates": [[[[0, 0], [4, 0], [4, 4], [0, 0]]]]}}]}"#;
+        for text in [csv, wkt, geo] {
+            let polygons = read_polygons(text.as_bytes(), None, None).unwrap();
+            assert_eq!(polygons.len(), 1);
+            assert_eq!(polygons[0].vertices.len(), 3);
+        }
+        assert!(read_polygons("[]".as_bytes(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_dot_not_read() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(0, 0, 3, 4)];
+        let mut buffer = Vec::new();
+        write_tree(&mut buffer, Format::Dot, &points, &edges).unwrap();
+        assert!(read_edges(buffer.as_slice(), None, None).is_err());
+        assert!(read_points(buffer.as_slice(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_write_indexed_tree() {
+        let points =
+            vec![Vertex::new(3, 4), Vertex::new(0, 0), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(0, 0, 3, 4)];
+        let mut buffer = Vec::new();
+        write_indexed_tree(
+            &mut buffer,
+            Format::Dot,
+            &points,
+            &edges,
+            &[(1, 2)],
+        )
+        .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("  1 -- 2 [weight=5];"));
+        let mut buffer = Vec::new();
+        write_indexed_tree(
+            &mut buffer,
+            Format::Binary,
+            &points,
+            &edges,
+            &[(1, 2)],
+        )
+        .unwrap();
+        let pairs = read_pairs(buffer.as_slice(), None, None).unwrap();
+        assert_eq!(pairs.len(), 1);
+    }
+}
@@ -0,0 +1,313 @@
+//! GraphML Files
+//!
+//! Read and write points and trees as GraphML for Gephi, NetworkX and yEd.
+//!
+//! Each point is written as a node with its coordinates as `x` and `y`
+//! attributes, as Gephi places them, and as a `pos` attribute (`"x,y"`), as
+//! Graphviz and NetworkX place them.  Each edge has its length as its
+//! `weight`.
+//!
+//! When read, a node is placed by its `x` and `y` attributes, or otherwise
+//! its `pos` attribute, rounded to the nearest integer, and an edge has its
+//! `weight` (or `length`) attribute as its length, or otherwise is
+//! measured.  Attributes are found by name (`attr.name`) and otherwise by
+//! key, and edges between copies of a point are skipped.  A weight must be
+//! a finite number, not negative.
+//!
+//! `read_weighted` reads the graph as a whole, with each edge as the
+//! indices of its nodes and its weight, for finding its minimum spanning
+//! tree.
+
+use crate::{edge_indices, Edge, GraphEdge, Vertex};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+/// Make an invalid data error.
+fn invalid<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Write a set of points and edges between them as a GraphML graph.  The
+/// edges are matched to the points by their coordinates, see
+/// `write_indexed_graph` for edges whose end indices are known.
+pub fn write_graph<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    edges: &[Edge],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let indices = edge_indices(points, edges)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    write_indexed_graph(writer, points, edges, &indices)
+}
+
+/// Write a set of points and edges between them as a GraphML graph, with
+/// the indices of the end points of each edge.
+pub fn write_indexed_graph<W>(
+    writer: &mut W,
+    points: &[Vertex],
+    edges: &[Edge],
+    indices: &[(usize, usize)],
+) -> io::Result<()>
+where
+    W: Write,
+{
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        writer,
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+    )?;
+    for (id, kind, name, attr) in [
+        ("x", "node", "x", "double"),
+        ("y", "node", "y", "double"),
+        ("pos", "node", "pos", "string"),
+        ("weight", "edge", "weight", "double"),
+    ] {
+        writeln!(
+            writer,
+            "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            id, kind, name, attr
+        )?;
+    }
+    writeln!(writer, "  <graph id=\"mst\" edgedefault=\"undirected\">")?;
+    for (i, p) in points.iter().enumerate() {
+        writeln!(
+            writer,
+            "    <node id=\"n{}\"><data key=\"x\">{}</data>\
+             <data key=\"y\">{}</data><data key=\"pos\">{},{}</data></node>",
+            i, p.x, p.y, p.x, p.y
+        )?;
+    }
+    for (e, &(u, v)) in edges.iter().zip(indices) {
+        writeln!(
+            writer,
+            "    <edge source=\"n{}\" target=\"n{}\">\
+             <data key=\"weight\">{}</data></edge>",
+            u,
+            v,
+            e.len()
+        )?;
+    }
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</graphml>")
+}
+
+/// Read the attributes of a node or edge by name.
+fn attributes<'a>(
+    node: roxmltree::Node<'a, '_>,
+    names: &HashMap<&str, &'a str>,
+) -> HashMap<&'a str, &'a str> {
+    node.children()
+        .filter(|c| c.has_tag_name("data"))
+        .filter_map(|c| {
+            let key = c.attribute("key")?;
+            let name = names.get(key).copied().unwrap_or(key);
+            Some((name, c.text().unwrap_or("").trim()))
+        })
+        .collect()
+}
+
+/// Find the position of a node from its attributes.
+fn position(attributes: &HashMap<&str, &str>) -> Option<Vertex> {
+    let (x, y) = match (attributes.get("x"), attributes.get("y")) {
+        (Some(x), Some(y)) => (*x, *y),
+        _ => {
+            let pos = attributes.get("pos")?.trim_end_matches('!');
+            pos.split_once(',')?
+        }
+    };
+    let x: f64 = x.trim().parse().ok()?;
+    let y: f64 = y.trim().parse().ok()?;
+    Some(Vertex::new(x.round() as i32, y.round() as i32))
+}
+
+/// The indices of the nodes at the ends of an edge, and its weight if it
+/// has one
+type Ends = (usize, usize, Option<f32>);
+
+/// Read the points and the ends of the edges of the first graph in a
+/// GraphML document.
+fn read_graph<R>(mut reader: R) -> io::Result<(Vec<Vertex>, Vec<Ends>)>
+where
+    R: Read,
+{
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let document = roxmltree::Document::parse(&text).map_err(invalid)?;
+    let root = document.root_element();
+    if !root.has_tag_name("graphml") {
+        return Err(invalid("not a GraphML document"));
+    }
+    let names: HashMap<&str, &str> = root
+        .children()
+        .filter(|c| c.has_tag_name("key"))
+        .filter_map(|c| Some((c.attribute("id")?, c.attribute("attr.name")?)))
+        .collect();
+    let graph = root
+        .children()
+        .find(|c| c.has_tag_name("graph"))
+        .ok_or_else(|| invalid("GraphML document without a graph"))?;
+
+    let mut points = Vec::new();
+    let mut ids = HashMap::new();
+    for node in graph.children().filter(|c| c.has_tag_name("node")) {
+        let id = node
+            .attribute("id")
+            .ok_or_else(|| invalid("GraphML node without an id"))?;
+        let p = position(&attributes(node, &names))
+            .ok_or_else(|| invalid("GraphML node without a position"))?;
+        ids.insert(id, points.len());
+        points.push(p);
+    }
+
+    let mut edges = Vec::new();
+    for edge in graph.children().filter(|c| c.has_tag_name("edge")) {
+        let end = |name| {
+            edge.attribute(name)
+                .and_then(|id| ids.get(id))
+                .copied()
+                .ok_or_else(|| invalid("GraphML edge to an unknown node"))
+        };
+        let (u, v) = (end("source")?, end("target")?);
+        let attributes = attributes(edge, &names);
+        let weight = attributes.get("weight").or(attributes.get("length"));
+        let weight = match weight.map(|w| w.parse::<f32>()) {
+            Some(Ok(w)) if w.is_finite() && w >= 0.0 => Some(w),
+            Some(_) => return Err(invalid("invalid GraphML edge weight")),
+            None => None,
+        };
+        edges.push((u, v, weight));
+    }
+    Ok((points, edges))
+}
+
+/// Read points from the nodes of a GraphML graph.
+pub fn read_points<R>(reader: R) -> io::Result<Vec<Vertex>>
+where
+    R: Read,
+{
+    read_graph(reader).map(|(points, _)| points)
+}
+
+/// Read edges from a GraphML graph.
+pub fn read_edges<R>(reader: R) -> io::Result<Vec<Edge>>
+where
+    R: Read,
+{
+    let (points, ends) = read_graph(reader)?;
+    Ok(ends
+        .into_iter()
+        .map(|(u, v, weight)| (points[u], points[v], weight))
+        .filter(|(u, v, _)| (u.x, u.y) != (v.x, v.y))
+        .map(|(u, v, weight)| Edge {
+            u,
+            v,
+            length: weight.unwrap_or_else(|| u.distance(&v)),
+        })
+        .collect())
+}
+
+/// Read the end points of every edge of a GraphML graph, copies of a point
+/// included.
+pub fn read_pairs<R>(reader: R) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: Read,
+{
+    let (points, ends) = read_graph(reader)?;
+    Ok(ends
+        .into_iter()
+        .map(|(u, v, _)| (points[u], points[v]))
+        .collect())
+}
+
+/// Read the nodes of a GraphML graph as points, and its edges as the
+/// indices of their end points and their weights, measured if not given.
+/// Unlike `read_edges`, edges between copies of a point are kept, as the
+/// copies are distinct nodes.
+pub fn read_weighted<R>(reader: R) -> io::Result<(Vec<Vertex>, Vec<GraphEdge>)>
+where
+    R: Read,
+{
+    let (points, ends) = read_graph(reader)?;
+    let graph = ends
+        .into_iter()
+        .map(|(u, v, weight)| {
+            (
+                u,
+                v,
+                weight.unwrap_or_else(|| points[u].distance(&points[v])),
+            )
+        })
+        .collect();
+    Ok((points, graph))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(3, 4)];
+        let edges = vec![Edge::new(0, 0, 3, 4)];
+        let mut buffer = Vec::new();
+        write_graph(&mut buffer, &points, &edges).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("<data key=\"pos\">3,4</data>"));
+        assert!(text.contains("<data key=\"weight\">5</data>"));
+        let again = read_points(buffer.as_slice()).unwrap();
+        assert_eq!(again.len(), 3);
+        assert_eq!((again[1].x, again[1].y), (3, 4));
+        let again = read_edges(buffer.as_slice()).unwrap();
+        assert_eq!((again.len(), again[0].len()), (1, 5.0));
+
+        let mut buffer = Vec::new();
+        write_indexed_graph(&mut buffer, &points, &edges, &[(0, 2)]).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("<edge source=\"n0\" target=\"n2\">"));
+    }
+
+    #[test]
+    fn test_read_weighted() {
+        // As NetworkX writes a graph with pos strings and weights
+        let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="pos" attr.type="string"/>
+  <key id="d1" for="edge" attr.name="weight" attr.type="double"/>
+  <graph edgedefault="undirected">
+    <node id="a"><data key="d0">0.4,-0.4</data></node>
+    <node id="b"><data key="d0">6,8</data></node>
+    <node id="c"><data key="d0">6.2,8.1</data></node>
+    <node id="d"><data key="d0">6,0</data></node>
+    <edge source="a" target="b"><data key="d1">2.5</data></edge>
+    <edge source="b" target="c"><data key="d1">1</data></edge>
+    <edge source="a" target="d"/>
+  </graph>
+</graphml>"#;
+        let points = read_points(text.as_bytes()).unwrap();
+        assert_eq!((points[0].x, points[0].y), (0, 0));
+        assert_eq!(points.len(), 4);
+        let edges = read_edges(text.as_bytes()).unwrap();
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [2.5, 6.0]);
+
+        let (points, graph) = read_weighted(text.as_bytes()).unwrap();
+        assert_eq!(points.len(), 4);
+        assert_eq!(graph, [(0, 1, 2.5), (1, 2, 1.0), (0, 3, 6.0)]);
+
+        let bad = text.replace(">2.5<", ">-1<");
+        assert!(read_edges(bad.as_bytes()).is_err());
+        let bad = text.replace(">2.5<", ">inf<");
+        assert!(read_edges(bad.as_bytes()).is_err());
+        let bad = text.replace("target=\"d\"", "target=\"e\"");
+        assert!(read_edges(bad.as_bytes()).is_err());
+        assert!(read_points("<graph/>".as_bytes()).is_err());
+        assert!(read_points("not xml".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! HDBSCAN
+//!
+//! Density based clustering built on the minimum spanning tree of the
+//! mutual reachability distance.
+//!
+//! The core distance of a point is the distance to its k-th nearest
+//! neighbour.  The mutual reachability distance between two points is the
+//! largest of their core distances and the distance between them, which
+//! pushes sparse points away from everything else.  The single-linkage
+//! hierarchy of the mutual reachability tree is condensed by dropping splits
+//! that shed fewer than `min_cluster_size` points, and the most stable
+//! clusters of the condensed tree are selected.  Points not in a selected
+//! cluster are noise.
+
+use crate::cluster::linkage_indexed;
+use crate::{prim, Edge, Vertex};
+
+/// A row of the condensed cluster tree.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Condensed {
+    /// Parent cluster id.  Clusters are numbered from n, the root cluster.
+    pub parent: usize,
+    /// Child cluster id (>= n) or point index (< n)
+    pub child: usize,
+    /// The density (1 / distance) at which the child left the parent
+    pub lambda: f32,
+    /// Number of points in the child
+    pub size: usize,
+}
+
+/// Find the core distance of each point, the distance to its k-th nearest
+/// neighbour (not counting the point itself).
+pub fn core_distances(points: &[Vertex], k: usize) -> Vec<f32> {
+    let mut core = Vec::with_capacity(points.len());
+    let mut d = Vec::with_capacity(points.len());
+    for (i, p) in points.iter().enumerate() {
+        d.clear();
+        for (j, q) in points.iter().enumerate() {
+            if i != j {
+                d.push(p.distance(q));
+            }
+        }
+        if k == 0 || d.is_empty() {
+            core.push(0.0);
+        } else {
+            let k = k.min(d.len()) - 1;
+            let (_, kth, _) = d.select_nth_unstable_by(k, f32::total_cmp);
+            core.push(*kth);
+        }
+    }
+    core
+}
+
+/// Find the minimum spanning tree of a set of points using the mutual
+/// reachability distance with core distances to the k-th nearest neighbour.
+/// The length of each edge is its mutual reachability distance.
+pub fn mutual_reachability_tree(points: &[Vertex], k: usize) -> Vec<Edge> {
+    let core = core_distances(points, k);
+    reachability_tree(points, &core)
+        .into_iter()
+        .map(|(u, v, length)| Edge {
+            u: points[u],
+            v: points[v],
+            length,
+        })
+        .collect()
+}
+
+/// Cluster a set of points with HDBSCAN.  Core distances are to the
+/// `min_samples`-th nearest neighbour and clusters must have at least
+/// `min_cluster_size` points.  Returns a cluster label for each point, or
+/// None for noise.  Labels are numbered from 0 in the order the clusters
+/// first appear in the list of points.
+pub fn hdbscan(
+    points: &[Vertex],
+    min_samples: usize,
+    min_cluster_size: usize,
+) -> Result<Vec<Option<usize>>, &'static str> {
+    if min_cluster_size < 2 {
+        return Err("minimum cluster size must be at least 2");
+    }
+    if points.len() < 2 {
+        return Ok(vec![None; points.len()]);
+    }
+    let core = core_distances(points, min_samples);
+    let tree = reachability_tree(points, &core);
+    let condensed = condensed_tree(points.len(), &tree, min_cluster_size)?;
+    Ok(extract_clusters(points.len(), &condensed))
+}
+
+/// Build the condensed cluster tree from a spanning tree of n points given
+/// as (u, v, length) edges with vertex indices.
+pub fn condensed_tree(
+    n: usize,
+    tree: &[(usize, usize, f32)],
+    min_cluster_size: usize,
+) -> Result<Vec<Condensed>, &'static str> {
+    if tree.len() + 1 != n {
+        return Err("tree does not span the points");
+    }
+    let links = linkage_indexed(n, tree)?;
+    let size = |c: usize| if c < n { 1 } else { links[c - n].size };
+
+    let mut rows = Vec::new();
+    // Single-linkage nodes to visit with the condensed cluster they are in
+    let root = 2 * n - 2;
+    let mut stack = vec![(root, n)];
+    let mut next_cluster = n + 1;
+    while let Some((node, cluster)) = stack.pop() {
+        let link = links[node - n];
+        // Repeated points are zero distance apart, keep lambda finite
+        let lambda = 1.0 / link.distance.max(f32::EPSILON);
+        let (a, b) = (link.a, link.b);
+        let a_big = size(a) >= min_cluster_size;
+        let b_big = size(b) >= min_cluster_size;
+        if a_big && b_big {
+            // A true split, both children become new clusters
+            for c in [a, b] {
+                rows.push(Condensed {
+                    parent: cluster,
+                    child: next_cluster,
+                    lambda,
+                    size: size(c),
+                });
+                if c >= n {
+                    stack.push((c, next_cluster));
+                }
+                next_cluster += 1;
+            }
+        } else {
+            // Small children shed their points, a large one carries on
+            for (c, big) in [(a, a_big), (b, b_big)] {
+                if big {
+                    stack.push((c, cluster));
+                } else {
+                    for p in leaves(&links, n, c) {
+                        rows.push(Condensed {
+                            parent: cluster,
+                            child: p,
+                            lambda,
+                            size: 1,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Select the most stable clusters of a condensed tree and label the points
+/// in them.  The root cluster is never selected.
+fn extract_clusters(n: usize, condensed: &[Condensed]) -> Vec<Option<usize>> {
+    let num_clusters = condensed
+        .iter()
+        .map(|r| r.child.max(r.parent) + 1)
+        .max()
+        .unwrap_or(n + 1)
+        - n;
+    // Density at which each cluster appears and its parent cluster
+    let mut birth = vec![0.0f32; num_clusters];
+    let mut parent = vec![usize::MAX; num_clusters];
+    for r in condensed.iter().filter(|r| r.child >= n) {
+        birth[r.child - n] = r.lambda;
+        parent[r.child - n] = r.parent - n;
+    }
+    let mut stability = vec![0.0f32; num_clusters];
+    for r in condensed {
+        let c = r.parent - n;
+        stability[c] += (r.lambda - birth[c]) * r.size as f32;
+    }
+
+    // Children are numbered after their parents, so working backwards visits
+    // every cluster after all of its descendants.
+    let mut selected = vec![true; num_clusters];
+    selected[0] = false;
+    let mut subtree = vec![0.0f32; num_clusters];
+    for c in (1..num_clusters).rev() {
+        if subtree[c] > stability[c] {
+            selected[c] = false;
+            stability[c] = subtree[c];
+        }
+        subtree[parent[c]] += stability[c];
+    }
+    // Keep only the highest selected cluster on each branch
+    for c in 1..num_clusters {
+        let mut a = parent[c];
+        while a != 0 {
+            if selected[a] {
+                selected[c] = false;
+                break;
+            }
+            a = parent[a];
+        }
+    }
+
+    let mut labels = vec![None; n];
+    let mut cluster_label = vec![usize::MAX; num_clusters];
+    let mut next = 0;
+    let mut owner = vec![0; n];
+    for r in condensed.iter().filter(|r| r.child < n) {
+        owner[r.child] = r.parent - n;
+    }
+    for (p, label) in labels.iter_mut().enumerate() {
+        let mut c = owner[p];
+        while c != 0 && !selected[c] {
+            c = parent[c];
+        }
+        if selected[c] {
+            if cluster_label[c] == usize::MAX {
+                cluster_label[c] = next;
+                next += 1;
+            }
+            *label = Some(cluster_label[c]);
+        }
+    }
+    labels
+}
+
+/// Find the points under a node of a single-linkage hierarchy.
+fn leaves(links: &[crate::Link], n: usize, node: usize) -> Vec<usize> {
+    let mut points = Vec::new();
+    let mut stack = vec![node];
+    while let Some(c) = stack.pop() {
+        if c < n {
+            points.push(c);
+        } else {
+            stack.push(links[c - n].a);
+            stack.push(links[c - n].b);
+        }
+    }
+    points
+}
+
+/// Prim's algorithm over the mutual reachability distance.
+/// Returns the tree as (u, v, length) edges with vertex indices.
+fn reachability_tree(
+    points: &[Vertex],
+    core: &[f32],
+) -> Vec<(usize, usize, f32)> {
+    let reach = |u: usize, v: usize| {
+        points[u].distance(&points[v]).max(core[u]).max(core[v])
+    };
+    prim(points.len(), reach)
+        .into_iter()
+        .map(|(u, v)| (u, v, reach(u, v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two tight groups of points and a few scattered points
+    fn blobs() -> Vec<Vertex> {
+        let mut points = Vec::new();
+        for (cx, cy) in [(10, 10), (80, 80)] {
+            for dx in 0..4 {
+                for dy in 0..4 {
+                    points.push(Vertex::new(cx + dx * 2, cy + dy * 2));
+                }
+            }
+        }
+        points.push(Vertex::new(150, 10));
+        points.push(Vertex::new(10, 150));
+        points
+    }
+
+    #[test]
+    fn test_core_distances() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(6, 8)];
+        assert_eq!(core_distances(&points, 1), vec![5.0, 5.0, 5.0]);
+        assert_eq!(core_distances(&points, 2), vec![10.0, 5.0, 10.0]);
+        // k larger than the number of neighbours uses the farthest
+        assert_eq!(core_distances(&points, 5), vec![10.0, 5.0, 10.0]);
+    }
+
+    #[test]
+    fn test_mutual_reachability_tree() {
+        let points = blobs();
+        let tree = mutual_reachability_tree(&points, 3);
+        assert_eq!(tree.len(), points.len() - 1);
+        let core = core_distances(&points, 3);
+        for e in &tree {
+            assert!(e.len() >= e.u.distance(&e.v));
+        }
+        // Never shorter than the plain minimum spanning tree
+        let mst = crate::minimum_spanning_tree(&points).unwrap();
+        let a: f32 = mst.iter().map(|e| e.len()).sum();
+        let b: f32 = tree.iter().map(|e| e.len()).sum();
+        assert!(b >= a);
+        assert!(core.iter().all(|&c| c > 0.0));
+    }
+
+    #[test]
+    fn test_hdbscan() {
+        let points = blobs();
+        let labels = hdbscan(&points, 3, 5).unwrap();
+        assert!(labels[..16].iter().all(|&l| l == Some(0)));
+        assert!(labels[16..32].iter().all(|&l| l == Some(1)));
+        assert_eq!(labels[32], None);
+        assert_eq!(labels[33], None);
+    }
+
+    #[test]
+    fn test_hdbscan_small() {
+        assert!(hdbscan(&blobs(), 3, 1).is_err());
+        assert_eq!(hdbscan(&[Vertex::new(1, 1)], 3, 5), Ok(vec![None]));
+    }
+
+    #[test]
+    fn test_condensed_tree() {
+        // Two pairs of points joined by a long edge
+        let tree = vec![(0, 1, 1.0), (2, 3, 1.0), (1, 2, 10.0)];
+        let rows = condensed_tree(4, &tree, 2).unwrap();
+        assert_eq!(rows.len(), 6);
+        // The root splits into two clusters at lambda 0.1
+        assert_eq!(
+            rows[0],
+            Condensed {
+                parent: 4,
+                child: 5,
+                lambda: 0.1,
+                size: 2
+            }
+        );
+        assert_eq!(
+            rows[1],
+            Condensed {
+                parent: 4,
+                child: 6,
+                lambda: 0.1,
+                size: 2
+            }
+        );
+        // Each cluster sheds its points at lambda 1
+        assert!(rows[2..].iter().all(|r| r.lambda == 1.0 && r.size == 1));
+    }
+}
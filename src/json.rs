@@ -0,0 +1,405 @@
+//! JSON Files
+//!
+//! Read and write points and trees as JSON for web tools, either plain or
+//! as GeoJSON.
+//!
+//! Plain points are an array of `Vertex` objects (`{"x": 1, "y": 2}`) and a
+//! tree is a `Tree` object holding its points, its edges (`{"u": .., "v":
+//! .., "length": ..}`) and its total length.  Either is accepted where
+//! points are read, and a tree or an array of edges where edges are read.
+//! Read edges are measured, whatever length is given, and edges between
+//! copies of a point are skipped.
+//!
+//! GeoJSON points are a `FeatureCollection` of `Point`s and a tree is a
+//! `FeatureCollection` of `LineString`s with a `length` property.  When
+//! read, each `LineString` gives an edge between each pair of consecutive
+//! positions, the outer ring of each `Polygon` and `MultiPolygon` part gives
+//! an obstacle, other geometries are ignored, and coordinates are rounded
+//! to the nearest integer.
+
+use crate::obstacles::Polygon;
+use crate::{pair_edges, Edge, Vertex};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::io::{self, Read, Write};
+
+/// A spanning tree of a set of points
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tree {
+    /// The points
+    pub points: Vec<Vertex>,
+    /// The edges between them
+    pub edges: Vec<Edge>,
+    /// Total length of the edges
+    pub length: f32,
+}
+
+impl Tree {
+    /// Create a tree from its points and edges.
+    pub fn new(points: &[Vertex], edges: &[Edge]) -> Self {
+        Self {
+            points: points.to_vec(),
+            edges: edges.to_vec(),
+            length: edges.iter().map(|e| e.len()).sum(),
+        }
+    }
+}
+
+/// Points, as a bare array or from a tree
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Points {
+    Points(Vec<Vertex>),
+    Tree { points: Vec<Vertex> },
+}
+
+/// The end points of an edge as read, which may be copies of a point
+#[derive(Deserialize)]
+struct Ends {
+    u: Vertex,
+    v: Vertex,
+}
+
+/// Edges, as a bare array or from a tree
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Edges {
+    Edges(Vec<Ends>),
+    Tree { edges: Vec<Ends> },
+}
+
+/// A GeoJSON feature collection
+#[derive(Serialize, Deserialize)]
+struct FeatureCollection {
+    #[serde(rename = "type")]
+    kind: String,
+    features: Vec<Feature>,
+}
+
+/// A GeoJSON feature
+#[derive(Serialize, Deserialize)]
+struct Feature {
+    #[serde(rename = "type")]
+    kind: String,
+    geometry: Option<Geometry>,
+    #[serde(default)]
+    properties: Option<Map<String, Value>>,
+}
+
+/// The GeoJSON geometries read and written
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    Point {
+        coordinates: Vec<f64>,
+    },
+    LineString {
+        coordinates: Vec<Vec<f64>>,
+    },
+    Polygon {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Vec<f64>>>>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Convert a serde error to an IO error.
+fn invalid(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Write points as a JSON array.
+pub fn write_points<W>(writer: &mut W, points: &[Vertex]) -> io::Result<()>
+where
+    W: Write,
+{
+    serde_json::to_writer_pretty(&mut *writer, points).map_err(invalid)?;
+    writeln!(writer)
+}
+
+/// Write a tree as a JSON object.
+pub fn write_tree<W>(writer: &mut W, tree: &Tree) -> io::Result<()>
+where
+    W: Write,
+{
+    serde_json::to_writer_pretty(&mut *writer, tree).map_err(invalid)?;
+    writeln!(writer)
+}
+
+/// Read points from a JSON array of points or a tree.
+pub fn read_points<R>(reader: R) -> io::Result<Vec<Vertex>>
+where
+    R: Read,
+{
+    match serde_json::from_reader(reader).map_err(invalid)? {
+        Points::Points(points) => Ok(points),
+        Points::Tree { points } => Ok(points),
+    }
+}
+
+/// Read the end points of every edge of a JSON array of edges or a tree,
+/// copies of a point included.
+pub fn read_pairs<R>(reader: R) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: Read,
+{
+    let ends = match serde_json::from_reader(reader).map_err(invalid)? {
+        Edges::Edges(ends) => ends,
+        Edges::Tree { edges } => edges,
+    };
+    Ok(ends.into_iter().map(|e| (e.u, e.v)).collect())
+}
+
+/// Read edges from a JSON array of edges or a tree.  Edges between copies
+/// of a point are skipped.
+pub fn read_edges<R>(reader: R) -> io::Result<Vec<Edge>>
+where
+    R: Read,
+{
+    read_pairs(reader).map(pair_edges)
+}
+
+/// Write a GeoJSON feature collection.
+fn write_features<W>(writer: &mut W, features: Vec<Feature>) -> io::Result<()>
+where
+    W: Write,
+{
+    let collection = FeatureCollection {
+        kind: "FeatureCollection".to_string(),
+        features,
+    };
+    serde_json::to_writer_pretty(&mut *writer, &collection).map_err(invalid)?;
+    writeln!(writer)
+}
+
+/// Make a GeoJSON feature.
+fn feature(geometry: Geometry, properties: Map<String, Value>) -> Feature {
+    Feature {
+        kind: "Feature".to_string(),
+        geometry: Some(geometry),
+        properties: Some(properties),
+    }
+}
+
+/// Write points as a GeoJSON collection of Points.
+pub fn write_geo_points<W>(writer: &mut W, points: &[Vertex]) -> io::Result<()>
+where
+    W: Write,
+{
+    let features = points
+        .iter()
+        .map(|p| {
+            let coordinates = vec![p.x as f64, p.y as f64];
+            feature(Geometry::Point { coordinates }, Map::new())
+        })
+        .collect();
+    write_features(writer, features)
+}
+
+/// Write edges as a GeoJSON collection of LineStrings, each with its
+/// length.
+pub fn write_geo_edges<W>(writer: &mut W, edges: &[Edge]) -> io::Result<()>
+where
+    W: Write,
+{
+    let features = edges
+        .iter()
+        .map(|e| {
+            let coordinates = vec![
+                vec![e.u.x as f64, e.u.y as f64],
+                vec![e.v.x as f64, e.v.y as f64],
+            ];
+            let mut properties = Map::new();
+            properties.insert("length".to_string(), json!(e.len()));
+            feature(Geometry::LineString { coordinates }, properties)
+        })
+        .collect();
+    write_features(writer, features)
+}
+
+/// Read the geometries of a GeoJSON feature collection.
+fn read_geometries<R>(reader: R) -> io::Result<Vec<Geometry>>
+where
+    R: Read,
+{
+    let collection: FeatureCollection =
+        serde_json::from_reader(reader).map_err(invalid)?;
+    Ok(collection
+        .features
+        .into_iter()
+        .filter_map(|f| f.geometry)
+        .collect())
+}
+
+/// Round a GeoJSON position to a vertex.
+fn position(coordinates: &[f64]) -> Option<Vertex> {
+    match coordinates {
+        [x, y, ..] => Some(Vertex::new(x.round() as i32, y.round() as i32)),
+        _ => None,
+    }
+}
+
+/// Read points from the Points of a GeoJSON feature collection.
+pub fn read_geo_points<R>(reader: R) -> io::Result<Vec<Vertex>>
+where
+    R: Read,
+{
+    Ok(read_geometries(reader)?
+        .iter()
+        .filter_map(|g| match g {
+            Geometry::Point { coordinates } => position(coordinates),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Read obstacles from the outer rings of the Polygons and MultiPolygons of
+/// a GeoJSON feature collection, without the closing position.  Rings of
+/// fewer than three points are skipped.
+pub fn read_geo_polygons<R>(reader: R) -> io::Result<Vec<Polygon>>
+where
+    R: Read,
+{
+    let mut rings = Vec::new();
+    for g in read_geometries(reader)? {
+        match g {
+            Geometry::Polygon { mut coordinates }
+                if !coordinates.is_empty() =>
+            {
+                rings.push(coordinates.swap_remove(0))
+            }
+            Geometry::MultiPolygon { coordinates } => {
+                rings.extend(coordinates.into_iter().filter_map(|mut p| {
+                    (!p.is_empty()).then(|| p.swap_remove(0))
+                }))
+            }
+            _ => {}
+        }
+    }
+    let mut polygons = Vec::new();
+    for ring in rings {
+        let ring = ring.iter().filter_map(|c| position(c)).collect();
+        if let Ok(polygon) = Polygon::from_ring(ring) {
+            polygons.push(polygon);
+        }
+    }
+    Ok(polygons)
+}
+
+/// Read the end points of every segment of the LineStrings of a GeoJSON
+/// feature collection, copies of a point included.
+pub fn read_geo_pairs<R>(reader: R) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: Read,
+{
+    let mut pairs = Vec::new();
+    for g in read_geometries(reader)? {
+        if let Geometry::LineString { coordinates } = g {
+            let line: Vec<Vertex> =
+                coordinates.iter().filter_map(|c| position(c)).collect();
+            pairs.extend(line.windows(2).map(|pair| (pair[0], pair[1])));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Read edges from the LineStrings of a GeoJSON feature collection.
+/// Segments between copies of a point are skipped.
+pub fn read_geo_edges<R>(reader: R) -> io::Result<Vec<Edge>>
+where
+    R: Read,
+{
+    read_geo_pairs(reader).map(pair_edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(-3, 12)];
+        let mut buffer = Vec::new();
+        write_points(&mut buffer, &points).unwrap();
+        let again = read_points(buffer.as_slice()).unwrap();
+        assert_eq!((again.len(), again[2].x), (3, -3));
+
+        let edges = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, -3, 12)];
+        let mut buffer = Vec::new();
+        write_tree(&mut buffer, &Tree::new(&points, &edges)).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("\"length\": 15.0"));
+        let again = read_edges(buffer.as_slice()).unwrap();
+        assert_eq!(again.len(), 2);
+        assert_eq!(again[1].len(), 10.0);
+        // Points are read from a tree too
+        assert_eq!(read_points(buffer.as_slice()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_read_edges_measures() {
+        let text = r#"[{"u": {"x": 0, "y": 0}, "v": {"x": 3, "y": 4}},
+                       {"u": {"x": 0, "y": 0}, "v": {"x": 0, "y": 0}},
+                       {"u": {"x": 0, "y": 0}, "v": {"x": 1, "y": 1},
+                        "length": 7.5},
+                       {"u": {"x": 1, "y": 1}, "v": {"x": 1, "y": 2},
+                        "length": -1}]"#;
+        let edges = read_edges(text.as_bytes()).unwrap();
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [5.0, 2f32.sqrt(), 1.0]);
+        assert!(read_edges("[{\"u\": 1}]".as_bytes()).is_err());
+
+        // An Edge itself can't join copies of a point
+        let copies = r#"{"u": {"x": 1, "y": 1}, "v": {"x": 1, "y": 1}}"#;
+        assert!(serde_json::from_str::<Edge>(copies).is_err());
+        let edge: Edge = serde_json::from_str(
+            r#"{"u": {"x": 0, "y": 0}, "v": {"x": 3, "y": 4}, "length": 9}"#,
+        )
+        .unwrap();
+        assert_eq!(edge.len(), 5.0);
+    }
+
+    #[test]
+    fn test_geojson_round_trip() {
+        let points = vec![Vertex::new(1, 2), Vertex::new(4, 6)];
+        let mut buffer = Vec::new();
+        write_geo_points(&mut buffer, &points).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("\"FeatureCollection\""));
+        assert!(text.contains("\"Point\""));
+        let again = read_geo_points(buffer.as_slice()).unwrap();
+        assert_eq!((again[1].x, again[1].y), (4, 6));
+
+        let edges = vec![Edge::new(1, 2, 4, 6)];
+        let mut buffer = Vec::new();
+        write_geo_edges(&mut buffer, &edges).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.contains("\"LineString\""));
+        assert!(text.contains("\"length\": 5.0"));
+        let again = read_geo_edges(buffer.as_slice()).unwrap();
+        assert_eq!((again.len(), again[0].len()), (1, 5.0));
+    }
+
+    #[test]
+    fn test_read_geojson() {
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "a"},
+             "geometry": {"type": "Point", "coordinates": [1.4, 2.6, 9]}},
+            {"type": "Feature", "properties": null,
+             "geometry": {"type": "Polygon", "coordinates": [[[0, 0]]]}},
+            {"type": "Feature", "geometry": null},
+            {"type": "Feature", "geometry": {"type": "LineString",
+             "coordinates": [[0, 0], [3, 4], [3, 4], [3, 0]]}}
+        ]}"#;
+        let points = read_geo_points(text.as_bytes()).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!((points[0].x, points[0].y), (1, 3));
+        let edges = read_geo_edges(text.as_bytes()).unwrap();
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [5.0, 4.0]);
+    }
+}
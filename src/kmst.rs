@@ -0,0 +1,338 @@
+//! k-Minimum Spanning Tree
+//!
+//! Find a short tree spanning exactly k of a set of points, e.g. to choose
+//! a subset of sensors to connect.  The k-MST problem is NP-hard.
+//!
+//! When there are few enough subsets of k points every one is tried, taking
+//! the minimum spanning tree of each, which gives the optimal tree.
+//! Otherwise Prim's algorithm is grown for k - 1 edges from points spread
+//! through the set, keeping the shortest tree, which is then improved by
+//! swapping a point in the tree for one outside it while that shortens the
+//! tree.  Swaps are scored without rebuilding the tree, from what removing
+//! a point saves and what joining the new point to its nearest chosen point
+//! costs, so each round takes O(nk) and only the swap made rebuilds it.
+//!
+//! Trees are given by the indices of the chosen points and the edges of
+//! their minimum spanning tree.
+
+use crate::{index_edges, prim, Edge, Vertex};
+use crate::{minimum_spanning_tree_steps, minimum_spanning_tree_steps_from};
+
+/// Most subsets of k points searched exhaustively
+const EXACT_SUBSETS: usize = 20_000;
+
+/// Smallest change in length counted as an improvement, so rounding can't
+/// make the swaps cycle.
+const IMPROVEMENT: f32 = 1e-4;
+
+/// Most starting points for growing Prim's algorithm
+const MAX_STARTS: usize = 64;
+
+/// Most swaps made improving the tree
+const MAX_SWAPS: usize = 256;
+
+/// A tree spanning k of the points
+#[derive(Clone)]
+pub struct KTree {
+    /// Indices of the chosen points, in increasing order
+    pub points: Vec<usize>,
+    /// Edges of the minimum spanning tree of the chosen points
+    pub edges: Vec<Edge>,
+    /// Indices into all the points of the end points of each edge
+    pub ends: Vec<(usize, usize)>,
+}
+
+impl KTree {
+    /// Get the total length of the tree.
+    pub fn len(&self) -> f32 {
+        self.edges.iter().map(|e| e.len()).sum()
+    }
+
+    /// Check if the tree has no edges.
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+/// Find a short tree spanning exactly k of the points.  The tree is optimal
+/// if there are few enough subsets of k points to try them all.
+pub fn k_mst(points: &[Vertex], k: usize) -> Result<KTree, &'static str> {
+    check(points, k)?;
+    if choose(points.len(), k) <= EXACT_SUBSETS {
+        k_mst_exact(points, k)
+    } else {
+        k_mst_heuristic(points, k)
+    }
+}
+
+/// Find the shortest tree spanning exactly k of the points by trying every
+/// subset of k points.  Takes time exponential in k.
+pub fn k_mst_exact(points: &[Vertex], k: usize) -> Result<KTree, &'static str> {
+    check(points, k)?;
+    let n = points.len();
+    let mut subset: Vec<usize> = (0..k).collect();
+    let mut best = subset_tree(points, &subset);
+    loop {
+        // Advance to the next subset in lexicographic order
+        let mut i = k;
+        while i > 0 && subset[i - 1] == n - k + i - 1 {
+            i -= 1;
+        }
+        if i == 0 {
+            break;
+        }
+        subset[i - 1] += 1;
+        for j in i..k {
+            subset[j] = subset[j - 1] + 1;
+        }
+        let tree = subset_tree(points, &subset);
+        if tree.len() < best.len() {
+            best = tree;
+        }
+    }
+    Ok(best)
+}
+
+/// Find a short tree spanning exactly k of the points by growing Prim's
+/// algorithm from up to `MAX_STARTS` points, then making up to `MAX_SWAPS`
+/// swaps of points in and out of the best tree.
+pub fn k_mst_heuristic(
+    points: &[Vertex],
+    k: usize,
+) -> Result<KTree, &'static str> {
+    check(points, k)?;
+    let n = points.len();
+
+    // The shortest partial Prim tree over the starting points
+    let mut best: Option<(f32, usize)> = None;
+    for start in (0..n).step_by(n.div_ceil(MAX_STARTS)) {
+        let length: f32 = minimum_spanning_tree_steps_from(points, start)
+            .max_edges(k - 1)
+            .map(|(e, _)| e.len())
+            .sum();
+        if best.is_none_or(|(shortest, _)| length < shortest) {
+            best = Some((length, start));
+        }
+    }
+    let start = best.map_or(0, |(_, start)| start);
+    let mut steps =
+        minimum_spanning_tree_steps_from(points, start).max_edges(k - 1);
+    steps.by_ref().count();
+    let mut chosen: Vec<bool> = (0..n).map(|i| steps.in_tree(i)).collect();
+    let mut tree = subset_tree(points, &indices(&chosen));
+
+    // Swap a chosen point for another while the tree gets shorter
+    for _ in 0..MAX_SWAPS {
+        let (out, into) = match best_swap(points, &chosen, &tree) {
+            Some(swap) => swap,
+            None => break,
+        };
+        chosen[out] = false;
+        chosen[into] = true;
+        tree = subset_tree(points, &indices(&chosen));
+    }
+    Ok(tree)
+}
+
+/// Find the swap of a chosen point for one outside the tree that shortens
+/// the tree most, without rebuilding it.  Removing a point saves its edges
+/// less the minimum spanning tree of its neighbours, which joins the rest
+/// of the tree back up, and adding a point costs its distance to the
+/// nearest other chosen point.  Both are bounds, so the tree after the swap
+/// is at least as short as scored.  Returns None if no swap shortens the
+/// tree.
+fn best_swap(
+    points: &[Vertex],
+    chosen: &[bool],
+    tree: &KTree,
+) -> Option<(usize, usize)> {
+    let distance = |u: usize, v: usize| points[u].distance(&points[v]);
+
+    // What removing each chosen point saves
+    let mut adjacent = vec![Vec::new(); points.len()];
+    let mut saved = vec![0.0; points.len()];
+    for (e, &(u, v)) in tree.edges.iter().zip(&tree.ends) {
+        adjacent[u].push(v);
+        adjacent[v].push(u);
+        saved[u] += e.len();
+        saved[v] += e.len();
+    }
+    for &c in &tree.points {
+        let near = &adjacent[c];
+        if near.len() > 1 {
+            let rejoin: f32 =
+                prim(near.len(), |a, b| distance(near[a], near[b]))
+                    .into_iter()
+                    .map(|(a, b)| distance(near[a], near[b]))
+                    .sum();
+            saved[c] -= rejoin;
+        }
+    }
+
+    // Join each point outside to its nearest chosen point, or the second
+    // nearest in place of that one
+    let mut best: Option<(f32, usize, usize)> = None;
+    for into in (0..points.len()).filter(|&i| !chosen[i]) {
+        let (mut first, mut d1, mut d2) =
+            (usize::MAX, f32::INFINITY, f32::INFINITY);
+        for &c in &tree.points {
+            let d = distance(into, c);
+            if d < d1 {
+                (first, d1, d2) = (c, d, d1);
+            } else if d < d2 {
+                d2 = d;
+            }
+        }
+        for &out in &tree.points {
+            let cost = if out == first { d2 } else { d1 };
+            let gain = saved[out] - cost;
+            if gain > IMPROVEMENT && best.is_none_or(|(most, _, _)| gain > most)
+            {
+                best = Some((gain, out, into));
+            }
+        }
+    }
+    best.map(|(_, out, into)| (out, into))
+}
+
+/// Check that a tree of k points can be found.
+fn check(points: &[Vertex], k: usize) -> Result<(), &'static str> {
+    if k == 0 {
+        return Err("k must be at least one");
+    }
+    if k > points.len() {
+        return Err("k is more than the number of points");
+    }
+    Ok(())
+}
+
+/// Find the minimum spanning tree of a subset of the points.
+fn subset_tree(points: &[Vertex], subset: &[usize]) -> KTree {
+    let vertices: Vec<Vertex> = subset.iter().map(|&i| points[i]).collect();
+    let ends: Vec<(usize, usize)> = minimum_spanning_tree_steps(&vertices)
+        .indices()
+        .map(|(u, v)| (subset[u], subset[v]))
+        .collect();
+    KTree {
+        points: subset.to_vec(),
+        edges: index_edges(points, &ends),
+        ends,
+    }
+}
+
+/// List the indices of the chosen points.
+fn indices(chosen: &[bool]) -> Vec<usize> {
+    (0..chosen.len()).filter(|&i| chosen[i]).collect()
+}
+
+/// Count the subsets of k of n things, saturating.
+fn choose(n: usize, k: usize) -> usize {
+    let k = k.min(n - k);
+    let mut c: usize = 1;
+    for i in 0..k {
+        c = match c.checked_mul(n - i) {
+            Some(c) => c / (i + 1),
+            None => return usize::MAX,
+        };
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, minimum_spanning_tree};
+
+    /// Shortest k-tree by brute force over bit sets of the points
+    fn brute_force(points: &[Vertex], k: usize) -> f32 {
+        let n = points.len();
+        let mut best = f32::INFINITY;
+        for m in 0usize..1 << n {
+            if m.count_ones() as usize != k {
+                continue;
+            }
+            let subset: Vec<Vertex> = (0..n)
+                .filter(|i| m & 1 << i != 0)
+                .map(|i| points[i])
+                .collect();
+            let length: f32 = minimum_spanning_tree(&subset)
+                .unwrap()
+                .iter()
+                .map(|e| e.len())
+                .sum();
+            best = best.min(length);
+        }
+        best
+    }
+
+    #[test]
+    fn test_k_mst_errors() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(1, 1)];
+        assert!(k_mst(&points, 0).is_err());
+        assert!(k_mst(&points, 3).is_err());
+        let one = k_mst(&points, 1).unwrap();
+        assert_eq!(one.points.len(), 1);
+        assert!(one.is_empty());
+    }
+
+    #[test]
+    fn test_k_mst_cluster() {
+        // A tight cluster away from scattered points
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(100, 0),
+            Vertex::new(50, 1),
+            Vertex::new(52, 2),
+            Vertex::new(0, 100),
+            Vertex::new(51, 3),
+            Vertex::new(100, 100),
+        ];
+        let tree = k_mst(&points, 3).unwrap();
+        assert_eq!(tree.points, vec![2, 3, 5]);
+        assert_eq!(tree.edges.len(), 2);
+        let heuristic = k_mst_heuristic(&points, 3).unwrap();
+        assert_eq!(heuristic.points, vec![2, 3, 5]);
+    }
+
+    #[test]
+    fn test_k_mst_brute_force() {
+        for seed in 0..5 {
+            let points = generate(10, 1.0, seed * 7, 0, 100, 100).unwrap();
+            for k in 2..=points.len() {
+                let best = brute_force(&points, k);
+                let exact = k_mst_exact(&points, k).unwrap();
+                assert_eq!(exact.points.len(), k);
+                assert_eq!(exact.edges.len(), k - 1);
+                assert!((exact.len() - best).abs() < 1e-3);
+                // The heuristic is never shorter than optimal
+                let heuristic = k_mst_heuristic(&points, k).unwrap();
+                assert_eq!(heuristic.points.len(), k);
+                assert!(heuristic.len() >= best - 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_mst_large() {
+        let points = generate(3000, 1.0, 0, 0, 1000, 1000).unwrap();
+        let start = std::time::Instant::now();
+        let tree = k_mst(&points, 300).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(20));
+        assert_eq!(tree.points.len(), 300);
+        assert_eq!(tree.edges.len(), 299);
+        // No longer than the tree of the first point's 299 nearest
+        let prim: f32 = minimum_spanning_tree_steps(&points)
+            .max_edges(299)
+            .map(|(e, _)| e.len())
+            .sum();
+        assert!(tree.len() <= prim + 1e-3);
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(choose(10, 3), 120);
+        assert_eq!(choose(10, 10), 1);
+        assert_eq!(choose(52, 5), 2_598_960);
+        assert_eq!(choose(1000, 500), usize::MAX);
+    }
+}
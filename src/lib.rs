@@ -1,12 +1,44 @@
 /// Minimum Spanning Tree library
 extern crate image;
 extern crate imageproc;
+use image::gif;
 use image::Rgb;
 use image::RgbImage;
-use imageproc::drawing::{draw_filled_rect_mut, draw_line_segment_mut};
+use imageproc::drawing::{
+    draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut,
+};
 use imageproc::rect::Rect;
 
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+
+pub mod binary;
+pub mod bottleneck;
+pub mod cluster;
+pub mod colormap;
+pub mod csv;
+mod disjoint_set;
+pub mod dot;
+pub mod format;
+pub mod graphml;
+pub mod hdbscan;
+pub mod json;
+pub mod kmst;
+pub mod obstacles;
+pub mod rectilinear;
+pub mod sensitivity;
+pub mod stats;
+pub mod steiner;
+pub mod tree;
+pub mod tsp;
+pub mod verify;
+pub mod wkt;
+pub use cluster::Link;
+pub use colormap::Colormap;
 
 // Table markers
 const FMARK: f32 = f32::INFINITY;
@@ -18,13 +50,54 @@ pub const MINIMUM_MIN_DISTANCE: f32 = 1.0;
 pub const MINIMUM_WIDTH: i32 = 10;
 pub const MINIMUM_HEIGHT: i32 = 10;
 
+// Plot colours
+const WHITE: Rgb<u8> = Rgb([255u8, 255u8, 255u8]);
+const BLACK: Rgb<u8> = Rgb([0u8, 0u8, 0u8]);
+const RED: Rgb<u8> = Rgb([220u8, 0u8, 0u8]);
+const LIGHT_GRAY: Rgb<u8> = Rgb([200u8, 200u8, 200u8]);
+const DARK_GRAY: Rgb<u8> = Rgb([100u8, 100u8, 100u8]);
+
+/// Delay between animation frames (1/100 s)
+const ANIMATION_DELAY: u16 = 10;
+/// Delay on the last animation frame (1/100 s)
+const ANIMATION_FINAL_DELAY: u16 = 300;
+
+/// Space around the graph in a plot
+const PLOT_MARGIN: i32 = 10;
+/// Height of the colour bar legend below the graph
+const LEGEND_HEIGHT: i32 = 24;
+/// Height of the dendrogram, without margins
+const DENDROGRAM_HEIGHT: i32 = 300;
+/// Approximate width of the dendrogram leaves, without margins
+const DENDROGRAM_WIDTH: i32 = 800;
+/// Width of the dendrogram distance axis and its labels
+const DENDROGRAM_AXIS: i32 = 56;
+
+// Label glyphs, 3x5 pixels, one row per byte, drawn at GLYPH_SCALE
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_SCALE: i32 = 2;
+const GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const GLYPH_DOT: [u8; 5] = [0b000, 0b000, 0b000, 0b000, 0b010];
+const GLYPH_MINUS: [u8; 5] = [0b000, 0b000, 0b111, 0b000, 0b000];
+
 /// CSV File field separator
 pub const FIELD_SEPARATOR: &str = "\t";
 /// CSV File record separator
 pub const RECORD_SEPARATOR: &str = "\n";
 
 /// A 2D Vertex
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Vertex {
     pub x: i32,
     pub y: i32,
@@ -43,13 +116,33 @@ impl Vertex {
 }
 
 /// A graph edge
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(try_from = "EdgeFields")]
 pub struct Edge {
     pub u: Vertex,
     pub v: Vertex,
     length: f32,
 }
 
+/// The fields of a serialised Edge.  A length is not read, the edge is
+/// measured.
+#[derive(Deserialize)]
+struct EdgeFields {
+    u: Vertex,
+    v: Vertex,
+}
+
+impl TryFrom<EdgeFields> for Edge {
+    type Error = &'static str;
+
+    fn try_from(e: EdgeFields) -> Result<Self, Self::Error> {
+        if (e.u.x, e.u.y) == (e.v.x, e.v.y) {
+            return Err("edge between copies of a point");
+        }
+        Ok(Self::from_vertices(e.u, e.v))
+    }
+}
+
 impl Edge {
     /// Create a new Edge from two coordinate pairs.
     pub fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
@@ -77,30 +170,6 @@ impl Edge {
     }
 }
 
-/// Vertex Table Item
-#[derive(Copy, Clone)]
-struct Item {
-    /// Index is the position in the Vec
-    index: usize,
-    /// Index of the nearest Vertex
-    near: usize,
-    /// Distance to the nearest Vertex
-    cost: f32,
-    /// This Vertex
-    vertex: Vertex,
-}
-
-impl Item {
-    pub fn new(index: usize, vertex: Vertex) -> Self {
-        Self {
-            index,
-            near: UMARK,
-            cost: FMARK,
-            vertex,
-        }
-    }
-}
-
 /// Generate a set of N points within the rectangle bound by min_x, min_y,
 /// max_x, max_y with a min_d minimum distance between points.
 pub fn generate(
@@ -144,10 +213,10 @@ pub fn generate(
 }
 
 /// Find the minimum distance between a point and a set of points.
-fn minimum_distance(v: &Vertex, points: &Vec<Vertex>) -> f32 {
+fn minimum_distance(v: &Vertex, points: &[Vertex]) -> f32 {
     let mut min_d = FMARK;
     for p in points {
-        let d = v.distance(&p);
+        let d = v.distance(p);
         if d < min_d {
             min_d = d;
         }
@@ -163,112 +232,1031 @@ fn minimum_distance(v: &Vertex, points: &Vec<Vertex>) -> f32 {
 ///    and add it to the tree.
 /// 3. Repeat #2 until all points are in the tree.
 ///
+/// Each edge runs from the newly added point to the tree point it joins,
+/// in the order they join, as `prim` finds them in O(n²) time.
 pub fn minimum_spanning_tree(
-    points: &Vec<Vertex>,
+    points: &[Vertex],
 ) -> Result<Vec<Edge>, &'static str> {
-    let mut vertex_table = Vec::<Item>::new();
-    let mut table_index = 0;
+    Ok(index_edges(points, &minimum_spanning_tree_indices(points)))
+}
 
-    // Initialize the vertex table from the list of points
-    for p in points {
-        vertex_table.push(Item::new(table_index, *p));
-        table_index += 1;
+/// Find the minimum spanning tree of the complete graph on n vertices with
+/// Prim's algorithm, in O(n²) time and O(n) memory.  The weight of each
+/// edge is found when needed, so any metric can be used; infinite weights
+/// are allowed and taken last.  Returns the edges in the order they join
+/// the tree, each as the newly added vertex and the tree vertex it joins.
+pub fn prim<F>(n: usize, weight: F) -> Vec<(usize, usize)>
+where
+    F: Fn(usize, usize) -> f32,
+{
+    let mut edges = Vec::with_capacity(n.saturating_sub(1));
+    if n == 0 {
+        return edges;
     }
-    // Verify table index is correct
-    for i in 0..vertex_table.len() {
-        assert_eq!(i, vertex_table[i].index);
+    let mut in_tree = vec![false; n];
+    let mut near = vec![0; n];
+    let mut cost = vec![FMARK; n];
+    let mut last = 0;
+    in_tree[0] = true;
+    for _ in 1..n {
+        let mut next = UMARK;
+        for i in 0..n {
+            if in_tree[i] {
+                continue;
+            }
+            let w = weight(last, i);
+            if w < cost[i] {
+                cost[i] = w;
+                near[i] = last;
+            }
+            if next == UMARK || cost[i] < cost[next] {
+                next = i;
+            }
+        }
+        in_tree[next] = true;
+        edges.push((next, near[next]));
+        last = next;
     }
+    edges
+}
 
-    // Put vertex 0 in the tree
-    vertex_table[0].near = 0;
-    vertex_table[0].cost = 0.0;
+/// Find the minimum spanning tree of a set of points as the indices of the
+/// end points of each edge, the newly added vertex then the tree vertex, in
+/// the order Prim's algorithm adds them from the first point.  Repeated
+/// points keep their own indices.
+pub fn minimum_spanning_tree_indices(points: &[Vertex]) -> Vec<(usize, usize)> {
+    prim(points.len(), |u, v| points[u].distance(&points[v]))
+}
+
+/// An edge of a weighted graph of points: the indices of its end points and
+/// its weight
+pub type GraphEdge = (usize, usize, f32);
 
-    while vertices_available(&vertex_table) {
-        let mut tree_index = UMARK;
+/// Find the minimum spanning forest of a weighted graph of points with
+/// Kruskal's algorithm.  Each graph edge is the indices of its end points
+/// and its weight.  The forest's edges have their weights as their lengths,
+/// and come with the indices of their end points.  Loops are ignored, and a
+/// disconnected graph has fewer than `points.len() - 1` edges.
+pub fn minimum_spanning_forest(
+    points: &[Vertex],
+    graph: &[GraphEdge],
+) -> (Vec<Edge>, Vec<(usize, usize)>) {
+    let mut order: Vec<usize> = (0..graph.len()).collect();
+    order.sort_by(|&a, &b| graph[a].2.total_cmp(&graph[b].2));
+    let mut sets = disjoint_set::DisjointSet::new(points.len());
+    let mut edges = Vec::new();
+    let mut ends = Vec::new();
+    for (u, v, length) in order.into_iter().map(|i| graph[i]) {
+        if u != v && sets.union(u, v) {
+            edges.push(Edge {
+                u: points[u],
+                v: points[v],
+                length,
+            });
+            ends.push((u, v));
+        }
+    }
+    (edges, ends)
+}
+
+/// Iterate over the edges of the minimum spanning tree of a set of points in
+/// the order Prim's algorithm adds them to the tree, starting from the first
+/// point.  Each item is an edge and its step, the number of edges added to
+/// the tree before it.  Each edge runs from the newly added vertex (u) to the
+/// vertex already in the tree that it connects to (v).
+///
+/// The tree can be grown part way, e.g. `max_edges(k)` or `max_length(d)`,
+/// and the partial tree examined with `in_tree` without recomputing it.
+/// With `max_degree(d)` or `degree_limits` each vertex joins the nearest
+/// tree vertex that still has room, which may not give the minimum tree.
+pub fn minimum_spanning_tree_steps(points: &[Vertex]) -> MstSteps<'_> {
+    minimum_spanning_tree_steps_from(points, 0)
+}
+
+/// Iterate over the edges of the minimum spanning tree as for
+/// `minimum_spanning_tree_steps`, growing the tree from the point at index
+/// `start`.  Stopping early gives the tree of the points nearest the start.
+pub fn minimum_spanning_tree_steps_from(
+    points: &[Vertex],
+    start: usize,
+) -> MstSteps<'_> {
+    assert!(points.is_empty() || start < points.len());
+    let mut steps = MstSteps {
+        points,
+        near: vec![UMARK; points.len()],
+        cost: vec![FMARK; points.len()],
+        in_tree: vec![false; points.len()],
+        step: 0,
+        max_edges: usize::MAX,
+        max_length: FMARK,
+        degree: vec![0; points.len()],
+        limits: Vec::new(),
+        satisfied: true,
+    };
+    if !points.is_empty() {
+        steps.connect(start);
+    }
+    steps
+}
+
+/// Prim's algorithm, one edge at a time.
+///
+/// Keeps the nearest tree vertex and its distance for every vertex not yet
+/// in the tree, so each step costs O(n).
+pub struct MstSteps<'a> {
+    /// The graph vertices
+    points: &'a [Vertex],
+    /// Index of the nearest vertex in the tree
+    near: Vec<usize>,
+    /// Distance to the nearest vertex in the tree
+    cost: Vec<f32>,
+    /// Vertices already in the tree
+    in_tree: Vec<bool>,
+    /// Number of edges added so far
+    step: usize,
+    /// Stop after this many edges
+    max_edges: usize,
+    /// Stop before adding an edge longer than this
+    max_length: f32,
+    /// Number of tree edges at each vertex
+    degree: Vec<usize>,
+    /// Most tree edges allowed at each vertex, empty for no limit
+    limits: Vec<usize>,
+    /// No vertex has gone over its limit
+    satisfied: bool,
+}
+
+impl MstSteps<'_> {
+    /// Stop once the tree has k edges.
+    pub fn max_edges(mut self, k: usize) -> Self {
+        self.max_edges = k;
+        self
+    }
+
+    /// Stop when the next edge would be longer than the given length.
+    pub fn max_length(mut self, length: f32) -> Self {
+        self.max_length = length;
+        self
+    }
+
+    /// Allow at most d tree edges at any vertex.
+    pub fn max_degree(self, d: usize) -> Self {
+        let n = self.points.len();
+        self.degree_limits(&vec![d; n])
+    }
+
+    /// Allow at most limits[i] tree edges at vertex i.
+    pub fn degree_limits(mut self, limits: &[usize]) -> Self {
+        assert_eq!(limits.len(), self.points.len());
+        self.limits = limits.to_vec();
+        self
+    }
+
+    /// Determine if every vertex is within its degree limit.  A vertex goes
+    /// over its limit only when no vertex in the tree has room left.
+    pub fn satisfied(&self) -> bool {
+        self.satisfied
+    }
+
+    /// Number of edges added to the tree so far.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Determine if a vertex (by index) has been added to the tree.
+    pub fn in_tree(&self, index: usize) -> bool {
+        self.in_tree[index]
+    }
+
+    /// The edge that will be added next, ignoring the stopping limits.
+    /// With degree limits this may change once the nearest tree vertex
+    /// becomes full.
+    pub fn peek(&self) -> Option<Edge> {
+        match self.nearest() {
+            UMARK => None,
+            index => Some(self.edge_to(index)),
+        }
+    }
+
+    /// The edge connecting a vertex not in the tree to the tree.
+    fn edge_to(&self, index: usize) -> Edge {
+        Edge {
+            u: self.points[index],
+            v: self.points[self.near[index]],
+            length: self.cost[index],
+        }
+    }
+
+    /// Find the vertex not in the tree nearest to the tree.
+    /// Returns UMARK if all vertices are in the tree.
+    fn nearest(&self) -> usize {
         let mut index = UMARK;
+        for i in 0..self.points.len() {
+            if !self.in_tree[i]
+                && (index == UMARK || self.cost[i] < self.cost[index])
+            {
+                index = i;
+            }
+        }
+        index
+    }
+
+    /// Determine if a vertex has as many tree edges as it is allowed.
+    fn full(&self, index: usize) -> bool {
+        self.limits
+            .get(index)
+            .is_some_and(|&limit| self.degree[index] >= limit)
+    }
+
+    /// Find the nearest tree vertex with room for the vertex not in the
+    /// tree.  Returns false, leaving it unchanged, if there is none.
+    fn reconnect(&mut self, index: usize) -> bool {
+        let p = self.points[index];
+        let mut near = UMARK;
         let mut cost = FMARK;
+        for j in 0..self.points.len() {
+            if self.in_tree[j] && !self.full(j) {
+                let d = p.distance(&self.points[j]);
+                if near == UMARK || d < cost {
+                    near = j;
+                    cost = d;
+                }
+            }
+        }
+        if near == UMARK {
+            return false;
+        }
+        self.near[index] = near;
+        self.cost[index] = cost;
+        true
+    }
 
-        // Find the shortest distance between a vertex in the tree to a
-        // vertex not in the tree.
-        // Vertices in the tree have a valid 'near' value, not UMARK.
-        for t in &vertex_table {
-            // Skip vertices not in tree
-            if t.near == UMARK {
+    /// Add a vertex to the tree and update the distances of the vertices
+    /// not yet in the tree.
+    fn connect(&mut self, index: usize) {
+        self.in_tree[index] = true;
+        let p = self.points[index];
+        for i in 0..self.points.len() {
+            if self.in_tree[i] {
                 continue;
             }
-            for i in &vertex_table {
-                if i.near == UMARK {
-                    // vertex 'v' is available (not in tree)
-                    let length = t.vertex.distance(&i.vertex);
-                    if length < cost {
-                        tree_index = t.index;
-                        index = i.index;
-                        cost = length;
-                    }
-                }
+            let d = p.distance(&self.points[i]);
+            if d < self.cost[i] {
+                self.cost[i] = d;
+                self.near[i] = index;
             }
         }
-        // Connect this point to the tree
-        vertex_table[index].cost = cost;
-        vertex_table[index].near = tree_index;
     }
+}
 
-    // Build the list of edges making up the spanning tree
-    let mut edges = Vec::new();
-    for i in 0..vertex_table.len() {
-        // The first vertex connected to the tree has itself as its nearest
-        // vertex so skip it
-        if vertex_table[i].near == i {
-            continue;
+impl<'a> MstSteps<'a> {
+    /// Iterate over the remaining edges as the indices of their end points,
+    /// the newly added vertex (u) then the tree vertex (v), rather than as
+    /// edges.  Repeated points keep their own indices.
+    pub fn indices(self) -> MstIndices<'a> {
+        MstIndices { steps: self }
+    }
+
+    /// Add the next vertex to the tree, returning its index.
+    fn advance(&mut self) -> Option<usize> {
+        if self.step >= self.max_edges {
+            return None;
+        }
+        let mut index = self.nearest();
+        // Move vertices off full tree vertices until the nearest has room
+        while index != UMARK && self.full(self.near[index]) {
+            if !self.reconnect(index) {
+                break;
+            }
+            index = self.nearest();
         }
-        // Add a edge for each vertex to the nearest other vertex
-        edges.push(Edge {
-            u: vertex_table[i].vertex,
-            v: vertex_table[vertex_table[i].near].vertex,
-            length: vertex_table[i].cost,
-        });
+        if index == UMARK || self.cost[index] > self.max_length {
+            return None;
+        }
+        for i in [index, self.near[index]] {
+            self.degree[i] += 1;
+            if self
+                .limits
+                .get(i)
+                .is_some_and(|&limit| self.degree[i] > limit)
+            {
+                self.satisfied = false;
+            }
+        }
+        self.connect(index);
+        self.step += 1;
+        Some(index)
+    }
+}
+
+impl Iterator for MstSteps<'_> {
+    type Item = (Edge, usize);
+
+    fn next(&mut self) -> Option<(Edge, usize)> {
+        let index = self.advance()?;
+        Some((self.edge_to(index), self.step - 1))
+    }
+}
+
+/// Prim's algorithm, one edge at a time as the indices of its end points.
+/// See `MstSteps::indices`.
+pub struct MstIndices<'a> {
+    steps: MstSteps<'a>,
+}
+
+impl MstIndices<'_> {
+    /// Determine if every vertex is within its degree limit.
+    pub fn satisfied(&self) -> bool {
+        self.steps.satisfied()
+    }
+}
+
+impl Iterator for MstIndices<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let index = self.steps.advance()?;
+        Some((index, self.steps.near[index]))
+    }
+}
+
+/// Find a spanning tree of a set of points with at most limits[i] edges at
+/// vertex i, using Prim's algorithm joining each vertex to the nearest tree
+/// vertex with room.  Returns the tree and whether every vertex is within
+/// its limit.
+pub fn degree_constrained_mst(
+    points: &[Vertex],
+    limits: &[usize],
+) -> Result<(Vec<Edge>, bool), &'static str> {
+    let mut steps = degree_constrained_mst_indices(points, limits)?;
+    let ends: Vec<(usize, usize)> = steps.by_ref().collect();
+    Ok((index_edges(points, &ends), steps.satisfied()))
+}
+
+/// Iterate over the edges of a spanning tree with degree limits as for
+/// `degree_constrained_mst`, as the indices of their end points.  Check
+/// `satisfied` once all the edges are taken.
+pub fn degree_constrained_mst_indices<'a>(
+    points: &'a [Vertex],
+    limits: &[usize],
+) -> Result<MstIndices<'a>, &'static str> {
+    if limits.len() != points.len() {
+        return Err("need a degree limit for each point");
+    }
+    Ok(minimum_spanning_tree_steps(points)
+        .degree_limits(limits)
+        .indices())
+}
+
+/// Find the minimum spanning tree of a set of points that includes every
+/// required edge and none of the forbidden edges, using Prim's algorithm
+/// with the required edges taken before any other and the forbidden edges
+/// never.  Edges are matched to points by their coordinates, in either
+/// direction.  Takes O(n^2) time and memory only for the points and the
+/// constraints.
+pub fn constrained_mst(
+    points: &[Vertex],
+    required: &[Edge],
+    forbidden: &[Edge],
+) -> Result<Vec<Edge>, &'static str> {
+    let ends = constrained_mst_indices(points, required, forbidden)?;
+    Ok(index_edges(points, &ends))
+}
+
+/// Find the constrained minimum spanning tree as for `constrained_mst`, as
+/// the indices of the end points of each edge.
+pub fn constrained_mst_indices(
+    points: &[Vertex],
+    required: &[Edge],
+    forbidden: &[Edge],
+) -> Result<Vec<(usize, usize)>, &'static str> {
+    let n = points.len();
+    let pair = |u: usize, v: usize| (u.min(v), u.max(v));
+    let forbidden: HashSet<(usize, usize)> = edge_indices(points, forbidden)?
+        .into_iter()
+        .map(|(u, v)| pair(u, v))
+        .collect();
+
+    let mut sets = disjoint_set::DisjointSet::new(n);
+    let mut kept = HashSet::new();
+    for (u, v) in edge_indices(points, required)? {
+        if forbidden.contains(&pair(u, v)) {
+            return Err("edge is both required and forbidden");
+        }
+        if !sets.union(u, v) {
+            return Err("required edges form a cycle");
+        }
+        kept.insert(pair(u, v));
     }
 
+    // Required edges are shorter than any other, so the minimum spanning
+    // tree takes them all, and forbidden edges are infinitely long
+    let weight = |u: usize, v: usize| {
+        if kept.contains(&pair(u, v)) {
+            -1.0
+        } else if forbidden.contains(&pair(u, v)) {
+            FMARK
+        } else {
+            points[u].distance(&points[v])
+        }
+    };
+    let edges = prim(n, weight);
+    if edges.iter().any(|&(u, v)| weight(u, v) == FMARK) {
+        return Err("forbidden edges leave the points disconnected");
+    }
     Ok(edges)
 }
 
+/// Make the edges between pairs of points as the readers do, measuring each
+/// and skipping pairs of copies of a point.
+pub(crate) fn pair_edges<I>(pairs: I) -> Vec<Edge>
+where
+    I: IntoIterator<Item = (Vertex, Vertex)>,
+{
+    pairs
+        .into_iter()
+        .filter(|(u, v)| (u.x, u.y) != (v.x, v.y))
+        .map(|(u, v)| Edge::from_vertices(u, v))
+        .collect()
+}
+
+/// Make the edges between points given by the indices of their end points,
+/// measuring each.  An edge between copies of a point has length zero.
+pub fn index_edges(points: &[Vertex], ends: &[(usize, usize)]) -> Vec<Edge> {
+    ends.iter()
+        .map(|&(u, v)| Edge {
+            u: points[u],
+            v: points[v],
+            length: points[u].distance(&points[v]),
+        })
+        .collect()
+}
+
+/// Find the indices of the end points of each edge in a set of points.
+/// Points are matched by their coordinates; where points are repeated the
+/// first is used, except that an edge between two copies of a point joins
+/// the next unused copy to the first.  Algorithms that know the indices
+/// return them instead, e.g. `minimum_spanning_tree_indices`.
+pub fn edge_indices(
+    points: &[Vertex],
+    edges: &[Edge],
+) -> Result<Vec<(usize, usize)>, &'static str> {
+    let mut index: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, p) in points.iter().enumerate() {
+        index.entry((p.x, p.y)).or_default().push(i);
+    }
+    let mut copies: HashMap<(i32, i32), usize> = HashMap::new();
+    let mut indices = Vec::with_capacity(edges.len());
+    for e in edges {
+        let (u, v) = ((e.u.x, e.u.y), (e.v.x, e.v.y));
+        match (index.get(&u), index.get(&v)) {
+            (Some(same), Some(_)) if u == v => {
+                let used = copies.entry(u).or_insert(0);
+                *used += 1;
+                indices.push((same[*used % same.len()], same[0]));
+            }
+            (Some(us), Some(vs)) => indices.push((us[0], vs[0])),
+            _ => return Err("edge vertex not in the set of points"),
+        }
+    }
+    Ok(indices)
+}
+
 /// Plot the graph and write to a PNG file.
-pub fn plot(edges: &Vec<Edge>, output_file: &str) -> Result<(), &'static str> {
-    let margin = 10;
-    let (width, height, x0, y0) = plot_dimensions(edges, margin);
-    //let mut image = ImageBuffer::new(width, height);
+pub fn plot(edges: &[Edge], output_file: &str) -> Result<(), &'static str> {
+    let (mut image, x0, y0) = plot_canvas(edges, 0);
+    plot_edges(&mut image, edges, x0, y0, |_| BLACK);
+    plot_save(&image, output_file)
+}
+
+/// Plot the graph with each edge coloured by its length and a colour bar
+/// legend showing the minimum and maximum lengths, then write to a PNG file.
+pub fn plot_by_length(
+    edges: &[Edge],
+    output_file: &str,
+    colormap: Colormap,
+) -> Result<(), &'static str> {
+    let (min, max) = length_range(edges);
+    let (mut image, x0, y0) = plot_canvas(edges, LEGEND_HEIGHT);
+    plot_edges(&mut image, edges, x0, y0, |e| {
+        colormap.scaled(e.len(), min, max)
+    });
+    plot_legend(&mut image, colormap, min, max);
+    plot_save(&image, output_file)
+}
+
+/// Plot the graph with edges longer than the threshold highlighted in red,
+/// then write to a PNG file.
+pub fn plot_threshold(
+    edges: &[Edge],
+    output_file: &str,
+    threshold: f32,
+) -> Result<(), &'static str> {
+    let (mut image, x0, y0) = plot_canvas(edges, 0);
+    plot_edges(&mut image, edges, x0, y0, |e| {
+        if e.len() > threshold {
+            RED
+        } else {
+            BLACK
+        }
+    });
+    plot_save(&image, output_file)
+}
+
+/// Plot the graph with each cluster in a distinct colour, then write to a
+/// PNG file.  Each edge has the indices of its end points, which label each
+/// end by position as `cluster::label` numbers them, so repeated points keep
+/// their own labels.  Edges joining two clusters and noise points (no
+/// label) are drawn in light gray.
+pub fn plot_clusters(
+    edges: &[Edge],
+    ends: &[(usize, usize)],
+    labels: &[Option<usize>],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    let cluster = |i: usize| labels.get(i).copied().flatten();
+    let color = |c: Option<usize>| c.map_or(LIGHT_GRAY, colormap::palette);
+
+    let (mut image, x0, y0) = plot_canvas(edges, 0);
+    for (e, &(u, v)) in edges.iter().zip(ends) {
+        let same = match (cluster(u), cluster(v)) {
+            (Some(a), Some(b)) if a == b => Some(a),
+            _ => None,
+        };
+        draw_line_segment_mut(
+            &mut image,
+            ((e.u.x - x0) as f32, (e.u.y - y0) as f32),
+            ((e.v.x - x0) as f32, (e.v.y - y0) as f32),
+            color(same),
+        );
+    }
+    for (e, &(u, v)) in edges.iter().zip(ends) {
+        for (p, i) in [(e.u, u), (e.v, v)] {
+            let (x, y) = ((p.x - x0) as u32, (p.y - y0) as u32);
+            plot_vertex_color(&mut image, x, y, color(cluster(i)));
+        }
+    }
+    plot_save(&image, output_file)
+}
+
+/// Plot a closed tour through the points in order, with the tree it came
+/// from (if any) underneath in light gray, then write to a PNG file.
+pub fn plot_tour(
+    tour: &[Vertex],
+    tree: &[Edge],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    if tour.len() < 2 {
+        return Err("nothing to plot");
+    }
+    let legs: Vec<Edge> = (0..tour.len())
+        .map(|i| {
+            let (u, v) = (tour[i], tour[(i + 1) % tour.len()]);
+            Edge {
+                u,
+                v,
+                length: u.distance(&v),
+            }
+        })
+        .collect();
+    let all: Vec<Edge> = legs.iter().chain(tree).copied().collect();
+    let (mut image, x0, y0) = plot_canvas(&all, 0);
+    plot_edges(&mut image, tree, x0, y0, |_| LIGHT_GRAY);
+    plot_edges(&mut image, &legs, x0, y0, |_| BLACK);
+    plot_save(&image, output_file)
+}
+
+/// Plot paths around obstacles, with the obstacles filled in light gray,
+/// then write to a PNG file.
+pub fn plot_obstacles(
+    paths: &[obstacles::Path],
+    obstacles: &[obstacles::Polygon],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    // Size the plot to fit the paths and the obstacles
+    let mut extent: Vec<Edge> = paths.iter().map(|p| p.edge).collect();
+    for o in obstacles {
+        extent.extend(o.sides().map(|(u, v)| Edge { u, v, length: 0.0 }));
+    }
+    let (mut image, x0, y0) = plot_canvas(&extent, 0);
+    for o in obstacles {
+        let min_x = o.vertices.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_x = o.vertices.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_y = o.vertices.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = o.vertices.iter().map(|p| p.y).max().unwrap_or(0);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if o.contains(x as f64, y as f64) {
+                    image.put_pixel(
+                        (x - x0) as u32,
+                        (y - y0) as u32,
+                        LIGHT_GRAY,
+                    );
+                }
+            }
+        }
+        for (u, v) in o.sides() {
+            draw_line_segment_mut(
+                &mut image,
+                ((u.x - x0) as f32, (u.y - y0) as f32),
+                ((v.x - x0) as f32, (v.y - y0) as f32),
+                DARK_GRAY,
+            );
+        }
+    }
+    for p in paths {
+        for leg in p.vertices().windows(2) {
+            draw_line_segment_mut(
+                &mut image,
+                ((leg[0].x - x0) as f32, (leg[0].y - y0) as f32),
+                ((leg[1].x - x0) as f32, (leg[1].y - y0) as f32),
+                BLACK,
+            );
+        }
+    }
+    for p in paths {
+        for v in [p.edge.u, p.edge.v] {
+            plot_vertex(&mut image, (v.x - x0) as u32, (v.y - y0) as u32);
+        }
+    }
+    plot_save(&image, output_file)
+}
+
+/// Plot a Steiner tree with its Steiner points marked by red squares, then
+/// write to a PNG file.
+pub fn plot_steiner(
+    edges: &[Edge],
+    steiner: &[Vertex],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    let (mut image, x0, y0) = plot_canvas(edges, 0);
+    plot_edges(&mut image, edges, x0, y0, |_| BLACK);
+    for p in steiner {
+        draw_hollow_rect_mut(
+            &mut image,
+            Rect::at(p.x - x0 - 2, p.y - y0 - 2).of_size(5, 5),
+            RED,
+        );
+    }
+    plot_save(&image, output_file)
+}
+
+/// Plot edges as L-shaped horizontal and vertical wires, then write to a
+/// PNG file.  The ends of the edges are marked, except where a wire bends.
+pub fn plot_rectilinear(
+    edges: &[Edge],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    let wires = rectilinear::segments(edges);
+    // Count horizontal and vertical wires at each end
+    let mut ends: HashMap<(i32, i32), (u32, u32)> = HashMap::new();
+    for w in &wires {
+        for p in [w.u, w.v] {
+            let count = ends.entry((p.x, p.y)).or_default();
+            if w.u.y == w.v.y {
+                count.0 += 1;
+            } else {
+                count.1 += 1;
+            }
+        }
+    }
+    let (mut image, x0, y0) = plot_canvas(edges, 0);
+    for w in &wires {
+        draw_line_segment_mut(
+            &mut image,
+            ((w.u.x - x0) as f32, (w.u.y - y0) as f32),
+            ((w.v.x - x0) as f32, (w.v.y - y0) as f32),
+            BLACK,
+        );
+    }
+    for e in edges {
+        for p in [e.u, e.v] {
+            if ends.get(&(p.x, p.y)) != Some(&(1, 1)) {
+                plot_vertex(&mut image, (p.x - x0) as u32, (p.y - y0) as u32);
+            }
+        }
+    }
+    plot_save(&image, output_file)
+}
+
+/// Plot a dendrogram of a single-linkage hierarchy and write to a PNG file.
+/// Leaves are along the bottom and merge distance increases upwards, with
+/// the distance axis on the left.
+pub fn plot_dendrogram(
+    links: &[Link],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    if links.is_empty() {
+        return Err("nothing to plot");
+    }
+    let n = links.len() + 1;
+    for (i, l) in links.iter().enumerate() {
+        if l.a >= n + i || l.b >= n + i || l.a == l.b {
+            return Err("invalid linkage matrix");
+        }
+    }
+
+    // Order the leaves by walking down from the root so branches don't cross
+    let mut position = vec![0.0f32; 2 * n - 1];
+    let mut height = vec![0.0f32; 2 * n - 1];
+    let mut visited = vec![false; 2 * n - 1];
+    let mut next = 0;
+    let mut stack = vec![2 * n - 2];
+    while let Some(c) = stack.pop() {
+        // Each cluster must be merged exactly once
+        if visited[c] {
+            return Err("invalid linkage matrix");
+        }
+        visited[c] = true;
+        if c < n {
+            position[c] = next as f32;
+            next += 1;
+        } else {
+            stack.push(links[c - n].b);
+            stack.push(links[c - n].a);
+        }
+    }
+    if next != n {
+        return Err("invalid linkage matrix");
+    }
+    // Merged clusters sit midway between the clusters they merge
+    for (i, l) in links.iter().enumerate() {
+        position[n + i] = (position[l.a] + position[l.b]) / 2.0;
+        height[n + i] = l.distance;
+    }
+    let max_distance = links.iter().fold(0.0f32, |m, l| m.max(l.distance));
+    let max_distance = if max_distance > 0.0 {
+        max_distance
+    } else {
+        1.0
+    };
+
+    let spacing = (DENDROGRAM_WIDTH / n as i32).clamp(1, 8);
+    let left = DENDROGRAM_AXIS;
+    let top = PLOT_MARGIN;
+    let bottom = PLOT_MARGIN + DENDROGRAM_HEIGHT;
+    let width = left + spacing * n as i32 + PLOT_MARGIN;
+    let mut image = blank_image(width, bottom + PLOT_MARGIN);
+    let px =
+        |c: usize| (left + spacing / 2) as f32 + position[c] * spacing as f32;
+    let py = |d: f32| bottom as f32 - d / max_distance * (bottom - top) as f32;
+
+    for (i, l) in links.iter().enumerate() {
+        let y = py(height[n + i]);
+        for c in [l.a, l.b] {
+            draw_line_segment_mut(
+                &mut image,
+                (px(c), py(height[c])),
+                (px(c), y),
+                BLACK,
+            );
+        }
+        draw_line_segment_mut(&mut image, (px(l.a), y), (px(l.b), y), BLACK);
+    }
+
+    // Distance axis, labelled at zero, half and full height
+    let axis = (left - 4) as f32;
+    draw_line_segment_mut(
+        &mut image,
+        (axis, top as f32),
+        (axis, bottom as f32),
+        BLACK,
+    );
+    for d in [0.0, max_distance / 2.0, max_distance] {
+        let y = py(d);
+        draw_line_segment_mut(&mut image, (axis - 3.0, y), (axis, y), BLACK);
+        let label = format!("{:.1}", d);
+        let x = left - 8 - label_width(&label);
+        plot_label(&mut image, x, y as i32 - 5, &label, BLACK);
+    }
+
+    plot_save(&image, output_file)
+}
+
+/// Render Prim's algorithm growing the minimum spanning tree of a set of
+/// points as an animated GIF.  Each frame shows the tree so far with the next
+/// edge to be added highlighted in red; the last frame shows the whole tree.
+pub fn animate(
+    points: &[Vertex],
+    output_file: &str,
+) -> Result<(), &'static str> {
+    let edges: Vec<Edge> = minimum_spanning_tree_steps(points)
+        .map(|(e, _)| e)
+        .collect();
+    if edges.is_empty() {
+        return Err("nothing to animate");
+    }
+    let (blank, x0, y0) = plot_canvas(&edges, 0);
+
+    let file = match File::create(output_file) {
+        Ok(file) => file,
+        Err(_) => return Err("failed to create animation"),
+    };
+    let mut encoder = gif::Encoder::new(BufWriter::new(file));
+
+    for step in 0..=edges.len() {
+        let mut image = blank.clone();
+        plot_edges(&mut image, &edges[..step], x0, y0, |_| BLACK);
+        if step < edges.len() {
+            plot_edges(&mut image, &edges[step..=step], x0, y0, |_| RED);
+        }
+        for p in points {
+            plot_vertex(&mut image, (p.x - x0) as u32, (p.y - y0) as u32);
+        }
+        let delay = if step < edges.len() {
+            ANIMATION_DELAY
+        } else {
+            ANIMATION_FINAL_DELAY
+        };
+        if encoder.encode(&gif_frame(&image, delay)?).is_err() {
+            return Err("failed to write animation frame");
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a plot to a GIF frame.  Plots use only a handful of colours so
+/// the palette is built from the pixels directly rather than quantized.
+fn gif_frame(
+    image: &RgbImage,
+    delay: u16,
+) -> Result<gif::Frame<'static>, &'static str> {
+    let mut palette: Vec<Rgb<u8>> = Vec::new();
+    let mut pixels =
+        Vec::with_capacity((image.width() * image.height()) as usize);
+    for p in image.pixels() {
+        let index = match palette.iter().position(|c| c == p) {
+            Some(index) => index,
+            None if palette.len() < 256 => {
+                palette.push(*p);
+                palette.len() - 1
+            }
+            None => return Err("too many colours for animation frame"),
+        };
+        pixels.push(index as u8);
+    }
+    let palette: Vec<u8> = palette.iter().flat_map(|c| c.0).collect();
+    let mut frame = gif::Frame::from_palette_pixels(
+        image.width() as u16,
+        image.height() as u16,
+        &pixels,
+        &palette,
+        None,
+    );
+    frame.delay = delay;
+    Ok(frame)
+}
+
+/// Find the shortest and longest edge lengths.
+/// Returns: min, max
+fn length_range(edges: &[Edge]) -> (f32, f32) {
+    let mut min = FMARK;
+    let mut max = 0.0f32;
+    for e in edges {
+        min = min.min(e.len());
+        max = max.max(e.len());
+    }
+    if min > max {
+        min = max;
+    }
+    (min, max)
+}
+
+/// Create a blank image large enough for the graph with extra space below
+/// it for a legend.
+/// Returns: image, min_x, min_y
+fn plot_canvas(edges: &[Edge], legend_height: i32) -> (RgbImage, i32, i32) {
+    let (width, height, x0, y0) = plot_dimensions(edges, PLOT_MARGIN);
+    (blank_image(width, height + legend_height), x0, y0)
+}
+
+/// Create a white image.
+fn blank_image(width: i32, height: i32) -> RgbImage {
     let mut image = RgbImage::new(width as u32, height as u32);
     draw_filled_rect_mut(
         &mut image,
         Rect::at(0, 0).of_size(width as u32, height as u32),
-        Rgb([255u8, 255u8, 255u8]),
+        WHITE,
     );
+    image
+}
 
+/// Draw edges and their vertices, choosing a colour for each edge.
+fn plot_edges<F>(
+    image: &mut RgbImage,
+    edges: &[Edge],
+    x0: i32,
+    y0: i32,
+    color: F,
+) where
+    F: Fn(&Edge) -> Rgb<u8>,
+{
     // draw edges
     for e in edges {
         draw_line_segment_mut(
-            &mut image,
+            image,
             ((e.u.x - x0) as f32, (e.u.y - y0) as f32),
             ((e.v.x - x0) as f32, (e.v.y - y0) as f32),
-            Rgb([0u8, 0u8, 0u8]),
+            color(e),
         );
     }
     // draw vertices
     for e in edges {
-        plot_vertex(&mut image, (e.u.x - x0) as u32, (e.u.y - y0) as u32);
-        plot_vertex(&mut image, (e.v.x - x0) as u32, (e.v.y - y0) as u32);
+        plot_vertex(image, (e.u.x - x0) as u32, (e.u.y - y0) as u32);
+        plot_vertex(image, (e.v.x - x0) as u32, (e.v.y - y0) as u32);
     }
+}
 
+/// Write an image to a file.  The format is chosen by the file extension.
+fn plot_save(image: &RgbImage, output_file: &str) -> Result<(), &'static str> {
     match image.save(output_file) {
         Ok(_) => Ok(()),
         Err(_) => Err("failed to save image"),
     }
 }
 
+/// Draw a colour bar across the bottom of the image with the minimum value
+/// labelled on the left and the maximum value on the right.
+fn plot_legend(image: &mut RgbImage, colormap: Colormap, min: f32, max: f32) {
+    let width = image.width() as i32;
+    let top = image.height() as i32 - LEGEND_HEIGHT;
+    let bar_width = width - 2 * PLOT_MARGIN;
+    if bar_width <= 0 {
+        return;
+    }
+    for i in 0..bar_width {
+        let t = if bar_width > 1 {
+            i as f32 / (bar_width - 1) as f32
+        } else {
+            0.5
+        };
+        draw_line_segment_mut(
+            image,
+            ((PLOT_MARGIN + i) as f32, (top + 2) as f32),
+            ((PLOT_MARGIN + i) as f32, (top + 9) as f32),
+            colormap.color(t),
+        );
+    }
+    let min_label = format!("{:.1}", min);
+    let max_label = format!("{:.1}", max);
+    plot_label(image, PLOT_MARGIN, top + 12, &min_label, BLACK);
+    let x = width - PLOT_MARGIN - label_width(&max_label);
+    plot_label(image, x, top + 12, &max_label, BLACK);
+}
+
+/// Width in pixels of a label drawn by `plot_label`.
+fn label_width(text: &str) -> i32 {
+    let n = text.chars().count() as i32;
+    if n == 0 {
+        0
+    } else {
+        (n * (GLYPH_WIDTH + 1) - 1) * GLYPH_SCALE
+    }
+}
+
+/// Draw a short numeric label with its top left corner at x, y.  Only digits,
+/// '.' and '-' are drawn; other characters are left blank.  Pixels outside
+/// the image are clipped.
+fn plot_label(
+    image: &mut RgbImage,
+    x: i32,
+    y: i32,
+    text: &str,
+    color: Rgb<u8>,
+) {
+    let (w, h) = (image.width() as i32, image.height() as i32);
+    for (n, c) in text.chars().enumerate() {
+        let glyph = match c {
+            '0'..='9' => GLYPHS[c as usize - '0' as usize],
+            '.' => GLYPH_DOT,
+            '-' => GLYPH_MINUS,
+            _ => continue,
+        };
+        let gx = x + n as i32 * (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for sy in 0..GLYPH_SCALE {
+                    for sx in 0..GLYPH_SCALE {
+                        let px = gx + col * GLYPH_SCALE + sx;
+                        let py = y + row as i32 * GLYPH_SCALE + sy;
+                        if px >= 0 && px < w && py >= 0 && py < h {
+                            image.put_pixel(px as u32, py as u32, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Find the width and height of the (square) area containing all vertices and
 /// the lower left corner of that area with margins.
 /// Returns: width, height, min_x, min_y
-fn plot_dimensions(edges: &Vec<Edge>, margin: i32) -> (i32, i32, i32, i32) {
+fn plot_dimensions(edges: &[Edge], margin: i32) -> (i32, i32, i32, i32) {
     let mut min_x = i32::MAX;
     let mut max_x = i32::MIN;
     let mut min_y = i32::MAX;
@@ -314,27 +1302,22 @@ fn plot_dimensions(edges: &Vec<Edge>, margin: i32) -> (i32, i32, i32, i32) {
 fn plot_vertex(image: &mut RgbImage, x: u32, y: u32) {
     //let color = [128u8, 0u8, 0u8];
     let color = [0u8, 128u8, 128u8];
-    *image.get_pixel_mut(x - 1, y - 1) = Rgb(color);
-    *image.get_pixel_mut(x - 1, y + 1) = Rgb(color);
-    *image.get_pixel_mut(x, y) = Rgb(color);
-    *image.get_pixel_mut(x + 1, y - 1) = Rgb(color);
-    *image.get_pixel_mut(x + 1, y + 1) = Rgb(color);
+    plot_vertex_color(image, x, y, Rgb(color));
 }
 
-/// Determine if any vertices in the table have not been added to the tree.
-/// Available vertices don't have a nearest vertex (item.near == UMARK).
-fn vertices_available(vertices: &Vec<Item>) -> bool {
-    for v in vertices {
-        if v.near == UMARK {
-            return true;
-        }
-    }
-    false
+/// Draw an X centered on the given coordinates in the given colour.
+fn plot_vertex_color(image: &mut RgbImage, x: u32, y: u32, color: Rgb<u8>) {
+    *image.get_pixel_mut(x - 1, y - 1) = color;
+    *image.get_pixel_mut(x - 1, y + 1) = color;
+    *image.get_pixel_mut(x, y) = color;
+    *image.get_pixel_mut(x + 1, y - 1) = color;
+    *image.get_pixel_mut(x + 1, y + 1) = color;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use image::AnimationDecoder;
 
     #[test]
     fn test_vertex_distance() {
@@ -361,6 +1344,240 @@ mod tests {
             vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(6, 8)];
         let mst = minimum_spanning_tree(&points).unwrap();
         assert_eq!(mst.len(), 2);
+        assert!(minimum_spanning_tree(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prim() {
+        let points = generate(50, 1.0, 0, 0, 100, 100).unwrap();
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let edges = prim(points.len(), |u, v| points[u].distance(&points[v]));
+        assert_eq!(edges.len(), points.len() - 1);
+        let a: f32 = mst.iter().map(|e| e.len()).sum();
+        let b: f32 = edges
+            .iter()
+            .map(|&(u, v)| points[u].distance(&points[v]))
+            .sum();
+        assert!((a - b).abs() < 1e-3);
+        assert!(prim(0, |_, _| 0.0).is_empty());
+
+        // Infinite weights are taken last
+        let edges =
+            prim(3, |u, v| if u + v == 1 { f32::INFINITY } else { 1.0 });
+        assert_eq!(edges, [(2, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn test_minimum_spanning_forest() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(6, 8),
+            Vertex::new(6, 0),
+            Vertex::new(6, 0),
+            Vertex::new(50, 50),
+        ];
+        // Weights, not distances, choose the edges; the last point is apart
+        let graph = [(0, 1, 1.0), (1, 2, 2.0), (0, 2, 9.0), (2, 3, 0.5)];
+        let (edges, ends) = minimum_spanning_forest(&points, &graph);
+        assert_eq!(ends, [(2, 3), (0, 1), (1, 2)]);
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [0.5, 1.0, 2.0]);
+        let (edges, _) = minimum_spanning_forest(&points, &[(4, 4, 0.0)]);
+        assert!(edges.is_empty());
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree_indices() {
+        // Repeated points keep their own indices
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(0, 0),
+            Vertex::new(20, 0),
+            Vertex::new(10, 0),
+            Vertex::new(30, 5),
+        ];
+        let ends = minimum_spanning_tree_indices(&points);
+        assert_eq!(ends, [(2, 0), (1, 0), (4, 1), (3, 1), (5, 3)]);
+        let steps: Vec<(usize, usize)> =
+            minimum_spanning_tree_steps(&points).indices().collect();
+        assert_eq!(steps, ends);
+        let edges = index_edges(&points, &ends);
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(&lengths[..4], [0.0, 10.0, 0.0, 10.0]);
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree_steps() {
+        let points = generate(50, 1.0, 0, 0, 100, 100).unwrap();
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let steps: Vec<Edge> = minimum_spanning_tree_steps(&points)
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(steps.len(), points.len() - 1);
+        let a: f32 = mst.iter().map(|e| e.len()).sum();
+        let b: f32 = steps.iter().map(|e| e.len()).sum();
+        assert!((a - b).abs() < 1e-3);
+        // The first edge connects the nearest point to the first point
+        assert_eq!(steps[0].v.x, points[0].x);
+        assert_eq!(steps[0].v.y, points[0].y);
+        assert_eq!(steps[0].len(), minimum_distance(&points[0], &points[1..]));
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree_steps_limits() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(1, 0),
+            Vertex::new(3, 0),
+            Vertex::new(6, 0),
+            Vertex::new(10, 0),
+        ];
+        let steps: Vec<usize> = minimum_spanning_tree_steps(&points)
+            .map(|(_, step)| step)
+            .collect();
+        assert_eq!(steps, vec![0, 1, 2, 3]);
+
+        let mut partial = minimum_spanning_tree_steps(&points).max_edges(2);
+        assert_eq!(partial.by_ref().count(), 2);
+        assert_eq!(partial.step(), 2);
+        assert!(partial.in_tree(2));
+        assert!(!partial.in_tree(3));
+        assert_eq!(partial.next().map(|(_, step)| step), None);
+        // Continue growing the same tree
+        let mut rest = partial.max_edges(usize::MAX);
+        assert_eq!(
+            rest.next().map(|(e, step)| (e.len(), step)),
+            Some((3.0, 2))
+        );
+
+        let short = minimum_spanning_tree_steps(&points).max_length(2.5);
+        let lengths: Vec<f32> = short.map(|(e, _)| e.len()).collect();
+        assert_eq!(lengths, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree_steps_from() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(1, 0),
+            Vertex::new(3, 0),
+            Vertex::new(6, 0),
+            Vertex::new(10, 0),
+        ];
+        let mut steps = minimum_spanning_tree_steps_from(&points, 4);
+        let first = steps.next().unwrap().0;
+        assert_eq!((first.u.x, first.v.x), (6, 10));
+        assert!(steps.in_tree(4));
+        assert!(!steps.in_tree(0));
+        let length: f32 = steps.map(|(e, _)| e.len()).sum();
+        assert_eq!(length, 6.0);
+    }
+
+    #[test]
+    fn test_degree_constrained_mst() {
+        // A hub with eight close neighbours
+        let mut points = vec![Vertex::new(50, 50)];
+        for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1)] {
+            points.push(Vertex::new(50 + dx * 10, 50 + dy * 10));
+        }
+        for (dx, dy) in [(1, -1), (1, 0), (1, 1)] {
+            points.push(Vertex::new(50 + dx * 10, 50 + dy * 10));
+        }
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let mst_length: f32 = mst.iter().map(|e| e.len()).sum();
+
+        let limits = vec![3; points.len()];
+        let (tree, satisfied) =
+            degree_constrained_mst(&points, &limits).unwrap();
+        assert!(satisfied);
+        assert_eq!(tree.len(), points.len() - 1);
+        let mut degree = vec![0; points.len()];
+        for (u, v) in edge_indices(&points, &tree).unwrap() {
+            degree[u] += 1;
+            degree[v] += 1;
+        }
+        assert!(degree.iter().all(|&d| d <= 3));
+        let length: f32 = tree.iter().map(|e| e.len()).sum();
+        assert!(length >= mst_length);
+
+        // Only the hub can take more than one edge
+        let mut limits = vec![1; points.len()];
+        limits[0] = 2;
+        let (tree, satisfied) =
+            degree_constrained_mst(&points, &limits).unwrap();
+        assert!(!satisfied);
+        assert_eq!(tree.len(), points.len() - 1);
+        assert!(degree_constrained_mst(&points, &[2]).is_err());
+    }
+
+    #[test]
+    fn test_constrained_mst() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(20, 0),
+            Vertex::new(10, 30),
+        ];
+        let length = |edges: &[Edge]| edges.iter().map(|e| e.len()).sum();
+        let mst = constrained_mst(&points, &[], &[]).unwrap();
+        let expected: f32 = length(&minimum_spanning_tree(&points).unwrap());
+        assert_eq!(length(&mst), expected);
+
+        // Keep a long edge, given backwards
+        let required = vec![Edge::new(10, 30, 0, 0)];
+        let tree = constrained_mst(&points, &required, &[]).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert!(tree.iter().any(|e| e.len() == required[0].len()));
+
+        // Forbid a short edge
+        let forbidden = vec![Edge::new(0, 0, 10, 0)];
+        let tree = constrained_mst(&points, &[], &forbidden).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert!(length(&tree) > expected);
+        assert!(tree
+            .iter()
+            .all(|e| !(e.u.x + e.v.x == 10 && e.u.y == 0 && e.v.y == 0)));
+    }
+
+    #[test]
+    fn test_constrained_mst_impossible() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(10, 0), Vertex::new(0, 10)];
+        let both = vec![Edge::new(0, 0, 10, 0)];
+        assert!(constrained_mst(&points, &both, &both).is_err());
+        let cycle = vec![
+            Edge::new(0, 0, 10, 0),
+            Edge::new(10, 0, 0, 10),
+            Edge::new(0, 10, 0, 0),
+        ];
+        assert!(constrained_mst(&points, &cycle, &[]).is_err());
+        // Cut off the first point
+        let cut = vec![Edge::new(0, 0, 10, 0), Edge::new(0, 0, 0, 10)];
+        assert!(constrained_mst(&points, &[], &cut).is_err());
+        let stranger = vec![Edge::new(0, 0, 5, 5)];
+        assert!(constrained_mst(&points, &stranger, &[]).is_err());
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree_steps_small() {
+        assert_eq!(minimum_spanning_tree_steps(&[]).count(), 0);
+        assert_eq!(
+            minimum_spanning_tree_steps(&[Vertex::new(1, 1)]).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_animate() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(6, 8)];
+        assert!(animate(&points, "test_output_animate.gif").is_ok());
+        // One frame per edge plus the finished tree
+        let file = File::open("test_output_animate.gif").unwrap();
+        let decoder = gif::Decoder::new(file).unwrap();
+        assert_eq!(decoder.into_frames().count(), 3);
+        assert!(animate(&points[..1], "test_output_animate.gif").is_err());
     }
 
     #[test]
@@ -371,6 +1588,141 @@ mod tests {
         assert!(plot(&mst, "test_output.png").is_ok());
     }
 
+    #[test]
+    fn test_plot_by_length() {
+        let edges = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, 30, 40)];
+        let file = "test_output_length.png";
+        assert!(plot_by_length(&edges, file, Colormap::Viridis).is_ok());
+        let image = image::open(file).unwrap().to_rgb();
+        assert_eq!(image.height(), image.width() + LEGEND_HEIGHT as u32);
+    }
+
+    #[test]
+    fn test_plot_clusters() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(30, 40)];
+        let ends = minimum_spanning_tree_indices(&points);
+        let mst = index_edges(&points, &ends);
+        let labels = vec![Some(0), Some(0), None];
+        let file = "test_output_clusters.png";
+        assert!(plot_clusters(&mst, &ends, &labels, file).is_ok());
+    }
+
+    #[test]
+    fn test_plot_steiner() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(100, 0), Vertex::new(50, 87)];
+        let tree = steiner::steiner_tree(&points).unwrap();
+        let file = "test_output_steiner.png";
+        assert!(plot_steiner(&tree.edges, &tree.steiner, file).is_ok());
+    }
+
+    #[test]
+    fn test_plot_rectilinear() {
+        let points = generate(20, 1.0, 0, 0, 100, 100).unwrap();
+        let tree = rectilinear::rectilinear_steiner_tree(&points).unwrap();
+        let file = "test_output_rectilinear.png";
+        assert!(plot_rectilinear(&tree.edges, file).is_ok());
+    }
+
+    #[test]
+    fn test_plot_obstacles() {
+        let walls = vec![obstacles::Polygon::new(vec![
+            Vertex::new(40, 0),
+            Vertex::new(60, 0),
+            Vertex::new(50, 80),
+        ])
+        .unwrap()];
+        let points = vec![Vertex::new(0, 40), Vertex::new(100, 40)];
+        let paths = obstacles::obstacle_mst(&points, &walls, true).unwrap();
+        let file = "test_output_obstacles.png";
+        assert!(plot_obstacles(&paths, &walls, file).is_ok());
+    }
+
+    #[test]
+    fn test_plot_tour() {
+        let points = generate(20, 1.0, 0, 0, 100, 100).unwrap();
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let file = "test_output_tour.png";
+        assert!(plot_tour(&points, &mst, file).is_ok());
+        assert!(plot_tour(&points, &[], file).is_ok());
+        assert!(plot_tour(&points[..1], &[], file).is_err());
+    }
+
+    #[test]
+    fn test_plot_dendrogram() {
+        let points = generate(20, 1.0, 0, 0, 100, 100).unwrap();
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let links = cluster::linkage(&points, &mst).unwrap();
+        let file = "test_output_dendrogram.png";
+        assert!(plot_dendrogram(&links, file).is_ok());
+        // A cluster merged twice
+        let bad = vec![
+            Link {
+                a: 0,
+                b: 1,
+                distance: 1.0,
+                size: 2,
+            },
+            Link {
+                a: 0,
+                b: 3,
+                distance: 2.0,
+                size: 3,
+            },
+        ];
+        assert!(plot_dendrogram(&bad, file).is_err());
+        assert!(plot_dendrogram(&[], file).is_err());
+    }
+
+    #[test]
+    fn test_edge_indices() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(6, 8)];
+        let edges = vec![Edge::new(6, 8, 0, 0), Edge::new(3, 4, 6, 8)];
+        assert_eq!(edge_indices(&points, &edges), Ok(vec![(2, 0), (1, 2)]));
+        let edges = vec![Edge::new(6, 8, 1, 1)];
+        assert!(edge_indices(&points, &edges).is_err());
+        // Copies of a point are joined to the first copy
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(5, 5),
+            Vertex::new(0, 0),
+            Vertex::new(0, 0),
+        ];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let indices = edge_indices(&points, &tree).unwrap();
+        let mut set = disjoint_set::DisjointSet::new(points.len());
+        assert!(indices.iter().all(|&(u, v)| set.union(u, v)));
+        assert_eq!(set.size(0), points.len());
+    }
+
+    #[test]
+    fn test_plot_threshold() {
+        let edges = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, 30, 40)];
+        assert!(
+            plot_threshold(&edges, "test_output_threshold.png", 10.0).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_length_range() {
+        let edges = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, 9, 12)];
+        assert_eq!(length_range(&edges), (5.0, 10.0));
+        assert_eq!(length_range(&[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_plot_label() {
+        let mut image = RgbImage::new(40, 20);
+        plot_label(&mut image, 0, 0, "1.5", WHITE);
+        assert_eq!(label_width("1.5"), 22);
+        // Top of the '1' stem
+        assert_eq!(*image.get_pixel(2, 0), WHITE);
+        // Labels running off the edge are clipped
+        plot_label(&mut image, 30, 15, "-12", WHITE);
+    }
+
     #[test]
     fn test_minimum_distance() {
         let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
@@ -387,17 +1739,4 @@ mod tests {
         assert!(min_x <= 0);
         assert!(min_y <= 0);
     }
-
-    #[test]
-    fn test_vertices_available() {
-        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
-        let mut vertex_table = Vec::<Item>::new();
-        for (index, p) in points.iter().enumerate() {
-            vertex_table.push(Item::new(index, *p));
-        }
-        assert!(vertices_available(&vertex_table));
-        vertex_table[0].near = 0;
-        vertex_table[1].near = 0;
-        assert!(!vertices_available(&vertex_table));
-    }
 }
@@ -0,0 +1,474 @@
+//! Obstacles
+//!
+//! Spanning trees of points separated by polygonal obstacles, such as walls
+//! and rivers.  An edge may run along the boundary of an obstacle or touch
+//! its corners, but not pass through its inside.
+//!
+//! Edges blocked by an obstacle are either forbidden, or replaced by the
+//! shortest path around the obstacles.  Shortest paths only bend at obstacle
+//! corners, so they are found with Dijkstra's algorithm on the visibility
+//! graph of the corners.
+
+use crate::{prim, Edge, Vertex};
+
+/// Tolerance for points on a boundary
+const EPSILON: f64 = 1e-9;
+
+/// A closed polygon, the last vertex joins the first
+#[derive(Clone)]
+pub struct Polygon {
+    pub vertices: Vec<Vertex>,
+}
+
+impl Polygon {
+    /// Create a polygon from at least three vertices.
+    pub fn new(vertices: Vec<Vertex>) -> Result<Self, &'static str> {
+        if vertices.len() < 3 {
+            return Err("polygon needs at least three vertices");
+        }
+        Ok(Self { vertices })
+    }
+
+    /// Create a polygon from a ring of at least three vertices, as GIS
+    /// formats give them, with the last repeating the first.
+    pub fn from_ring(mut ring: Vec<Vertex>) -> Result<Self, &'static str> {
+        if let (Some(first), Some(last)) = (ring.first(), ring.last()) {
+            if ring.len() > 1 && (first.x, first.y) == (last.x, last.y) {
+                ring.pop();
+            }
+        }
+        Self::new(ring)
+    }
+
+    /// Iterate over the sides of the polygon.
+    pub fn sides(&self) -> impl Iterator<Item = (Vertex, Vertex)> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    /// Determine if a point is strictly inside the polygon, not on its
+    /// boundary.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let mut inside = false;
+        for (a, b) in self.sides() {
+            let (ax, ay, bx, by) =
+                (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+            if on_segment(x, y, ax, ay, bx, by) {
+                return false;
+            }
+            if (ay > y) != (by > y) && x < ax + (y - ay) * (bx - ax) / (by - ay)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Determine if the segment from a to b passes through the inside of
+    /// the polygon.
+    pub fn blocks(&self, a: &Vertex, b: &Vertex) -> bool {
+        // Cheap rejection on bounding boxes
+        let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+        let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+        if self.vertices.iter().all(|p| p.x <= min_x)
+            || self.vertices.iter().all(|p| p.x >= max_x)
+            || self.vertices.iter().all(|p| p.y <= min_y)
+            || self.vertices.iter().all(|p| p.y >= max_y)
+        {
+            return false;
+        }
+
+        // Split the segment where it meets the boundary, and check if any
+        // piece is inside
+        let mut ts = vec![0.0, 1.0];
+        for (c, d) in self.sides() {
+            crossings(a, b, &c, &d, &mut ts);
+        }
+        ts.sort_by(f64::total_cmp);
+        let (dx, dy) = ((b.x - a.x) as f64, (b.y - a.y) as f64);
+        ts.windows(2).any(|t| {
+            if t[1] - t[0] < EPSILON {
+                return false;
+            }
+            let m = (t[0] + t[1]) / 2.0;
+            self.contains(a.x as f64 + m * dx, a.y as f64 + m * dy)
+        })
+    }
+}
+
+/// A tree edge and the corners it bends around.  The length of the edge is
+/// the length of the whole path.
+#[derive(Clone)]
+pub struct Path {
+    pub edge: Edge,
+    /// Obstacle corners in order from u to v
+    pub bends: Vec<Vertex>,
+}
+
+impl Path {
+    /// Create a path from u through the bends to v.
+    pub fn new(u: Vertex, v: Vertex, bends: Vec<Vertex>) -> Self {
+        let mut length = 0.0;
+        let mut last = u;
+        for p in bends.iter().chain(std::iter::once(&v)) {
+            length += last.distance(p);
+            last = *p;
+        }
+        Self {
+            edge: Edge { u, v, length },
+            bends,
+        }
+    }
+
+    /// Get every vertex along the path, from u to v.
+    pub fn vertices(&self) -> Vec<Vertex> {
+        let mut vertices = Vec::with_capacity(self.bends.len() + 2);
+        vertices.push(self.edge.u);
+        vertices.extend_from_slice(&self.bends);
+        vertices.push(self.edge.v);
+        vertices
+    }
+}
+
+/// Determine if the segment from a to b passes through any obstacle.
+pub fn blocked(a: &Vertex, b: &Vertex, obstacles: &[Polygon]) -> bool {
+    obstacles.iter().any(|o| o.blocks(a, b))
+}
+
+/// Find the minimum spanning tree of a set of points among obstacles.
+/// Edges through an obstacle are forbidden, or with `detour` replaced by
+/// the shortest path around the obstacles.  Fails if the obstacles separate
+/// the points.
+pub fn obstacle_mst(
+    points: &[Vertex],
+    obstacles: &[Polygon],
+    detour: bool,
+) -> Result<Vec<Path>, &'static str> {
+    let graph = Visibility::new(points, obstacles);
+    let weight = weights(&graph, detour);
+    let mut paths = Vec::with_capacity(points.len().saturating_sub(1));
+    for (u, v) in spanning_tree(&weight)? {
+        let bends = if graph.visible(u, v) {
+            Vec::new()
+        } else {
+            let (dist, prev) = graph.shortest_paths(u);
+            let (_, corner) = graph.detour(&dist, v);
+            graph.bends(&prev, corner)
+        };
+        paths.push(Path::new(points[u], points[v], bends));
+    }
+    Ok(paths)
+}
+
+/// Find the minimum spanning tree of a set of points among obstacles, with
+/// no edge through an obstacle, as the indices of the end points of each
+/// edge.  Fails if the obstacles separate the points.
+pub fn obstacle_mst_indices(
+    points: &[Vertex],
+    obstacles: &[Polygon],
+) -> Result<Vec<(usize, usize)>, &'static str> {
+    let graph = Visibility::new(points, obstacles);
+    spanning_tree(&weights(&graph, false))
+}
+
+/// Find the distance between every pair of points, infinite if an obstacle
+/// is in the way, or with `detour` the length of the path around them.
+fn weights(graph: &Visibility, detour: bool) -> Vec<Vec<f32>> {
+    let points = graph.points;
+    let n = points.len();
+    let mut weight = vec![vec![f32::INFINITY; n]; n];
+    for u in 0..n {
+        let around = if detour {
+            Some(graph.shortest_paths(u))
+        } else {
+            None
+        };
+        for v in 0..n {
+            if u == v {
+                continue;
+            }
+            weight[u][v] = if graph.visible(u, v) {
+                points[u].distance(&points[v])
+            } else if let Some((dist, _)) = &around {
+                graph.detour(dist, v).0
+            } else {
+                f32::INFINITY
+            };
+        }
+    }
+    weight
+}
+
+/// Run Prim's algorithm on the distances between the points.  Fails if an
+/// edge of the tree has to be infinite.
+fn spanning_tree(
+    weight: &[Vec<f32>],
+) -> Result<Vec<(usize, usize)>, &'static str> {
+    let ends = prim(weight.len(), |u, v| weight[u][v]);
+    if ends.iter().any(|&(u, v)| weight[u][v].is_infinite()) {
+        return Err("obstacles leave the points disconnected");
+    }
+    Ok(ends)
+}
+
+/// Visibility between the points and the obstacle corners
+struct Visibility<'a> {
+    points: &'a [Vertex],
+    obstacles: &'a [Polygon],
+    corners: Vec<Vertex>,
+    /// Corners visible from each point
+    point_corner: Vec<Vec<bool>>,
+    /// Corners visible from each corner
+    corner_corner: Vec<Vec<bool>>,
+}
+
+impl<'a> Visibility<'a> {
+    fn new(points: &'a [Vertex], obstacles: &'a [Polygon]) -> Self {
+        let corners: Vec<Vertex> = obstacles
+            .iter()
+            .flat_map(|o| o.vertices.iter().copied())
+            .collect();
+        let see = |a: &Vertex, b: &Vertex| !blocked(a, b, obstacles);
+        let point_corner = points
+            .iter()
+            .map(|p| corners.iter().map(|c| see(p, c)).collect())
+            .collect();
+        let corner_corner = corners
+            .iter()
+            .map(|a| corners.iter().map(|b| see(a, b)).collect())
+            .collect();
+        Self {
+            points,
+            obstacles,
+            corners,
+            point_corner,
+            corner_corner,
+        }
+    }
+
+    /// Determine if two points can see each other.
+    fn visible(&self, u: usize, v: usize) -> bool {
+        !blocked(&self.points[u], &self.points[v], self.obstacles)
+    }
+
+    /// Find the shortest distance from a point to every corner, and the
+    /// previous corner on each path (usize::MAX for straight from u).
+    fn shortest_paths(&self, u: usize) -> (Vec<f32>, Vec<usize>) {
+        let m = self.corners.len();
+        let p = self.points[u];
+        let mut dist = vec![f32::INFINITY; m];
+        let mut prev = vec![usize::MAX; m];
+        for (c, corner) in self.corners.iter().enumerate() {
+            if self.point_corner[u][c] {
+                dist[c] = p.distance(corner);
+            }
+        }
+        let mut done = vec![false; m];
+        loop {
+            let mut next = usize::MAX;
+            for c in 0..m {
+                if !done[c]
+                    && dist[c].is_finite()
+                    && (next == usize::MAX || dist[c] < dist[next])
+                {
+                    next = c;
+                }
+            }
+            if next == usize::MAX {
+                break;
+            }
+            done[next] = true;
+            for c in 0..m {
+                if !done[c] && self.corner_corner[next][c] {
+                    let d = dist[next]
+                        + self.corners[next].distance(&self.corners[c]);
+                    if d < dist[c] {
+                        dist[c] = d;
+                        prev[c] = next;
+                    }
+                }
+            }
+        }
+        (dist, prev)
+    }
+
+    /// Find the shortest path length to a point via the corners, and the
+    /// last corner on it.
+    fn detour(&self, dist: &[f32], v: usize) -> (f32, usize) {
+        let q = self.points[v];
+        let mut best = (f32::INFINITY, usize::MAX);
+        for (c, &d) in dist.iter().enumerate() {
+            if d.is_finite() && self.point_corner[v][c] {
+                let length = d + self.corners[c].distance(&q);
+                if length < best.0 {
+                    best = (length, c);
+                }
+            }
+        }
+        best
+    }
+
+    /// List the corners on a shortest path ending at a corner, in order.
+    fn bends(&self, prev: &[usize], last: usize) -> Vec<Vertex> {
+        let mut bends = Vec::new();
+        let mut c = last;
+        while c != usize::MAX {
+            bends.push(self.corners[c]);
+            c = prev[c];
+        }
+        bends.reverse();
+        bends
+    }
+}
+
+/// Add the positions along a-b (0 at a, 1 at b) where it meets c-d.
+fn crossings(
+    a: &Vertex,
+    b: &Vertex,
+    c: &Vertex,
+    d: &Vertex,
+    ts: &mut Vec<f64>,
+) {
+    let (rx, ry) = ((b.x - a.x) as f64, (b.y - a.y) as f64);
+    let (qx, qy) = ((d.x - c.x) as f64, (d.y - c.y) as f64);
+    let (wx, wy) = ((c.x - a.x) as f64, (c.y - a.y) as f64);
+    let denom = rx * qy - ry * qx;
+    if denom.abs() < EPSILON {
+        // Parallel, only collinear sides meet a-b
+        if (wx * ry - wy * rx).abs() < EPSILON {
+            let rr = rx * rx + ry * ry;
+            for (px, py) in [(wx, wy), (wx + qx, wy + qy)] {
+                let t = (px * rx + py * ry) / rr;
+                if (0.0..=1.0).contains(&t) {
+                    ts.push(t);
+                }
+            }
+        }
+        return;
+    }
+    let t = (wx * qy - wy * qx) / denom;
+    let s = (wx * ry - wy * rx) / denom;
+    if (-EPSILON..=1.0 + EPSILON).contains(&t)
+        && (-EPSILON..=1.0 + EPSILON).contains(&s)
+    {
+        ts.push(t.clamp(0.0, 1.0));
+    }
+}
+
+/// Determine if point (x, y) is on the segment from a to b.
+fn on_segment(x: f64, y: f64, ax: f64, ay: f64, bx: f64, by: f64) -> bool {
+    let cross = (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+    if cross.abs() > EPSILON * ((bx - ax).abs() + (by - ay).abs() + 1.0) {
+        return false;
+    }
+    x >= ax.min(bx) - EPSILON
+        && x <= ax.max(bx) + EPSILON
+        && y >= ay.min(by) - EPSILON
+        && y <= ay.max(by) + EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wall from (40, 0) to (60, 80)
+    fn wall() -> Polygon {
+        Polygon::new(vec![
+            Vertex::new(40, 0),
+            Vertex::new(60, 0),
+            Vertex::new(60, 80),
+            Vertex::new(40, 80),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_polygon() {
+        let w = wall();
+        assert!(w.contains(50.0, 40.0));
+        assert!(!w.contains(40.0, 40.0));
+        assert!(!w.contains(30.0, 40.0));
+        assert!(
+            Polygon::new(vec![Vertex::new(0, 0), Vertex::new(1, 1)]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let w = wall();
+        // Straight through
+        assert!(w.blocks(&Vertex::new(0, 40), &Vertex::new(100, 40)));
+        // Past the end
+        assert!(!w.blocks(&Vertex::new(0, 90), &Vertex::new(100, 90)));
+        // Along a side and touching a corner
+        assert!(!w.blocks(&Vertex::new(40, -10), &Vertex::new(40, 90)));
+        assert!(!w.blocks(&Vertex::new(0, 80), &Vertex::new(40, 80)));
+        // Corner to corner across the inside
+        assert!(w.blocks(&Vertex::new(40, 0), &Vertex::new(60, 80)));
+        // Corner to corner along a side
+        assert!(!w.blocks(&Vertex::new(40, 0), &Vertex::new(60, 0)));
+    }
+
+    #[test]
+    fn test_obstacle_mst_forbid() {
+        let points = vec![
+            Vertex::new(0, 40),
+            Vertex::new(100, 40),
+            Vertex::new(50, 100),
+        ];
+        let paths = obstacle_mst(&points, &[wall()], false).unwrap();
+        assert_eq!(paths.len(), 2);
+        for p in &paths {
+            assert!(p.bends.is_empty());
+            assert!(!blocked(&p.edge.u, &p.edge.v, &[wall()]));
+        }
+        // Nothing can go round a wall without the third point
+        assert!(obstacle_mst(&points[..2], &[wall()], false).is_err());
+        assert!(obstacle_mst_indices(&points[..2], &[wall()]).is_err());
+    }
+
+    #[test]
+    fn test_obstacle_mst_indices() {
+        // Copies of a point stay apart
+        let points = vec![
+            Vertex::new(0, 40),
+            Vertex::new(100, 40),
+            Vertex::new(50, 100),
+            Vertex::new(0, 40),
+        ];
+        let ends = obstacle_mst_indices(&points, &[wall()]).unwrap();
+        let mut ends: Vec<(usize, usize)> = ends
+            .into_iter()
+            .map(|(u, v)| (u.min(v), u.max(v)))
+            .collect();
+        ends.sort_unstable();
+        assert_eq!(ends, [(0, 2), (0, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn test_obstacle_mst_detour() {
+        let points = vec![Vertex::new(0, 40), Vertex::new(100, 40)];
+        let paths = obstacle_mst(&points, &[wall()], true).unwrap();
+        assert_eq!(paths.len(), 1);
+        let path = &paths[0];
+        // Round the bottom of the wall, the shorter way
+        assert_eq!(path.bends.len(), 2);
+        let expected =
+            2.0 * Vertex::new(0, 40).distance(&Vertex::new(40, 80)) + 20.0;
+        assert!((path.edge.len() - expected).abs() < 1e-3);
+        let vertices = path.vertices();
+        for leg in vertices.windows(2) {
+            assert!(!blocked(&leg[0], &leg[1], &[wall()]));
+        }
+    }
+
+    #[test]
+    fn test_obstacle_mst_no_obstacles() {
+        let points = crate::generate(30, 1.0, 0, 0, 100, 100).unwrap();
+        let paths = obstacle_mst(&points, &[], true).unwrap();
+        let mst = crate::minimum_spanning_tree(&points).unwrap();
+        let a: f32 = paths.iter().map(|p| p.edge.len()).sum();
+        let b: f32 = mst.iter().map(|e| e.len()).sum();
+        assert!((a - b).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,204 @@
+//! Rectilinear Steiner Tree
+//!
+//! Approximate the rectilinear Steiner minimal tree of a set of points, the
+//! shortest network of horizontal and vertical wires connecting them, as
+//! used to estimate wiring in VLSI routing.  Distances are Manhattan
+//! distances.
+//!
+//! Starting from the rectilinear minimum spanning tree, a pair of edges
+//! meeting at a vertex is replaced by a Steiner point at the median of the
+//! three vertices wherever that is shorter, as for the Euclidean Steiner
+//! tree.  Medians take their coordinates from the vertices, so every Steiner
+//! point is on the Hanan grid of the points.  Each edge of the tree is then
+//! wired as an L, a horizontal segment followed by a vertical one.
+
+use crate::steiner::{insert_steiner_points, Metric, SteinerTree};
+use crate::{prim, Edge, Vertex};
+
+/// Manhattan distance
+const MANHATTAN: Metric = Metric {
+    distance: manhattan,
+    junction: median3,
+    median,
+};
+
+/// Find the Manhattan distance between two points.
+pub fn manhattan(a: &Vertex, b: &Vertex) -> f32 {
+    ((a.x - b.x).abs() + (a.y - b.y).abs()) as f32
+}
+
+/// Find the minimum spanning tree of a set of points with Manhattan
+/// distances.  The length of each edge is its Manhattan distance.
+pub fn rectilinear_mst(points: &[Vertex]) -> Vec<Edge> {
+    prim(points.len(), |u, v| manhattan(&points[u], &points[v]))
+        .into_iter()
+        .map(|(u, v)| Edge {
+            u: points[u],
+            v: points[v],
+            length: manhattan(&points[u], &points[v]),
+        })
+        .collect()
+}
+
+/// Find a short rectilinear Steiner tree of a set of points.  Edge lengths
+/// are Manhattan distances, use `segments` to wire the edges.
+pub fn rectilinear_steiner_tree(
+    points: &[Vertex],
+) -> Result<SteinerTree, &'static str> {
+    let mst = prim(points.len(), |u, v| manhattan(&points[u], &points[v]));
+    Ok(insert_steiner_points(points, &mst, MANHATTAN))
+}
+
+/// Wire edges with horizontal and vertical segments.  An edge that is not
+/// already axis-aligned becomes an L: horizontal from u, then vertical to v.
+pub fn segments(edges: &[Edge]) -> Vec<Edge> {
+    let mut wires = Vec::with_capacity(2 * edges.len());
+    for e in edges {
+        if e.u.x == e.v.x || e.u.y == e.v.y {
+            wires.push(Edge {
+                u: e.u,
+                v: e.v,
+                length: manhattan(&e.u, &e.v),
+            });
+        } else {
+            let corner = Vertex::new(e.v.x, e.u.y);
+            for (u, v) in [(e.u, corner), (corner, e.v)] {
+                wires.push(Edge {
+                    u,
+                    v,
+                    length: manhattan(&u, &v),
+                });
+            }
+        }
+    }
+    wires
+}
+
+/// Wire edges given by the indices of their end points with horizontal and
+/// vertical segments, as `segments` does.  Returns the points followed by
+/// the corners of the Ls, and the wires with the indices of their ends.
+pub fn indexed_segments(
+    points: &[Vertex],
+    ends: &[(usize, usize)],
+) -> (Vec<Vertex>, Vec<Edge>, Vec<(usize, usize)>) {
+    let mut vertices = points.to_vec();
+    let mut wires = Vec::with_capacity(2 * ends.len());
+    let mut wire_ends = Vec::with_capacity(2 * ends.len());
+    let mut wire = |vertices: &[Vertex], u: usize, v: usize| {
+        wires.push(Edge {
+            u: vertices[u],
+            v: vertices[v],
+            length: manhattan(&vertices[u], &vertices[v]),
+        });
+        wire_ends.push((u, v));
+    };
+    for &(u, v) in ends {
+        let (a, b) = (points[u], points[v]);
+        if a.x == b.x || a.y == b.y {
+            wire(&vertices, u, v);
+        } else {
+            let corner = vertices.len();
+            vertices.push(Vertex::new(b.x, a.y));
+            wire(&vertices, u, corner);
+            wire(&vertices, corner, v);
+        }
+    }
+    (vertices, wires, wire_ends)
+}
+
+/// Find the rectilinear Steiner point of three points, the median of their
+/// coordinates.
+fn median3(points: &[Vertex; 3]) -> Option<Vertex> {
+    median(points)
+}
+
+/// Find the point minimising the total Manhattan distance to a set of
+/// points, the median of their coordinates.
+fn median(points: &[Vertex]) -> Option<Vertex> {
+    if points.is_empty() {
+        return None;
+    }
+    let mut xs: Vec<i32> = points.iter().map(|p| p.x).collect();
+    let mut ys: Vec<i32> = points.iter().map(|p| p.y).collect();
+    xs.sort_unstable();
+    ys.sort_unstable();
+    let m = (points.len() - 1) / 2;
+    Some(Vertex::new(xs[m], ys[m]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate;
+
+    /// Points at the ends of a plus sign
+    fn plus() -> Vec<Vertex> {
+        vec![
+            Vertex::new(0, 50),
+            Vertex::new(100, 50),
+            Vertex::new(50, 0),
+            Vertex::new(50, 100),
+        ]
+    }
+
+    #[test]
+    fn test_rectilinear_mst() {
+        let tree = rectilinear_mst(&plus());
+        assert_eq!(tree.len(), 3);
+        let length: f32 = tree.iter().map(|e| e.len()).sum();
+        assert_eq!(length, 300.0);
+        assert!(rectilinear_mst(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_rectilinear_steiner_tree() {
+        let tree = rectilinear_steiner_tree(&plus()).unwrap();
+        assert_eq!(tree.len(), 200.0);
+        assert_eq!(tree.steiner.len(), 1);
+        assert_eq!((tree.steiner[0].x, tree.steiner[0].y), (50, 50));
+    }
+
+    #[test]
+    fn test_rectilinear_steiner_tree_random() {
+        let points = generate(60, 1.0, 0, 0, 300, 300).unwrap();
+        let mst: f32 = rectilinear_mst(&points).iter().map(|e| e.len()).sum();
+        let tree = rectilinear_steiner_tree(&points).unwrap();
+        assert_eq!(tree.edges.len(), points.len() + tree.steiner.len() - 1);
+        assert!(tree.len() <= mst);
+        // The rectilinear MST is at most 3/2 the Steiner minimal tree
+        assert!(tree.len() >= mst * 2.0 / 3.0);
+        // Steiner points are on the Hanan grid
+        for s in &tree.steiner {
+            assert!(points.iter().any(|p| p.x == s.x));
+            assert!(points.iter().any(|p| p.y == s.y));
+        }
+    }
+
+    #[test]
+    fn test_segments() {
+        let edges = vec![
+            Edge::new(0, 0, 10, 20),
+            Edge::new(5, 5, 5, 15),
+            Edge::new(0, 0, 3, 0),
+        ];
+        let wires = segments(&edges);
+        assert_eq!(wires.len(), 4);
+        assert!(wires.iter().all(|w| w.u.x == w.v.x || w.u.y == w.v.y));
+        assert_eq!((wires[0].v.x, wires[0].v.y), (10, 0));
+        let length: f32 = wires.iter().map(|w| w.len()).sum();
+        assert_eq!(length, 30.0 + 10.0 + 3.0);
+    }
+
+    #[test]
+    fn test_indexed_segments() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(10, 20), Vertex::new(0, 7)];
+        let (vertices, wires, ends) =
+            indexed_segments(&points, &[(0, 1), (2, 0)]);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!((vertices[3].x, vertices[3].y), (10, 0));
+        assert_eq!(ends, [(0, 3), (3, 1), (2, 0)]);
+        let length: f32 = wires.iter().map(|w| w.len()).sum();
+        assert_eq!(length, 30.0 + 7.0);
+    }
+}
@@ -0,0 +1,203 @@
+//! Sensitivity
+//!
+//! How much the minimum spanning tree depends on each of its edges.  If a
+//! tree edge fails, the best replacement is the shortest edge not in the
+//! tree that reconnects the two halves, and the cost of the failure is the
+//! difference in length.  The second-best spanning tree swaps the tree edge
+//! with the cheapest replacement.
+//!
+//! Every edge not in the tree can replace the tree edges on the tree path
+//! between its ends.  From each point in turn, the tree is walked away from
+//! the point, finding the nearest point not joined to it by a tree edge in
+//! the part of the tree beyond each edge; that edge can be replaced by the
+//! edge to that point.  The best replacement over all the points takes
+//! O(n^2) time, and memory only for the tree, never holding the edges of
+//! the complete graph.
+
+use crate::tree::MstTree;
+use crate::{Edge, Vertex};
+
+/// A tree edge and its best replacement
+#[derive(Clone)]
+pub struct Replacement {
+    /// The tree edge
+    pub edge: Edge,
+    /// The shortest edge reconnecting the tree without it, if there is one
+    pub replacement: Option<Edge>,
+}
+
+impl Replacement {
+    /// Get the increase in the length of the tree if the edge is replaced,
+    /// None if the edge can't be replaced.
+    pub fn delta(&self) -> Option<f32> {
+        self.replacement.map(|r| r.len() - self.edge.len())
+    }
+}
+
+/// Find the best replacement for each edge of the minimum spanning tree of
+/// a completely connected set of points, in the order of the tree edges.
+pub fn replacement_costs(
+    points: &[Vertex],
+    tree: &[Edge],
+) -> Result<Vec<Replacement>, &'static str> {
+    let n = points.len();
+    let tree = MstTree::new(points, tree)?;
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    // best[k] is the length and ends of the best replacement for edge k.
+    // From each point a, near[v] is the nearest point to a, not joined to
+    // it by a tree edge, in the subtree below v with the tree rooted at a.
+    let mut best: Vec<Option<(f32, usize, usize)>> = vec![None; n - 1];
+    let mut order = Vec::with_capacity(n);
+    let mut parent = vec![(0, 0); n];
+    let mut near = vec![(f32::INFINITY, 0); n];
+    for a in 0..n {
+        // Each vertex after its parent, the vertex and edge towards a
+        order.clear();
+        order.push(a);
+        let mut i = 0;
+        while i < order.len() {
+            let v = order[i];
+            for &(w, k) in tree.adjacent(v) {
+                if v == a || w != parent[v].0 {
+                    parent[w] = (v, k);
+                    order.push(w);
+                }
+            }
+            i += 1;
+        }
+
+        for &v in &order {
+            near[v] = if v == a || parent[v].0 == a {
+                (f32::INFINITY, v)
+            } else {
+                (points[a].distance(&points[v]), v)
+            };
+        }
+        for &v in order[1..].iter().rev() {
+            let (p, k) = parent[v];
+            let (length, b) = near[v];
+            if length < best[k].map_or(f32::INFINITY, |(d, _, _)| d) {
+                best[k] = Some((length, a.min(b), a.max(b)));
+            }
+            if length < near[p].0 {
+                near[p] = near[v];
+            }
+        }
+    }
+
+    Ok(tree
+        .edges()
+        .iter()
+        .zip(best)
+        .map(|(e, best)| Replacement {
+            edge: *e,
+            replacement: best.map(|(length, u, v)| Edge {
+                u: points[u],
+                v: points[v],
+                length,
+            }),
+        })
+        .collect())
+}
+
+/// Find the second-best spanning tree of a completely connected set of
+/// points from its minimum spanning tree, by making the replacement that
+/// adds the least length.  Fails if there is only one spanning tree.
+pub fn second_best_mst(
+    points: &[Vertex],
+    tree: &[Edge],
+) -> Result<Vec<Edge>, &'static str> {
+    let replacements = replacement_costs(points, tree)?;
+    let cheapest = replacements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| r.delta().map(|d| (d, i)))
+        .min_by(|a, b| a.0.total_cmp(&b.0));
+    match cheapest {
+        Some((_, i)) => {
+            let mut edges = tree.to_vec();
+            edges[i] = replacements[i].replacement.unwrap();
+            Ok(edges)
+        }
+        None => Err("there is only one spanning tree"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edge_indices, generate, minimum_spanning_tree};
+
+    /// Length of the minimum spanning tree without one edge, by Kruskal
+    fn without(points: &[Vertex], u: usize, v: usize) -> f32 {
+        let n = points.len();
+        let mut edges = Vec::new();
+        for a in 0..n {
+            for b in a + 1..n {
+                if (a, b) != (u.min(v), u.max(v)) {
+                    edges.push((points[a].distance(&points[b]), a, b));
+                }
+            }
+        }
+        edges.sort_by(|x, y| x.0.total_cmp(&y.0));
+        let mut set = crate::disjoint_set::DisjointSet::new(n);
+        let mut length = 0.0;
+        for (d, a, b) in edges {
+            if set.find(a) != set.find(b) {
+                set.union(a, b);
+                length += d;
+            }
+        }
+        length
+    }
+
+    #[test]
+    fn test_replacement_costs() {
+        let points = generate(40, 1.0, 0, 0, 100, 100).unwrap();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let mst: f32 = tree.iter().map(|e| e.len()).sum();
+        let replacements = replacement_costs(&points, &tree).unwrap();
+        assert_eq!(replacements.len(), tree.len());
+        let indices = edge_indices(&points, &tree).unwrap();
+        for (r, &(u, v)) in replacements.iter().zip(&indices) {
+            let delta = r.delta().unwrap();
+            assert!(delta >= 0.0);
+            let expected = without(&points, u, v) - mst;
+            assert!((delta - expected).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_second_best_mst() {
+        // A rectangle with one corner pulled out
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(10, 20),
+            Vertex::new(0, 21),
+        ];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let mst: f32 = tree.iter().map(|e| e.len()).sum();
+        let second = second_best_mst(&points, &tree).unwrap();
+        assert_eq!(second.len(), 3);
+        let length: f32 = second.iter().map(|e| e.len()).sum();
+        // Swap the side of length 20 for the side of length 21
+        assert!((length - mst - 1.0).abs() < 1e-4);
+        assert!(second.iter().any(|e| e.len() == 21.0));
+        assert!(!second.iter().any(|e| e.len() == 20.0));
+    }
+
+    #[test]
+    fn test_only_one_tree() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(3, 4)];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let replacements = replacement_costs(&points, &tree).unwrap();
+        assert!(replacements[0].replacement.is_none());
+        assert!(replacements[0].delta().is_none());
+        assert!(second_best_mst(&points, &tree).is_err());
+        assert!(replacement_costs(&points, &[]).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Statistics
+//!
+//! Summary statistics of a spanning tree: its length, the distribution of
+//! edge lengths and vertex degrees, and its shape.  The diameter is the
+//! longest path in the tree, counted in edges (hops) or by length, and is
+//! found by walking from any vertex to the farthest vertex, then from there
+//! to the farthest again.  The centre is the vertex on the weighted diameter
+//! closest to its middle, the vertex whose farthest vertex is nearest.
+
+use crate::tree::MstTree;
+use crate::Vertex;
+use serde::Serialize;
+use std::fmt;
+
+/// Default number of edge length histogram bins
+pub const DEFAULT_BINS: usize = 10;
+
+/// A histogram bin of edge lengths, from lower up to upper
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Bin {
+    /// Shortest length in the bin
+    pub lower: f32,
+    /// Longest length in the bin, exclusive except for the last bin
+    pub upper: f32,
+    /// Number of edges in the bin
+    pub count: usize,
+}
+
+/// Summary statistics of a tree
+#[derive(Clone, Serialize)]
+pub struct TreeStats {
+    /// Number of vertices
+    pub vertices: usize,
+    /// Number of edges
+    pub edges: usize,
+    /// Total length of the edges
+    pub total_length: f32,
+    /// Mean edge length
+    pub mean_length: f32,
+    /// Median edge length
+    pub median_length: f32,
+    /// Shortest edge length
+    pub min_length: f32,
+    /// Longest edge length
+    pub max_length: f32,
+    /// Edge lengths in equal width bins from shortest to longest
+    pub length_histogram: Vec<Bin>,
+    /// Number of vertices with each degree, indexed by degree
+    pub degree_histogram: Vec<usize>,
+    /// Number of vertices with one edge
+    pub leaves: usize,
+    /// Most edges on any path
+    pub diameter_hops: usize,
+    /// Longest path length
+    pub diameter_length: f32,
+    /// Vertex nearest the middle of the longest path
+    pub centre: Option<Vertex>,
+}
+
+/// Find the statistics of a tree, with the edge lengths in the given number
+/// of histogram bins.
+pub fn tree_stats(tree: &MstTree, bins: usize) -> TreeStats {
+    let n = tree.num_vertices();
+    let mut lengths: Vec<f32> = tree.edges().iter().map(|e| e.len()).collect();
+    lengths.sort_by(f32::total_cmp);
+    let total_length: f32 = lengths.iter().sum();
+    let m = lengths.len();
+    let (mean_length, median_length, min_length, max_length) = if m == 0 {
+        (0.0, 0.0, 0.0, 0.0)
+    } else if m % 2 == 1 {
+        (
+            total_length / m as f32,
+            lengths[m / 2],
+            lengths[0],
+            lengths[m - 1],
+        )
+    } else {
+        let median = (lengths[m / 2 - 1] + lengths[m / 2]) / 2.0;
+        (total_length / m as f32, median, lengths[0], lengths[m - 1])
+    };
+
+    let mut degree_histogram = Vec::new();
+    for v in 0..n {
+        let d = tree.degree(v);
+        if d >= degree_histogram.len() {
+            degree_histogram.resize(d + 1, 0);
+        }
+        degree_histogram[d] += 1;
+    }
+    let leaves = degree_histogram.get(1).copied().unwrap_or(0);
+
+    // Diameters, and the centre on the weighted diameter
+    let (mut diameter_hops, mut diameter_length, mut centre) = (0, 0.0, None);
+    if n > 0 {
+        let hops = |_: usize| 1.0;
+        let length = |k: usize| tree.edges()[k].len();
+        let (a, _) = farthest(tree, 0, hops);
+        let (b, from_a) = farthest(tree, a, hops);
+        diameter_hops = from_a[b] as usize;
+
+        let (a, _) = farthest(tree, 0, length);
+        let (b, from_a) = farthest(tree, a, length);
+        let (_, from_b) = farthest(tree, b, length);
+        diameter_length = from_a[b];
+        let middle = (0..n)
+            .filter(|&v| (from_a[v] + from_b[v] - diameter_length).abs() < 1e-3)
+            .min_by(|&u, &v| {
+                from_a[u]
+                    .max(from_b[u])
+                    .total_cmp(&from_a[v].max(from_b[v]))
+            });
+        centre = middle.map(|v| tree.points()[v]);
+    }
+
+    TreeStats {
+        vertices: n,
+        edges: m,
+        total_length,
+        mean_length,
+        median_length,
+        min_length,
+        max_length,
+        length_histogram: histogram(&lengths, bins),
+        degree_histogram,
+        leaves,
+        diameter_hops,
+        diameter_length,
+        centre,
+    }
+}
+
+/// Find the distance from a vertex to every vertex, weighing each edge by
+/// index, and the farthest vertex.
+fn farthest<F>(tree: &MstTree, source: usize, weight: F) -> (usize, Vec<f32>)
+where
+    F: Fn(usize) -> f32,
+{
+    let n = tree.num_vertices();
+    let mut distance = vec![f32::INFINITY; n];
+    distance[source] = 0.0;
+    let mut stack = vec![source];
+    let mut far = source;
+    while let Some(v) = stack.pop() {
+        if distance[v] > distance[far] {
+            far = v;
+        }
+        for &(w, k) in tree.adjacent(v) {
+            if distance[w].is_infinite() {
+                distance[w] = distance[v] + weight(k);
+                stack.push(w);
+            }
+        }
+    }
+    (far, distance)
+}
+
+/// Count sorted lengths in equal width bins from shortest to longest.
+fn histogram(lengths: &[f32], bins: usize) -> Vec<Bin> {
+    if lengths.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    let (lo, hi) = (lengths[0], lengths[lengths.len() - 1]);
+    let width = (hi - lo) / bins as f32;
+    let mut histogram: Vec<Bin> = (0..bins)
+        .map(|i| Bin {
+            lower: lo + i as f32 * width,
+            upper: if i + 1 == bins {
+                hi
+            } else {
+                lo + (i + 1) as f32 * width
+            },
+            count: 0,
+        })
+        .collect();
+    for &l in lengths {
+        let i = if width > 0.0 {
+            (((l - lo) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        histogram[i].count += 1;
+    }
+    histogram
+}
+
+impl fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Vertices        : {}", self.vertices)?;
+        writeln!(f, "Edges           : {}", self.edges)?;
+        writeln!(f, "Total length    : {}", self.total_length)?;
+        writeln!(f, "Mean length     : {}", self.mean_length)?;
+        writeln!(f, "Median length   : {}", self.median_length)?;
+        writeln!(f, "Min length      : {}", self.min_length)?;
+        writeln!(f, "Max length      : {}", self.max_length)?;
+        writeln!(f, "Leaves          : {}", self.leaves)?;
+        writeln!(f, "Diameter (hops) : {}", self.diameter_hops)?;
+        writeln!(f, "Diameter        : {}", self.diameter_length)?;
+        match self.centre {
+            Some(c) => writeln!(f, "Centre          : ({}, {})", c.x, c.y)?,
+            None => writeln!(f, "Centre          : none")?,
+        }
+        writeln!(f, "Edge lengths")?;
+        let most = self.length_histogram.iter().map(|b| b.count).max();
+        for b in &self.length_histogram {
+            writeln!(
+                f,
+                "  {:>10.3} - {:<10.3} {:>6} {}",
+                b.lower,
+                b.upper,
+                b.count,
+                bar(b.count, most.unwrap_or(0))
+            )?;
+        }
+        writeln!(f, "Degrees")?;
+        let most = self.degree_histogram.iter().max();
+        for (d, &count) in self.degree_histogram.iter().enumerate() {
+            if count > 0 {
+                writeln!(
+                    f,
+                    "  {:>4} {:>6} {}",
+                    d,
+                    count,
+                    bar(count, *most.unwrap_or(&0))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TreeStats {
+    /// Format the statistics as a JSON object.  Lengths that are not
+    /// finite, as of a tree without edges, are null.
+    pub fn to_json(&self) -> String {
+        // Plain data, so serialising cannot fail
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Draw a bar of up to 40 characters for a count.
+fn bar(count: usize, most: usize) -> String {
+    if most == 0 {
+        return String::new();
+    }
+    "#".repeat((count * 40).div_ceil(most))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Edge;
+
+    /// A star of three arms, lengths 1, 2 and 4, with a tail of 3 on the
+    /// longest arm
+    fn star() -> MstTree {
+        let edges = vec![
+            Edge::new(0, 0, 1, 0),
+            Edge::new(0, 0, 0, 2),
+            Edge::new(0, 0, -4, 0),
+            Edge::new(-4, 0, -4, 3),
+        ];
+        MstTree::from_edges(&edges).unwrap()
+    }
+
+    #[test]
+    fn test_tree_stats() {
+        let stats = tree_stats(&star(), 3);
+        assert_eq!(stats.vertices, 5);
+        assert_eq!(stats.edges, 4);
+        assert_eq!(stats.total_length, 10.0);
+        assert_eq!(stats.mean_length, 2.5);
+        assert_eq!(stats.median_length, 2.5);
+        assert_eq!((stats.min_length, stats.max_length), (1.0, 4.0));
+        assert_eq!(stats.degree_histogram, vec![0, 3, 1, 1]);
+        assert_eq!(stats.leaves, 3);
+        assert_eq!(stats.diameter_hops, 3);
+        // From the end of the tail to the end of the arm of length 2
+        assert_eq!(stats.diameter_length, 9.0);
+        let centre = stats.centre.unwrap();
+        assert_eq!((centre.x, centre.y), (-4, 0));
+        let counts: Vec<usize> =
+            stats.length_histogram.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![1, 1, 2]);
+        assert_eq!(stats.length_histogram[2].upper, 4.0);
+    }
+
+    #[test]
+    fn test_tree_stats_empty() {
+        let tree = MstTree::new(&[], &[]).unwrap();
+        let stats = tree_stats(&tree, DEFAULT_BINS);
+        assert_eq!(stats.vertices, 0);
+        assert!(stats.length_histogram.is_empty());
+        assert!(stats.centre.is_none());
+        let single = MstTree::new(&[Vertex::new(1, 1)], &[]).unwrap();
+        let stats = tree_stats(&single, DEFAULT_BINS);
+        assert_eq!(stats.degree_histogram, vec![1]);
+        assert_eq!(stats.diameter_hops, 0);
+    }
+
+    #[test]
+    fn test_formats() {
+        let stats = tree_stats(&star(), 2);
+        let text = stats.to_string();
+        assert!(text.contains("Total length    : 10"));
+        assert!(text.contains("Diameter (hops) : 3"));
+        let json: serde_json::Value =
+            serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["degree_histogram"], serde_json::json!([0, 3, 1, 1]));
+        assert_eq!(json["centre"], serde_json::json!({"x": -4, "y": 0}));
+        let bin = serde_json::json!({"lower": 1.0, "upper": 2.5, "count": 2});
+        assert_eq!(json["length_histogram"][0], bin);
+
+        // Not finite lengths are null, not invalid JSON
+        let mut stats = stats;
+        stats.mean_length = f32::NAN;
+        stats.min_length = f32::INFINITY;
+        let json: serde_json::Value =
+            serde_json::from_str(&stats.to_json()).unwrap();
+        assert!(json["mean_length"].is_null() && json["min_length"].is_null());
+    }
+}
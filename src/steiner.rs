@@ -0,0 +1,405 @@
+//! Euclidean Steiner Tree
+//!
+//! Approximate the Steiner minimal tree of a set of points, the shortest
+//! network connecting them when extra junctions (Steiner points) may be
+//! added.  The minimum spanning tree is at most 2/sqrt(3) times longer.
+//!
+//! Starting from the minimum spanning tree, each pair of edges meeting at a
+//! vertex at less than 120 degrees can be shortened by joining the three
+//! vertices at the Fermat point of their triangle instead, or by joining
+//! one vertex to the other when the Fermat point is on it.  The pair giving
+//! the biggest saving is replaced, Steiner points are moved to the point
+//! minimising the length of their edges, and this repeats until nothing
+//! improves.  Steiner points left joining fewer than three edges are
+//! removed.  Points have integer coordinates, so Steiner points are rounded
+//! to the nearest integer and only kept if the rounded point still helps.
+
+use crate::{minimum_spanning_tree_indices, Edge, Vertex};
+use std::collections::HashSet;
+
+/// Smallest saving in length worth adding a Steiner point for
+const MINIMUM_GAIN: f32 = 1e-3;
+
+/// Iterations of Weiszfeld's algorithm when placing a Steiner point
+const MEDIAN_ITERATIONS: usize = 64;
+
+/// A Steiner tree: its edges and the Steiner points added to the original
+/// set of points.
+#[derive(Clone)]
+pub struct SteinerTree {
+    /// Edges joining the points and the Steiner points
+    pub edges: Vec<Edge>,
+    /// The added Steiner points
+    pub steiner: Vec<Vertex>,
+    /// Indices of the end points of each edge, into the points followed by
+    /// the Steiner points
+    pub ends: Vec<(usize, usize)>,
+}
+
+impl SteinerTree {
+    /// Get the total length of the tree.
+    pub fn len(&self) -> f32 {
+        self.edges.iter().map(|e| e.len()).sum()
+    }
+
+    /// Check if the tree has no edges.
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+/// How distances are measured and where Steiner points go
+#[derive(Copy, Clone)]
+pub(crate) struct Metric {
+    /// Distance between two points
+    pub distance: fn(&Vertex, &Vertex) -> f32,
+    /// Best junction for three points, if it isn't one of them
+    pub junction: fn(&[Vertex; 3]) -> Option<Vertex>,
+    /// Point minimising the total distance to a set of points
+    pub median: fn(&[Vertex]) -> Option<Vertex>,
+}
+
+/// Straight line distance
+const EUCLIDEAN: Metric = Metric {
+    distance: Vertex::distance,
+    junction: fermat_point,
+    median: geometric_median,
+};
+
+/// Find a short Steiner tree of a set of points by Fermat point insertion on
+/// the minimum spanning tree.
+pub fn steiner_tree(points: &[Vertex]) -> Result<SteinerTree, &'static str> {
+    let mst = minimum_spanning_tree_indices(points);
+    Ok(insert_steiner_points(points, &mst, EUCLIDEAN))
+}
+
+/// Shorten a spanning tree of the points, given by the indices of the end
+/// points of its edges, by inserting Steiner points at the junctions of
+/// pairs of edges, measuring with the given metric.
+pub(crate) fn insert_steiner_points(
+    points: &[Vertex],
+    tree: &[(usize, usize)],
+    metric: Metric,
+) -> SteinerTree {
+    let mut graph = Graph::new(points, tree, metric);
+    while graph.insert_best() {
+        graph.relax();
+        graph.prune();
+    }
+    graph.into_tree()
+}
+
+/// A tree of the points and Steiner points, with vertex indices
+struct Graph {
+    metric: Metric,
+    /// Number of original points, Steiner points follow them
+    n: usize,
+    nodes: Vec<Vertex>,
+    adjacent: Vec<Vec<usize>>,
+    /// Steiner points that have been removed
+    removed: Vec<bool>,
+    /// Coordinates in use, a Steiner point can't sit on another vertex
+    occupied: HashSet<(i32, i32)>,
+}
+
+impl Graph {
+    fn new(points: &[Vertex], tree: &[(usize, usize)], metric: Metric) -> Self {
+        let mut adjacent = vec![Vec::new(); points.len()];
+        for &(u, v) in tree {
+            adjacent[u].push(v);
+            adjacent[v].push(u);
+        }
+        Self {
+            metric,
+            n: points.len(),
+            nodes: points.to_vec(),
+            adjacent,
+            removed: vec![false; points.len()],
+            occupied: points.iter().map(|p| (p.x, p.y)).collect(),
+        }
+    }
+
+    fn distance(&self, a: usize, b: usize) -> f32 {
+        (self.metric.distance)(&self.nodes[a], &self.nodes[b])
+    }
+
+    /// Replace the pair of edges (v, a), (v, b) giving the biggest saving
+    /// with edges from a Steiner point to a, b and v.  If the junction is a
+    /// or b itself, the other is joined to it instead.  Returns false if no
+    /// pair of edges can be shortened.
+    fn insert_best(&mut self) -> bool {
+        let mut best: Option<(f32, usize, usize, usize, Vertex)> = None;
+        for v in 0..self.nodes.len() {
+            let adj = &self.adjacent[v];
+            for (i, &a) in adj.iter().enumerate() {
+                for &b in &adj[i + 1..] {
+                    let f = match (self.metric.junction)(&[
+                        self.nodes[a],
+                        self.nodes[v],
+                        self.nodes[b],
+                    ]) {
+                        Some(f) => f,
+                        None => continue,
+                    };
+                    let at = |w: usize| {
+                        (self.nodes[w].x, self.nodes[w].y) == (f.x, f.y)
+                    };
+                    if !at(a) && !at(b) && self.occupied.contains(&(f.x, f.y)) {
+                        continue;
+                    }
+                    let d =
+                        |w: usize| (self.metric.distance)(&f, &self.nodes[w]);
+                    let gain = self.distance(v, a) + self.distance(v, b)
+                        - d(a)
+                        - d(b)
+                        - d(v);
+                    if gain > best.map_or(MINIMUM_GAIN, |b| b.0) {
+                        best = Some((gain, v, a, b, f));
+                    }
+                }
+            }
+        }
+
+        let (_, v, a, b, f) = match best {
+            Some(best) => best,
+            None => return false,
+        };
+        for (w, other) in [(a, b), (b, a)] {
+            if (self.nodes[w].x, self.nodes[w].y) == (f.x, f.y) {
+                // Move the edge (v, other) to (w, other)
+                self.adjacent[v].retain(|&x| x != other);
+                self.adjacent[other].retain(|&x| x != v);
+                self.adjacent[w].push(other);
+                self.adjacent[other].push(w);
+                return true;
+            }
+        }
+        let s = self.nodes.len();
+        self.nodes.push(f);
+        self.removed.push(false);
+        self.occupied.insert((f.x, f.y));
+        self.adjacent.push(vec![a, v, b]);
+        self.adjacent[v].retain(|&w| w != a && w != b);
+        self.adjacent[v].push(s);
+        for w in [a, b] {
+            for x in self.adjacent[w].iter_mut() {
+                if *x == v {
+                    *x = s;
+                }
+            }
+        }
+        true
+    }
+
+    /// Move each Steiner point to the rounded point minimising the length of
+    /// its edges, if that is free and shorter.
+    fn relax(&mut self) {
+        for s in self.n..self.nodes.len() {
+            if self.removed[s] {
+                continue;
+            }
+            let neighbours: Vec<Vertex> =
+                self.adjacent[s].iter().map(|&w| self.nodes[w]).collect();
+            let target = match (self.metric.median)(&neighbours) {
+                Some(t) => t,
+                None => continue,
+            };
+            let current = self.nodes[s];
+            if self.occupied.contains(&(target.x, target.y)) {
+                continue;
+            }
+            let d = self.metric.distance;
+            let before: f32 = neighbours.iter().map(|p| d(&current, p)).sum();
+            let after: f32 = neighbours.iter().map(|p| d(&target, p)).sum();
+            if after < before - MINIMUM_GAIN {
+                self.occupied.remove(&(current.x, current.y));
+                self.occupied.insert((target.x, target.y));
+                self.nodes[s] = target;
+            }
+        }
+    }
+
+    /// Remove Steiner points joining fewer than three edges.  A Steiner
+    /// point with two edges is replaced by a single straight edge.
+    fn prune(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for s in self.n..self.nodes.len() {
+                if self.removed[s] || self.adjacent[s].len() >= 3 {
+                    continue;
+                }
+                let neighbours = std::mem::take(&mut self.adjacent[s]);
+                for &w in &neighbours {
+                    self.adjacent[w].retain(|&x| x != s);
+                }
+                if let [a, b] = neighbours[..] {
+                    self.adjacent[a].push(b);
+                    self.adjacent[b].push(a);
+                }
+                self.removed[s] = true;
+                let p = self.nodes[s];
+                self.occupied.remove(&(p.x, p.y));
+                changed = true;
+            }
+        }
+    }
+
+    fn into_tree(self) -> SteinerTree {
+        // Number the Steiner points that are left after the points
+        let kept: Vec<usize> = (self.n..self.nodes.len())
+            .filter(|&s| !self.removed[s])
+            .collect();
+        let mut index: Vec<usize> = (0..self.nodes.len()).collect();
+        for (i, &s) in kept.iter().enumerate() {
+            index[s] = self.n + i;
+        }
+        let mut edges = Vec::with_capacity(self.nodes.len());
+        let mut ends = Vec::with_capacity(self.nodes.len());
+        for (u, adj) in self.adjacent.iter().enumerate() {
+            for &v in adj.iter().filter(|&&v| u < v) {
+                edges.push(Edge {
+                    u: self.nodes[u],
+                    v: self.nodes[v],
+                    length: self.distance(u, v),
+                });
+                ends.push((index[u], index[v]));
+            }
+        }
+        let steiner = kept.iter().map(|&s| self.nodes[s]).collect();
+        SteinerTree {
+            edges,
+            steiner,
+            ends,
+        }
+    }
+}
+
+/// Find the Fermat point of a triangle, rounded to integer coordinates, the
+/// point minimising the total distance to the three corners.  Returns None
+/// if an angle is 120 degrees or more, when the Fermat point is that corner.
+pub fn fermat_point(triangle: &[Vertex; 3]) -> Option<Vertex> {
+    for i in 0..3 {
+        let p = triangle[i];
+        let a = triangle[(i + 1) % 3];
+        let b = triangle[(i + 2) % 3];
+        let (ax, ay) = ((a.x - p.x) as f64, (a.y - p.y) as f64);
+        let (bx, by) = ((b.x - p.x) as f64, (b.y - p.y) as f64);
+        let la = (ax * ax + ay * ay).sqrt();
+        let lb = (bx * bx + by * by).sqrt();
+        if la == 0.0 || lb == 0.0 {
+            return None;
+        }
+        // cos(angle) <= cos(120 degrees)
+        if (ax * bx + ay * by) / (la * lb) <= -0.5 {
+            return None;
+        }
+    }
+    geometric_median(triangle)
+}
+
+/// Find the point minimising the total distance to a set of points, rounded
+/// to integer coordinates, using Weiszfeld's algorithm.
+fn geometric_median(points: &[Vertex]) -> Option<Vertex> {
+    if points.is_empty() {
+        return None;
+    }
+    let k = points.len() as f64;
+    let mut x = points.iter().map(|p| p.x as f64).sum::<f64>() / k;
+    let mut y = points.iter().map(|p| p.y as f64).sum::<f64>() / k;
+    for _ in 0..MEDIAN_ITERATIONS {
+        let (mut sx, mut sy, mut sw) = (0.0, 0.0, 0.0);
+        for p in points {
+            let d =
+                ((p.x as f64 - x).powi(2) + (p.y as f64 - y).powi(2)).sqrt();
+            if d < 1e-9 {
+                // Sitting on a point, which is a good enough answer
+                return Some(*p);
+            }
+            sx += p.x as f64 / d;
+            sy += p.y as f64 / d;
+            sw += 1.0 / d;
+        }
+        x = sx / sw;
+        y = sy / sw;
+    }
+    Some(Vertex::new(x.round() as i32, y.round() as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, minimum_spanning_tree};
+
+    #[test]
+    fn test_fermat_point() {
+        // Equilateral-ish triangle, Fermat point is the centre
+        let t = [Vertex::new(0, 0), Vertex::new(100, 0), Vertex::new(50, 87)];
+        let f = fermat_point(&t).unwrap();
+        assert_eq!((f.x, f.y), (50, 29));
+        // Obtuse angle of more than 120 degrees
+        let t = [Vertex::new(0, 0), Vertex::new(100, 0), Vertex::new(50, 10)];
+        assert!(fermat_point(&t).is_none());
+    }
+
+    #[test]
+    fn test_steiner_tree_triangle() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(100, 0), Vertex::new(50, 87)];
+        let tree = steiner_tree(&points).unwrap();
+        assert_eq!(tree.steiner.len(), 1);
+        assert_eq!(tree.edges.len(), 3);
+        assert!(tree.len() < 175.0);
+    }
+
+    #[test]
+    fn test_steiner_tree_square() {
+        // The Steiner minimal tree of a unit square is 1 + sqrt(3)
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(100, 0),
+            Vertex::new(100, 100),
+            Vertex::new(0, 100),
+        ];
+        let tree = steiner_tree(&points).unwrap();
+        assert_eq!(tree.steiner.len(), 2);
+        assert_eq!(tree.edges.len(), points.len() + 1);
+        assert!(tree.len() < 275.0);
+        // The ends index the points followed by the Steiner points
+        let vertices: Vec<Vertex> =
+            points.iter().chain(&tree.steiner).copied().collect();
+        for (e, &(u, v)) in tree.edges.iter().zip(&tree.ends) {
+            assert_eq!((e.u.x, e.u.y), (vertices[u].x, vertices[u].y));
+            assert_eq!((e.v.x, e.v.y), (vertices[v].x, vertices[v].y));
+        }
+    }
+
+    #[test]
+    fn test_steiner_tree_random() {
+        let points = generate(50, 1.0, 0, 0, 400, 400).unwrap();
+        let mst = minimum_spanning_tree(&points).unwrap();
+        let mst_length: f32 = mst.iter().map(|e| e.len()).sum();
+        let tree = steiner_tree(&points).unwrap();
+        // A tree over the points and the Steiner points
+        assert_eq!(tree.edges.len(), points.len() + tree.steiner.len() - 1);
+        assert!(tree.len() <= mst_length);
+        // Never better than the 2/sqrt(3) bound allows
+        assert!(tree.len() >= mst_length * 3f32.sqrt() / 2.0 - 1e-3);
+    }
+
+    #[test]
+    fn test_steiner_tree_small() {
+        assert!(steiner_tree(&[]).unwrap().is_empty());
+        let tree = steiner_tree(&[Vertex::new(1, 1)]).unwrap();
+        assert!(tree.is_empty() && tree.steiner.is_empty());
+    }
+
+    #[test]
+    fn test_steiner_tree_collinear() {
+        let points: Vec<Vertex> =
+            (0..5).map(|i| Vertex::new(i * 10, 0)).collect();
+        let tree = steiner_tree(&points).unwrap();
+        assert!(tree.steiner.is_empty());
+        assert_eq!(tree.len(), 40.0);
+    }
+}
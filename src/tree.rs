@@ -0,0 +1,515 @@
+//! Tree
+//!
+//! A spanning tree of a set of points with its topology: vertices are
+//! indices into the points, each vertex has a list of its neighbours, and
+//! the tree is rooted so every vertex but the root has a parent.  Built once
+//! from the flat list of edges, so each analysis doesn't have to match
+//! coordinates and walk the tree again.
+//!
+//! The root starts at the first point and can be moved to any vertex.
+//! Traversals visit neighbours in the order of the edges.
+//!
+//! Edges name their end points by coordinates, so a tree built from edges
+//! can't tell repeated points apart.  Build the tree from vertex indices
+//! (`from_ends`, `from_points`) to keep every copy as its own vertex.
+
+use crate::{edge_indices, index_edges, minimum_spanning_tree_indices};
+use crate::{Edge, Vertex};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A spanning tree with vertex indices, adjacency lists and a root
+#[derive(Clone)]
+pub struct MstTree {
+    /// The tree vertices
+    points: Vec<Vertex>,
+    /// The tree edges
+    edges: Vec<Edge>,
+    /// Vertex indices of the ends of each edge
+    ends: Vec<(usize, usize)>,
+    /// Index of each vertex by its coordinates
+    index: HashMap<(i32, i32), usize>,
+    /// Neighbours of each vertex, and the index of the edge to each
+    adjacent: Vec<Vec<(usize, usize)>>,
+    /// The root vertex
+    root: usize,
+    /// Parent of each vertex, None for the root
+    parent: Vec<Option<usize>>,
+    /// Index of the edge to the parent of each vertex, None for the root
+    parent_edge: Vec<Option<usize>>,
+    /// Number of edges from the root to each vertex
+    depth: Vec<usize>,
+}
+
+impl MstTree {
+    /// Create a tree from a set of points and edges between them, rooted at
+    /// the first point.  Fails unless the edges form a spanning tree.  The
+    /// end points are matched by coordinates; where points are repeated
+    /// use `from_ends`.
+    pub fn new(
+        points: &[Vertex],
+        edges: &[Edge],
+    ) -> Result<Self, &'static str> {
+        if edges.len() + 1 != points.len().max(1) {
+            return Err("tree does not span the points");
+        }
+        let ends = edge_indices(points, edges)?;
+        Self::build(points, edges.to_vec(), ends)
+    }
+
+    /// Create a tree from a set of points and the indices of the end points
+    /// of each edge, rooted at the first point.  Fails unless the edges form
+    /// a spanning tree.
+    pub fn from_ends(
+        points: &[Vertex],
+        ends: &[(usize, usize)],
+    ) -> Result<Self, &'static str> {
+        let n = points.len();
+        if ends.len() + 1 != n.max(1) {
+            return Err("tree does not span the points");
+        }
+        if ends.iter().any(|&(u, v)| u >= n || v >= n) {
+            return Err("edge vertex not in the set of points");
+        }
+        Self::build(points, index_edges(points, ends), ends.to_vec())
+    }
+
+    /// Create a tree from its points, edges and their end points.
+    fn build(
+        points: &[Vertex],
+        edges: Vec<Edge>,
+        ends: Vec<(usize, usize)>,
+    ) -> Result<Self, &'static str> {
+        let n = points.len();
+        let mut adjacent = vec![Vec::new(); n];
+        for (k, &(u, v)) in ends.iter().enumerate() {
+            adjacent[u].push((v, k));
+            adjacent[v].push((u, k));
+        }
+        let mut index = HashMap::new();
+        for (i, p) in points.iter().enumerate() {
+            index.entry((p.x, p.y)).or_insert(i);
+        }
+        let mut tree = Self {
+            points: points.to_vec(),
+            edges,
+            ends,
+            index,
+            adjacent,
+            root: 0,
+            parent: vec![None; n],
+            parent_edge: vec![None; n],
+            depth: vec![0; n],
+        };
+        if n > 0 && tree.root_at(0) != n {
+            return Err("tree does not span the points");
+        }
+        Ok(tree)
+    }
+
+    /// Create the minimum spanning tree of a set of points, rooted at the
+    /// first point.  Repeated points are distinct vertices.
+    pub fn from_points(points: &[Vertex]) -> Result<Self, &'static str> {
+        Self::from_ends(points, &minimum_spanning_tree_indices(points))
+    }
+
+    /// Create a tree from its edges alone, numbering the vertices in the
+    /// order they first appear.  Each distinct coordinate is one vertex, so
+    /// repeated points can't be represented; an edge between copies of a
+    /// point is an error.  (The edge readers skip such edges, so a tree of
+    /// repeated points read from a file has one vertex for all the copies.)
+    pub fn from_edges(edges: &[Edge]) -> Result<Self, &'static str> {
+        let mut seen = HashSet::new();
+        let mut points = Vec::new();
+        for e in edges {
+            if (e.u.x, e.u.y) == (e.v.x, e.v.y) {
+                return Err("edge between copies of a point");
+            }
+            for v in [e.u, e.v] {
+                if seen.insert((v.x, v.y)) {
+                    points.push(v);
+                }
+            }
+        }
+        Self::new(&points, edges)
+    }
+
+    /// Find the index of a vertex by its coordinates, the first vertex with
+    /// them where points are repeated.
+    pub fn find(&self, v: &Vertex) -> Option<usize> {
+        self.index.get(&(v.x, v.y)).copied()
+    }
+
+    /// Move the root to a vertex, and find the parent and depth of every
+    /// vertex.  Returns the number of vertices reached, all of them for a
+    /// tree.
+    pub fn root_at(&mut self, root: usize) -> usize {
+        assert!(root < self.points.len());
+        self.root = root;
+        self.parent[root] = None;
+        self.parent_edge[root] = None;
+        self.depth[root] = 0;
+        let mut visited = vec![false; self.points.len()];
+        visited[root] = true;
+        let mut reached = 1;
+        let mut stack = vec![root];
+        while let Some(i) = stack.pop() {
+            for &(j, k) in &self.adjacent[i] {
+                if !visited[j] {
+                    visited[j] = true;
+                    self.parent[j] = Some(i);
+                    self.parent_edge[j] = Some(k);
+                    self.depth[j] = self.depth[i] + 1;
+                    reached += 1;
+                    stack.push(j);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Get the total length of the tree.
+    pub fn len(&self) -> f32 {
+        self.edges.iter().map(|e| e.len()).sum()
+    }
+
+    /// Check if the tree has no edges.
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Number of vertices in the tree.
+    pub fn num_vertices(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The tree vertices.
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    /// The tree edges, in the order given.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Vertex indices of the ends of each edge.
+    pub fn ends(&self) -> &[(usize, usize)] {
+        &self.ends
+    }
+
+    /// Neighbours of a vertex with the index of the edge to each.
+    pub fn adjacent(&self, v: usize) -> &[(usize, usize)] {
+        &self.adjacent[v]
+    }
+
+    /// Iterate over the neighbours of a vertex.
+    pub fn neighbours(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.adjacent[v].iter().map(|&(w, _)| w)
+    }
+
+    /// Number of edges at a vertex.
+    pub fn degree(&self, v: usize) -> usize {
+        self.adjacent[v].len()
+    }
+
+    /// The root vertex.
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Parent of a vertex, None for the root.
+    pub fn parent(&self, v: usize) -> Option<usize> {
+        self.parent[v]
+    }
+
+    /// Parent of every vertex, None for the root.
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parent
+    }
+
+    /// Index of the edge from a vertex to its parent, None for the root.
+    pub fn parent_edge(&self, v: usize) -> Option<usize> {
+        self.parent_edge[v]
+    }
+
+    /// Number of edges from the root to a vertex.
+    pub fn depth(&self, v: usize) -> usize {
+        self.depth[v]
+    }
+
+    /// Depth of every vertex.
+    pub fn depths(&self) -> &[usize] {
+        &self.depth
+    }
+
+    /// Children of a vertex, its neighbours but the parent.
+    pub fn children(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let parent = self.parent[v];
+        self.neighbours(v).filter(move |&w| Some(w) != parent)
+    }
+
+    /// Iterate over the vertices depth first (preorder) from the root.
+    pub fn dfs(&self) -> Dfs<'_> {
+        Dfs {
+            tree: self,
+            stack: self.start(),
+        }
+    }
+
+    /// Iterate over the vertices breadth first from the root.
+    pub fn bfs(&self) -> Bfs<'_> {
+        Bfs {
+            tree: self,
+            queue: self.start().into(),
+        }
+    }
+
+    /// Number of vertices in the subtree below each vertex, including it.
+    pub fn subtree_sizes(&self) -> Vec<usize> {
+        let mut size = vec![1; self.points.len()];
+        let order: Vec<usize> = self.bfs().collect();
+        for &v in order.iter().rev() {
+            if let Some(p) = self.parent[v] {
+                size[p] += size[v];
+            }
+        }
+        size
+    }
+
+    /// Find the lowest common ancestor of two vertices.
+    pub fn lca(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = (a, b);
+        while self.depth[a] > self.depth[b] {
+            a = self.parent[a].unwrap();
+        }
+        while self.depth[b] > self.depth[a] {
+            b = self.parent[b].unwrap();
+        }
+        while a != b {
+            a = self.parent[a].unwrap();
+            b = self.parent[b].unwrap();
+        }
+        a
+    }
+
+    /// List the vertices on the tree path from a to b, including both.
+    pub fn path(&self, a: usize, b: usize) -> Vec<usize> {
+        let top = self.lca(a, b);
+        let mut path = self.climb(a, top);
+        path.push(top);
+        let mut down = self.climb(b, top);
+        down.reverse();
+        path.extend(down);
+        path
+    }
+
+    /// List the indices of the edges on the tree path from a to b, in order.
+    pub fn path_edges(&self, a: usize, b: usize) -> Vec<usize> {
+        let path = self.path(a, b);
+        path.windows(2)
+            .map(|w| {
+                if self.parent[w[0]] == Some(w[1]) {
+                    self.parent_edge[w[0]].unwrap()
+                } else {
+                    self.parent_edge[w[1]].unwrap()
+                }
+            })
+            .collect()
+    }
+
+    /// List the vertices from v up to, but not including, an ancestor.
+    fn climb(&self, v: usize, ancestor: usize) -> Vec<usize> {
+        let mut vertices = Vec::new();
+        let mut v = v;
+        while v != ancestor {
+            vertices.push(v);
+            v = self.parent[v].unwrap();
+        }
+        vertices
+    }
+
+    /// The root, if there are any vertices.
+    fn start(&self) -> Vec<usize> {
+        if self.points.is_empty() {
+            Vec::new()
+        } else {
+            vec![self.root]
+        }
+    }
+}
+
+/// Depth first (preorder) traversal of a tree
+pub struct Dfs<'a> {
+    tree: &'a MstTree,
+    stack: Vec<usize>,
+}
+
+impl Iterator for Dfs<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let v = self.stack.pop()?;
+        // Push in reverse so children are visited in edge order
+        let children: Vec<usize> = self.tree.children(v).collect();
+        self.stack.extend(children.into_iter().rev());
+        Some(v)
+    }
+}
+
+/// Breadth first traversal of a tree
+pub struct Bfs<'a> {
+    tree: &'a MstTree,
+    queue: VecDeque<usize>,
+}
+
+impl Iterator for Bfs<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let v = self.queue.pop_front()?;
+        self.queue.extend(self.tree.children(v));
+        Some(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small tree
+    ///
+    ///   0 - 1 - 2
+    ///       |
+    ///       3 - 4
+    fn small() -> MstTree {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(20, 0),
+            Vertex::new(10, 10),
+            Vertex::new(20, 10),
+        ];
+        let edges = vec![
+            Edge::new(0, 0, 10, 0),
+            Edge::new(10, 0, 20, 0),
+            Edge::new(10, 0, 10, 10),
+            Edge::new(10, 10, 20, 10),
+        ];
+        MstTree::new(&points, &edges).unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        let tree = small();
+        assert_eq!(tree.num_vertices(), 5);
+        assert_eq!(tree.len(), 40.0);
+        assert_eq!(tree.ends()[2], (1, 3));
+        assert_eq!(tree.degree(1), 3);
+        assert_eq!(tree.neighbours(1).collect::<Vec<_>>(), vec![0, 2, 3]);
+        assert_eq!(tree.parents(), &[None, Some(0), Some(1), Some(1), Some(3)]);
+        assert_eq!(tree.depths(), &[0, 1, 2, 2, 3]);
+        assert_eq!(tree.parent_edge(4), Some(3));
+
+        let points = tree.points().to_vec();
+        assert!(MstTree::new(&points, &tree.edges()[1..]).is_err());
+        let cycle = vec![
+            Edge::new(0, 0, 10, 0),
+            Edge::new(10, 0, 20, 0),
+            Edge::new(20, 0, 0, 0),
+            Edge::new(10, 10, 20, 10),
+        ];
+        assert!(MstTree::new(&points, &cycle).is_err());
+        assert!(MstTree::new(&[], &[]).unwrap().dfs().next().is_none());
+    }
+
+    #[test]
+    fn test_root_at() {
+        let mut tree = small();
+        assert_eq!(tree.root_at(4), 5);
+        assert_eq!(tree.root(), 4);
+        assert_eq!(tree.parent(4), None);
+        assert_eq!(tree.parent(1), Some(3));
+        assert_eq!(tree.depth(0), 3);
+        assert_eq!(tree.children(1).collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_traversal() {
+        let mut tree = small();
+        assert_eq!(tree.dfs().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.bfs().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        tree.root_at(3);
+        assert_eq!(tree.dfs().collect::<Vec<_>>(), vec![3, 1, 0, 2, 4]);
+        assert_eq!(tree.bfs().collect::<Vec<_>>(), vec![3, 1, 4, 0, 2]);
+    }
+
+    #[test]
+    fn test_subtree_sizes() {
+        let mut tree = small();
+        assert_eq!(tree.subtree_sizes(), vec![5, 4, 1, 2, 1]);
+        tree.root_at(2);
+        assert_eq!(tree.subtree_sizes(), vec![1, 4, 5, 2, 1]);
+    }
+
+    #[test]
+    fn test_path() {
+        let tree = small();
+        assert_eq!(tree.lca(2, 4), 1);
+        assert_eq!(tree.path(2, 4), vec![2, 1, 3, 4]);
+        assert_eq!(tree.path(4, 0), vec![4, 3, 1, 0]);
+        assert_eq!(tree.path(3, 3), vec![3]);
+        assert_eq!(tree.path_edges(2, 4), vec![1, 2, 3]);
+        assert!(tree.path_edges(0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_from_edges() {
+        let tree = small();
+        let rebuilt = MstTree::from_edges(tree.edges()).unwrap();
+        assert_eq!(rebuilt.num_vertices(), 5);
+        assert_eq!(rebuilt.find(&Vertex::new(10, 10)), Some(3));
+        assert_eq!(rebuilt.find(&Vertex::new(1, 1)), None);
+        assert_eq!(rebuilt.depths(), tree.depths());
+    }
+
+    #[test]
+    fn test_from_points() {
+        let points = crate::generate(30, 1.0, 0, 0, 100, 100).unwrap();
+        let tree = MstTree::from_points(&points).unwrap();
+        let mst: f32 = crate::minimum_spanning_tree(&points)
+            .unwrap()
+            .iter()
+            .map(|e| e.len())
+            .sum();
+        assert!((tree.len() - mst).abs() < 1e-3);
+        assert_eq!(tree.dfs().count(), points.len());
+        assert_eq!(tree.subtree_sizes()[0], points.len());
+    }
+
+    #[test]
+    fn test_repeated_points() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(0, 0),
+            Vertex::new(20, 0),
+            Vertex::new(10, 0),
+            Vertex::new(30, 5),
+        ];
+        let tree = MstTree::from_points(&points).unwrap();
+        assert_eq!(tree.num_vertices(), 6);
+        assert_eq!(tree.edges().len(), 5);
+        assert_eq!(tree.dfs().count(), 6);
+        assert_eq!(tree.degree(2), 1);
+        assert_eq!(tree.degree(4), 1);
+        assert_eq!(tree.find(&Vertex::new(10, 0)), Some(1));
+
+        let again = MstTree::from_ends(&points, tree.ends()).unwrap();
+        assert_eq!(again.ends(), tree.ends());
+        assert!(MstTree::from_ends(&points, &tree.ends()[1..]).is_err());
+        let mut ends = tree.ends().to_vec();
+        ends[0].0 = 6;
+        assert!(MstTree::from_ends(&points, &ends).is_err());
+        assert!(MstTree::from_edges(tree.edges()).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! Travelling Salesman
+//!
+//! Approximate travelling salesman tours derived from the minimum spanning
+//! tree.  A preorder walk of the tree, skipping vertices already visited, is
+//! at most twice the length of the optimal tour.  Christofides' algorithm
+//! adds a minimum weight perfect matching of the odd degree vertices of the
+//! tree and walks the resulting Eulerian multigraph, which is at most 1.5
+//! times optimal.  Either tour can be shortened with 2-opt (reverse a section
+//! of the tour) and Or-opt (move a short section elsewhere) improvements.
+//!
+//! Tours are lists of vertex indices into the set of points, visiting each
+//! point once.  The tour is closed: it returns from the last vertex to the
+//! first.
+
+use crate::tree::MstTree;
+use crate::{Edge, Vertex};
+
+/// Longest section of the tour moved by Or-opt
+const OR_OPT_SEGMENT: usize = 3;
+
+/// Most odd degree vertices matched exactly by Christofides.  The exact
+/// matching takes time and memory exponential in the number of vertices.
+const EXACT_MATCHING: usize = 20;
+
+/// Smallest change in length counted as an improvement, so rounding can't
+/// make the searches cycle.
+const IMPROVEMENT: f32 = 1e-4;
+
+/// Find a tour of the points by walking their minimum spanning tree in
+/// preorder, starting from the first point.
+pub fn preorder_tour(
+    points: &[Vertex],
+    tree: &[Edge],
+) -> Result<Vec<usize>, &'static str> {
+    Ok(MstTree::new(points, tree)?.dfs().collect())
+}
+
+/// Find a tour of the points with Christofides' algorithm, starting from
+/// the first point.
+///
+/// The odd degree vertices of the tree are paired by a minimum weight
+/// perfect matching, and the tree and matching edges together have an
+/// Eulerian circuit, which is shortcut past repeated vertices.  The tour is
+/// at most 1.5 times optimal when there are up to 20 odd degree vertices and
+/// the matching is exact.  Larger sets are matched greedily then improved by
+/// swapping partners, which keeps the algorithm fast but loses the bound.
+pub fn christofides(
+    points: &[Vertex],
+    tree: &[Edge],
+) -> Result<Vec<usize>, &'static str> {
+    let tree = MstTree::new(points, tree)?;
+    if points.is_empty() {
+        return Ok(Vec::new());
+    }
+    let odd: Vec<usize> = (0..points.len())
+        .filter(|&i| tree.degree(i) % 2 == 1)
+        .collect();
+    let mut edges = tree.ends().to_vec();
+    edges.extend(perfect_matching(points, &odd));
+
+    let mut visited = vec![false; points.len()];
+    let mut tour = Vec::with_capacity(points.len());
+    for i in euler_circuit(points.len(), &edges) {
+        if !visited[i] {
+            visited[i] = true;
+            tour.push(i);
+        }
+    }
+    Ok(tour)
+}
+
+/// Find the length of a closed tour.
+pub fn tour_length(points: &[Vertex], tour: &[usize]) -> f32 {
+    let n = tour.len();
+    (0..n)
+        .map(|i| points[tour[i]].distance(&points[tour[(i + 1) % n]]))
+        .sum()
+}
+
+/// Shorten a tour with 2-opt and Or-opt moves until neither finds an
+/// improvement.
+pub fn improve(points: &[Vertex], tour: &mut [usize]) {
+    while two_opt(points, tour) | or_opt(points, tour) {}
+}
+
+/// Shorten a tour by reversing sections of it wherever that removes a
+/// crossing or otherwise makes it shorter.  Repeats until no reversal helps.
+/// Returns true if the tour changed.
+pub fn two_opt(points: &[Vertex], tour: &mut [usize]) -> bool {
+    let n = tour.len();
+    if n < 4 {
+        return false;
+    }
+    let d = |a: usize, b: usize| points[a].distance(&points[b]);
+    let mut changed = false;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..n - 1 {
+            for j in i + 2..n {
+                // Edges (a, b) and (c, e), skipping the pair sharing a vertex
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                let (a, b) = (tour[i], tour[i + 1]);
+                let (c, e) = (tour[j], tour[(j + 1) % n]);
+                let delta = d(a, c) + d(b, e) - d(a, b) - d(c, e);
+                if delta < -IMPROVEMENT {
+                    tour[i + 1..=j].reverse();
+                    improved = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// Shorten a tour by moving sections of up to three vertices, forwards or
+/// reversed, to between two other vertices.  Repeats until no move helps.
+/// Returns true if the tour changed.
+pub fn or_opt(points: &[Vertex], tour: &mut [usize]) -> bool {
+    let n = tour.len();
+    if n < 5 {
+        return false;
+    }
+    let d = |a: usize, b: usize| points[a].distance(&points[b]);
+    let mut changed = false;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        'search: for len in 1..=OR_OPT_SEGMENT.min(n - 3) {
+            for start in 0..n {
+                // The section is tour[start..start + len], wrapping around
+                let at = |k: usize| tour[(start + k) % n];
+                let prev = tour[(start + n - 1) % n];
+                let next = at(len);
+                let (first, last) = (at(0), at(len - 1));
+                let removed = d(prev, first) + d(last, next) - d(prev, next);
+                // Try each remaining edge (p, q) outside the section
+                for k in len..n - 1 {
+                    let (p, q) = (at(k), at(k + 1));
+                    let forward = d(p, first) + d(last, q) - d(p, q);
+                    let reversed = d(p, last) + d(first, q) - d(p, q);
+                    if forward.min(reversed) < removed - IMPROVEMENT {
+                        move_section(tour, start, len, k, reversed < forward);
+                        improved = true;
+                        changed = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// Pair up an even number of vertices, minimising the total distance
+/// between partners.  Exact for small sets, see `christofides`.
+fn perfect_matching(
+    points: &[Vertex],
+    vertices: &[usize],
+) -> Vec<(usize, usize)> {
+    let k = vertices.len();
+    let d =
+        |a: usize, b: usize| points[vertices[a]].distance(&points[vertices[b]]);
+    let mut pairs = Vec::with_capacity(k / 2);
+    if k <= EXACT_MATCHING {
+        // cost[m] is the cheapest matching of the vertices in bit set m.
+        // The lowest vertex in m is paired first, so each set is built once.
+        let full = (1usize << k) - 1;
+        let mut cost = vec![f32::INFINITY; full + 1];
+        let mut partner = vec![0; full + 1];
+        cost[0] = 0.0;
+        for m in 1..=full {
+            if m.count_ones() % 2 == 1 {
+                continue;
+            }
+            let a = m.trailing_zeros() as usize;
+            for b in a + 1..k {
+                if m & (1 << b) != 0 {
+                    let c = cost[m & !(1 << a) & !(1 << b)] + d(a, b);
+                    if c < cost[m] {
+                        cost[m] = c;
+                        partner[m] = b;
+                    }
+                }
+            }
+        }
+        let mut m = full;
+        while m != 0 {
+            let a = m.trailing_zeros() as usize;
+            let b = partner[m];
+            pairs.push((vertices[a], vertices[b]));
+            m &= !(1 << a) & !(1 << b);
+        }
+        return pairs;
+    }
+
+    // Greedy: pair the closest remaining vertices
+    let mut candidates = Vec::with_capacity(k * (k - 1) / 2);
+    for a in 0..k {
+        for b in a + 1..k {
+            candidates.push((d(a, b), a, b));
+        }
+    }
+    candidates.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let mut matched = vec![false; k];
+    let mut local = Vec::with_capacity(k / 2);
+    for (_, a, b) in candidates {
+        if !matched[a] && !matched[b] {
+            matched[a] = true;
+            matched[b] = true;
+            local.push((a, b));
+        }
+    }
+    // Swap partners between two pairs while that shortens the matching
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..local.len() {
+            for j in i + 1..local.len() {
+                let ((a, b), (c, e)) = (local[i], local[j]);
+                let current = d(a, b) + d(c, e);
+                if d(a, c) + d(b, e) < current - IMPROVEMENT {
+                    local[i] = (a, c);
+                    local[j] = (b, e);
+                    improved = true;
+                } else if d(a, e) + d(b, c) < current - IMPROVEMENT {
+                    local[i] = (a, e);
+                    local[j] = (b, c);
+                    improved = true;
+                }
+            }
+        }
+    }
+    pairs.extend(local.into_iter().map(|(a, b)| (vertices[a], vertices[b])));
+    pairs
+}
+
+/// Find an Eulerian circuit of a connected multigraph whose vertices all
+/// have even degree, starting from vertex 0 (Hierholzer's algorithm).
+fn euler_circuit(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut adjacent = vec![Vec::new(); n];
+    for (i, &(u, v)) in edges.iter().enumerate() {
+        adjacent[u].push((v, i));
+        adjacent[v].push((u, i));
+    }
+    let mut used = vec![false; edges.len()];
+    let mut circuit = Vec::with_capacity(edges.len() + 1);
+    let mut stack = vec![0];
+    while let Some(&u) = stack.last() {
+        // Drop edges already walked from the other end
+        while adjacent[u].last().is_some_and(|&(_, i)| used[i]) {
+            adjacent[u].pop();
+        }
+        match adjacent[u].pop() {
+            Some((v, i)) => {
+                used[i] = true;
+                stack.push(v);
+            }
+            None => {
+                circuit.push(u);
+                stack.pop();
+            }
+        }
+    }
+    circuit.reverse();
+    circuit
+}
+
+/// Move the section of len vertices at start to after the vertex k places
+/// from start, optionally reversing it.
+fn move_section(
+    tour: &mut [usize],
+    start: usize,
+    len: usize,
+    k: usize,
+    reverse: bool,
+) {
+    let n = tour.len();
+    // Rotate a copy so the section is at the front
+    let rotated: Vec<usize> = (0..n).map(|i| tour[(start + i) % n]).collect();
+    let mut section = rotated[..len].to_vec();
+    if reverse {
+        section.reverse();
+    }
+    let mut result = Vec::with_capacity(n);
+    result.extend_from_slice(&rotated[len..=k]);
+    result.extend_from_slice(&section);
+    result.extend_from_slice(&rotated[k + 1..]);
+    tour.copy_from_slice(&result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, minimum_spanning_tree};
+
+    /// Check a tour visits every point exactly once.
+    fn is_tour(tour: &[usize], n: usize) -> bool {
+        let mut seen = vec![false; n];
+        for &i in tour {
+            if i >= n || seen[i] {
+                return false;
+            }
+            seen[i] = true;
+        }
+        tour.len() == n
+    }
+
+    #[test]
+    fn test_preorder_tour() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(0, 10),
+            Vertex::new(10, 10),
+        ];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let tour = preorder_tour(&points, &tree).unwrap();
+        assert!(is_tour(&tour, 4));
+        assert_eq!(tour[0], 0);
+        assert_eq!(tour_length(&points, &tour), 40.0);
+    }
+
+    #[test]
+    fn test_preorder_tour_bound() {
+        let points = generate(60, 1.0, 0, 0, 200, 200).unwrap();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let tour = preorder_tour(&points, &tree).unwrap();
+        assert!(is_tour(&tour, points.len()));
+        let mst: f32 = tree.iter().map(|e| e.len()).sum();
+        assert!(tour_length(&points, &tour) <= 2.0 * mst + 1e-3);
+    }
+
+    #[test]
+    fn test_preorder_tour_errors() {
+        let points = vec![Vertex::new(0, 0), Vertex::new(1, 0)];
+        assert!(preorder_tour(&points, &[]).is_err());
+        assert_eq!(preorder_tour(&[], &[]), Ok(vec![]));
+    }
+
+    /// Find the shortest tour by trying every order of the points.
+    fn brute_force(points: &[Vertex]) -> f32 {
+        fn search(
+            points: &[Vertex],
+            tour: &mut Vec<usize>,
+            used: &mut [bool],
+            best: &mut f32,
+        ) {
+            if tour.len() == points.len() {
+                *best = best.min(tour_length(points, tour));
+                return;
+            }
+            for i in 1..points.len() {
+                if !used[i] {
+                    used[i] = true;
+                    tour.push(i);
+                    search(points, tour, used, best);
+                    tour.pop();
+                    used[i] = false;
+                }
+            }
+        }
+        let mut best = f32::INFINITY;
+        let mut used = vec![false; points.len()];
+        used[0] = true;
+        search(points, &mut vec![0], &mut used, &mut best);
+        best
+    }
+
+    #[test]
+    fn test_christofides() {
+        for _ in 0..5 {
+            let points = generate(8, 1.0, 0, 0, 100, 100).unwrap();
+            let tree = minimum_spanning_tree(&points).unwrap();
+            let tour = christofides(&points, &tree).unwrap();
+            assert!(is_tour(&tour, points.len()));
+            assert_eq!(tour[0], 0);
+            let optimal = brute_force(&points);
+            assert!(tour_length(&points, &tour) <= 1.5 * optimal + 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_christofides_large() {
+        // Too many odd vertices for the exact matching.  A 12 x 13 grid
+        // has a shortest tour of one spacing per point, and the tree and
+        // the greedy matching are fixed by the order of the points.
+        let points: Vec<Vertex> = (0..12)
+            .flat_map(|i| (0..13).map(move |j| Vertex::new(i * 10, j * 10)))
+            .collect();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let tour = christofides(&points, &tree).unwrap();
+        assert!(is_tour(&tour, points.len()));
+        let optimal = 10.0 * points.len() as f32;
+        assert!(tour_length(&points, &tour) <= 1.5 * optimal);
+    }
+
+    #[test]
+    fn test_perfect_matching() {
+        // Two close pairs far apart, listed so neighbours don't pair
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(50, 0),
+            Vertex::new(1, 0),
+            Vertex::new(51, 0),
+        ];
+        let mut pairs = perfect_matching(&points, &[0, 1, 2, 3]);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 2), (1, 3)]);
+        assert!(perfect_matching(&points, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_euler_circuit() {
+        // Two triangles sharing vertex 0
+        let edges = vec![(0, 1), (1, 2), (2, 0), (0, 3), (3, 4), (4, 0)];
+        let circuit = euler_circuit(5, &edges);
+        assert_eq!(circuit.len(), edges.len() + 1);
+        assert_eq!(circuit[0], 0);
+        assert_eq!(circuit[6], 0);
+    }
+
+    #[test]
+    fn test_two_opt() {
+        // A square visited in a crossing order
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 10),
+            Vertex::new(10, 0),
+            Vertex::new(0, 10),
+        ];
+        let mut tour = vec![0, 1, 2, 3];
+        assert!(two_opt(&points, &mut tour));
+        assert!(is_tour(&tour, 4));
+        assert_eq!(tour_length(&points, &tour), 40.0);
+        assert!(!two_opt(&points, &mut tour));
+    }
+
+    #[test]
+    fn test_or_opt() {
+        // Points on a line visited out of order
+        let points: Vec<Vertex> =
+            (0..6).map(|i| Vertex::new(i * 10, 0)).collect();
+        let mut tour = vec![0, 3, 1, 2, 4, 5];
+        let before = tour_length(&points, &tour);
+        assert!(or_opt(&points, &mut tour));
+        assert!(is_tour(&tour, 6));
+        assert!(tour_length(&points, &tour) < before);
+        assert_eq!(tour_length(&points, &tour), 100.0);
+    }
+
+    #[test]
+    fn test_improve() {
+        let points = generate(60, 1.0, 0, 0, 200, 200).unwrap();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        let mut tour = preorder_tour(&points, &tree).unwrap();
+        let before = tour_length(&points, &tour);
+        improve(&points, &mut tour);
+        assert!(is_tour(&tour, points.len()));
+        assert!(tour_length(&points, &tour) <= before);
+    }
+}
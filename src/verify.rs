@@ -0,0 +1,244 @@
+//! Verify
+//!
+//! Check that a set of edges supplied by another tool is a minimum spanning
+//! tree of a set of points.  The edges must join points of the set, form no
+//! cycle and reach every point, and by the cycle property no edge outside
+//! the tree may be shorter than the longest tree edge on the path between
+//! its ends.
+//!
+//! Edges name their end points by coordinates, so repeated points are one
+//! vertex: the copies of a point are joined by edges of length zero in any
+//! minimum spanning tree, which the tree may give or leave out.
+//!
+//! It is enough to check the cycle property for the edges of one minimum
+//! spanning tree: a tree no longer than each of those edges on its path is
+//! no longer than that tree.  So a minimum spanning tree is found with
+//! Prim's algorithm, in O(n^2) time and O(n) memory, and the longest tree
+//! edge on the path between the ends of each of its edges with binary
+//! lifting (see `bottleneck`) in O(log n).  The check takes quadratic time,
+//! like finding the tree.
+
+use crate::bottleneck::Bottleneck;
+use crate::disjoint_set::DisjointSet;
+use crate::tree::MstTree;
+use crate::{prim, Edge, Vertex};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Shortest difference in length counted as a violation, so rounding in
+/// the supplied edges doesn't fail a correct tree.
+const TOLERANCE: f32 = 1e-3;
+
+/// The first way a set of edges fails to be a minimum spanning tree
+#[derive(Clone, Copy)]
+pub enum Violation {
+    /// The edge has an end that is not one of the points
+    UnknownVertex(Edge),
+    /// The edge closes a cycle with the edges before it
+    Cycle(Edge),
+    /// The point is not reached from the first point
+    Disconnected(Vertex),
+    /// The shorter edge, not in the tree, joins the two sides of the tree
+    /// edge
+    NotMinimal { tree: Edge, shorter: Edge },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::UnknownVertex(e) => {
+                write!(f, "edge {} has a vertex not in the points", show(e))
+            }
+            Violation::Cycle(e) => write!(f, "edge {} closes a cycle", show(e)),
+            Violation::Disconnected(p) => {
+                write!(f, "point ({}, {}) is not connected", p.x, p.y)
+            }
+            Violation::NotMinimal { tree, shorter } => write!(
+                f,
+                "tree edge {} (length {}) is longer than edge {} (length {})",
+                show(tree),
+                tree.len(),
+                show(shorter),
+                shorter.len()
+            ),
+        }
+    }
+}
+
+/// Format an edge by its end points.
+fn show(e: &Edge) -> String {
+    format!("({}, {}) - ({}, {})", e.u.x, e.u.y, e.v.x, e.v.y)
+}
+
+/// Check that the edges form a minimum spanning tree of the completely
+/// connected set of points, with straight line lengths.  Repeated points
+/// are one vertex, and edges between copies of a point are ignored.
+/// Returns the first violation found: unknown vertices and cycles in the
+/// order of the edges, then the first unreached point, then an edge of a
+/// minimum spanning tree shorter than the tree path between its ends.
+pub fn verify(points: &[Vertex], edges: &[Edge]) -> Result<(), Violation> {
+    // Number the distinct points
+    let mut index: HashMap<(i32, i32), usize> = HashMap::new();
+    let mut vertices = Vec::new();
+    for p in points {
+        index.entry((p.x, p.y)).or_insert_with(|| {
+            vertices.push(*p);
+            vertices.len() - 1
+        });
+    }
+    let mut tree_edges = Vec::with_capacity(edges.len());
+    let mut ends = Vec::with_capacity(edges.len());
+    for e in edges {
+        match (index.get(&(e.u.x, e.u.y)), index.get(&(e.v.x, e.v.y))) {
+            (Some(&u), Some(&v)) if u == v => {}
+            (Some(&u), Some(&v)) => {
+                tree_edges.push(*e);
+                ends.push((u, v));
+            }
+            _ => return Err(Violation::UnknownVertex(*e)),
+        }
+    }
+    verify_indices(&vertices, &tree_edges, &ends)
+}
+
+/// Check that edges, given with the indices of their end points, form a
+/// minimum spanning tree of distinct points.
+fn verify_indices(
+    points: &[Vertex],
+    edges: &[Edge],
+    ends: &[(usize, usize)],
+) -> Result<(), Violation> {
+    let n = points.len();
+
+    // Spanning and acyclic
+    let mut set = DisjointSet::new(n);
+    for (e, &(u, v)) in edges.iter().zip(ends) {
+        if !set.union(u, v) {
+            return Err(Violation::Cycle(*e));
+        }
+    }
+    if let Some(i) = (1..n).find(|&i| set.find(i) != set.find(0)) {
+        return Err(Violation::Disconnected(points[i]));
+    }
+    if n < 3 {
+        return Ok(());
+    }
+
+    // A tree now, measured with straight line lengths
+    let tree = match MstTree::from_ends(points, ends) {
+        Ok(tree) => tree,
+        Err(_) => return Err(Violation::Disconnected(points[0])),
+    };
+    let query = Bottleneck::new(&tree);
+
+    // Cycle property for the edges of a minimum spanning tree
+    for (a, b) in prim(n, |u, v| points[u].distance(&points[v])) {
+        let k = match query.bottleneck(a, b) {
+            Some(k) => k,
+            None => continue,
+        };
+        let d = points[a].distance(&points[b]);
+        if d < tree.edges()[k].len() - TOLERANCE {
+            return Err(Violation::NotMinimal {
+                tree: edges[k],
+                shorter: Edge {
+                    u: points[a.min(b)],
+                    v: points[a.max(b)],
+                    length: d,
+                },
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, minimum_spanning_tree};
+
+    #[test]
+    fn test_verify_mst() {
+        let points = generate(60, 1.0, 0, 0, 200, 200).unwrap();
+        let tree = minimum_spanning_tree(&points).unwrap();
+        assert!(verify(&points, &tree).is_ok());
+        assert!(verify(&[], &[]).is_ok());
+        assert!(verify(&points[..1], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_verify_repeated_points() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(0, 0),
+            Vertex::new(20, 0),
+            Vertex::new(10, 0),
+            Vertex::new(30, 5),
+        ];
+        let tree = minimum_spanning_tree(&points).unwrap();
+        assert!(verify(&points, &tree).is_ok());
+        // As read back from a file, without the edges between copies
+        let read: Vec<Edge> =
+            tree.iter().filter(|e| e.len() > 0.0).copied().collect();
+        assert_eq!(read.len(), 3);
+        assert!(verify(&points, &read).is_ok());
+        assert!(matches!(
+            verify(&points, &read[1..]),
+            Err(Violation::Disconnected(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_not_minimal() {
+        let points = vec![
+            Vertex::new(0, 0),
+            Vertex::new(10, 0),
+            Vertex::new(20, 0),
+            Vertex::new(30, 0),
+        ];
+        // A path that jumps over the middle
+        let edges = vec![
+            Edge::new(0, 0, 10, 0),
+            Edge::new(10, 0, 30, 0),
+            Edge::new(30, 0, 20, 0),
+        ];
+        match verify(&points, &edges) {
+            Err(Violation::NotMinimal { tree, shorter }) => {
+                assert_eq!(tree.len(), 20.0);
+                assert_eq!((shorter.u.x, shorter.v.x), (10, 20));
+            }
+            _ => panic!("expected a shorter edge"),
+        }
+    }
+
+    #[test]
+    fn test_verify_structure() {
+        let points =
+            vec![Vertex::new(0, 0), Vertex::new(3, 4), Vertex::new(6, 8)];
+        let stranger = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, 9, 9)];
+        assert!(matches!(
+            verify(&points, &stranger),
+            Err(Violation::UnknownVertex(_))
+        ));
+        let cycle = vec![
+            Edge::new(0, 0, 3, 4),
+            Edge::new(3, 4, 6, 8),
+            Edge::new(6, 8, 0, 0),
+        ];
+        assert!(
+            matches!(verify(&points, &cycle), Err(Violation::Cycle(e)) if e.u.x == 6)
+        );
+        let short = vec![Edge::new(0, 0, 3, 4)];
+        match verify(&points, &short) {
+            Err(Violation::Disconnected(p)) => assert_eq!(p.x, 6),
+            _ => panic!("expected a disconnected point"),
+        }
+    }
+
+    #[test]
+    fn test_violation_display() {
+        let v = Violation::Cycle(Edge::new(1, 2, 3, 4));
+        assert_eq!(v.to_string(), "edge (1, 2) - (3, 4) closes a cycle");
+    }
+}
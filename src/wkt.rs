@@ -0,0 +1,393 @@
+//! WKT Files
+//!
+//! Read and write points and trees as Well-Known Text for PostGIS and other
+//! GIS tools.  Points are written as one `MULTIPOINT` and a tree as one
+//! `MULTILINESTRING` of its edges, either of which `ST_GeomFromText` reads.
+//!
+//! Read text holds any number of geometries, one after another, as
+//! `ST_AsText` or `ST_AsEWKT` writes them, an `SRID=..;` prefix and `Z` or
+//! `M` coordinates allowed.  Points are read from `POINT`s and `MULTIPOINT`s
+//! and edges from `LINESTRING`s and `MULTILINESTRING`s, an edge between
+//! each pair of consecutive positions.  Obstacles are read from the outer
+//! rings of `POLYGON`s and `MULTIPOLYGON`s, holes ignored.  Other
+//! geometries are ignored, `GEOMETRYCOLLECTION`s are read for their parts,
+//! and coordinates are rounded to the nearest integer.  Brackets and
+//! collections nested more than `MAX_DEPTH` deep are invalid.
+
+use crate::obstacles::Polygon;
+use crate::{pair_edges, Edge, Vertex};
+use std::io::{self, Read, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Write points as a WKT MULTIPOINT.
+pub fn write_points<W>(writer: &mut W, points: &[Vertex]) -> io::Result<()>
+where
+    W: Write,
+{
+    if points.is_empty() {
+        return writeln!(writer, "MULTIPOINT EMPTY");
+    }
+    let points: Vec<String> = points
+        .iter()
+        .map(|p| format!("({} {})", p.x, p.y))
+        .collect();
+    writeln!(writer, "MULTIPOINT({})", points.join(", "))
+}
+
+/// Write edges as a WKT MULTILINESTRING.
+pub fn write_edges<W>(writer: &mut W, edges: &[Edge]) -> io::Result<()>
+where
+    W: Write,
+{
+    if edges.is_empty() {
+        return writeln!(writer, "MULTILINESTRING EMPTY");
+    }
+    let lines: Vec<String> = edges
+        .iter()
+        .map(|e| format!("({} {}, {} {})", e.u.x, e.u.y, e.v.x, e.v.y))
+        .collect();
+    writeln!(writer, "MULTILINESTRING({})", lines.join(", "))
+}
+
+/// Coordinates of a geometry, a position or a list
+enum Coordinates {
+    Position(Vertex),
+    List(Vec<Coordinates>),
+}
+
+impl Coordinates {
+    /// Get the position, of a position or a list of one position.
+    fn position(&self) -> Option<Vertex> {
+        match self {
+            Coordinates::Position(p) => Some(*p),
+            Coordinates::List(list) => match list.as_slice() {
+                [Coordinates::Position(p)] => Some(*p),
+                _ => None,
+            },
+        }
+    }
+
+    /// Get the positions of a list of positions.
+    fn line(&self) -> Option<Vec<Vertex>> {
+        match self {
+            Coordinates::List(list) => {
+                list.iter().map(|c| c.position()).collect()
+            }
+            Coordinates::Position(_) => None,
+        }
+    }
+
+    /// Get the members of a list.
+    fn members(&self) -> &[Coordinates] {
+        match self {
+            Coordinates::List(list) => list,
+            Coordinates::Position(_) => &[],
+        }
+    }
+}
+
+/// The points, lines and polygon outer rings of WKT geometries
+#[derive(Default)]
+struct Shapes {
+    points: Vec<Vertex>,
+    lines: Vec<Vec<Vertex>>,
+    rings: Vec<Vec<Vertex>>,
+}
+
+/// Deepest nesting of brackets and collections read
+const MAX_DEPTH: usize = 64;
+
+/// A WKT reader
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Brackets and collections open
+    depth: usize,
+}
+
+/// Make an invalid data error.
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid WKT")
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            depth: 0,
+        }
+    }
+
+    /// Go a level deeper, if that is not too deep.
+    fn descend(&mut self) -> io::Result<()> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Skip white space and geometry separators.
+    fn skip(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() || *c == ';' {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Look at the next character after white space.
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    /// Take the next character, if it is the one expected.
+    fn expect(&mut self, c: char) -> io::Result<()> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.chars.next();
+                Ok(())
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Read a word (tag, dimension or EMPTY) in upper case.
+    fn word(&mut self) -> String {
+        self.peek();
+        let mut word = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric()) {
+            word.push(c.to_ascii_uppercase());
+        }
+        word
+    }
+
+    /// Read a position, rounding the first two coordinates.
+    fn position(&mut self) -> io::Result<Vertex> {
+        let mut text = String::new();
+        while let Some(c) = self.chars.next_if(|&c| c != ',' && c != ')') {
+            text.push(c);
+        }
+        let xy: Vec<f64> = text
+            .split_whitespace()
+            .map(|s| s.parse().map_err(|_| invalid()))
+            .collect::<io::Result<_>>()?;
+        match xy.as_slice() {
+            [x, y, ..] => Ok(Vertex::new(x.round() as i32, y.round() as i32)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Read the coordinates of a geometry: a position or a list in
+    /// brackets.
+    fn coordinates(&mut self) -> io::Result<Coordinates> {
+        if self.peek() != Some('(') {
+            return self.position().map(Coordinates::Position);
+        }
+        self.chars.next();
+        self.descend()?;
+        let mut list = vec![self.coordinates()?];
+        while self.peek() == Some(',') {
+            self.chars.next();
+            list.push(self.coordinates()?);
+        }
+        self.expect(')')?;
+        self.depth -= 1;
+        Ok(Coordinates::List(list))
+    }
+
+    /// Read a geometry, adding its points and lines to the shapes.
+    fn geometry(&mut self, shapes: &mut Shapes) -> io::Result<()> {
+        let mut tag = self.word();
+        if tag == "SRID" {
+            self.expect('=')?;
+            while self.chars.next_if(|&c| c != ';').is_some() {}
+            self.expect(';')?;
+            tag = self.word();
+        }
+        if tag.is_empty() {
+            return Err(invalid());
+        }
+        let mut word = self.word();
+        if matches!(word.as_str(), "Z" | "M" | "ZM") {
+            word = self.word();
+        }
+        if word == "EMPTY" {
+            return Ok(());
+        } else if !word.is_empty() {
+            return Err(invalid());
+        }
+
+        if tag == "GEOMETRYCOLLECTION" {
+            self.expect('(')?;
+            self.descend()?;
+            self.geometry(shapes)?;
+            while self.peek() == Some(',') {
+                self.chars.next();
+                self.geometry(shapes)?;
+            }
+            self.expect(')')?;
+            self.depth -= 1;
+            return Ok(());
+        }
+
+        let coordinates = self.coordinates()?;
+        match tag.as_str() {
+            "POINT" => shapes
+                .points
+                .push(coordinates.position().ok_or_else(invalid)?),
+            "MULTIPOINT" => {
+                for c in coordinates.members() {
+                    shapes.points.push(c.position().ok_or_else(invalid)?);
+                }
+            }
+            "LINESTRING" => {
+                shapes.lines.push(coordinates.line().ok_or_else(invalid)?)
+            }
+            "MULTILINESTRING" => {
+                for c in coordinates.members() {
+                    shapes.lines.push(c.line().ok_or_else(invalid)?);
+                }
+            }
+            "POLYGON" => {
+                let ring = coordinates.members().first().ok_or_else(invalid)?;
+                shapes.rings.push(ring.line().ok_or_else(invalid)?);
+            }
+            "MULTIPOLYGON" => {
+                for c in coordinates.members() {
+                    let ring = c.members().first().ok_or_else(invalid)?;
+                    shapes.rings.push(ring.line().ok_or_else(invalid)?);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Read the points and lines of all the geometries in WKT text.
+fn read_shapes<R>(mut reader: R) -> io::Result<Shapes>
+where
+    R: Read,
+{
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut parser = Parser::new(&text);
+    let mut shapes = Shapes::default();
+    parser.skip();
+    while parser.chars.peek().is_some() {
+        parser.geometry(&mut shapes)?;
+        parser.skip();
+    }
+    Ok(shapes)
+}
+
+/// Read points from the POINTs and MULTIPOINTs of WKT text.
+pub fn read_points<R>(reader: R) -> io::Result<Vec<Vertex>>
+where
+    R: Read,
+{
+    read_shapes(reader).map(|shapes| shapes.points)
+}
+
+/// Read obstacles from the outer rings of the POLYGONs and MULTIPOLYGONs of
+/// WKT text, without the closing position.  Rings of fewer than three
+/// points are skipped.
+pub fn read_polygons<R>(reader: R) -> io::Result<Vec<Polygon>>
+where
+    R: Read,
+{
+    let mut polygons = Vec::new();
+    for ring in read_shapes(reader)?.rings {
+        if let Ok(polygon) = Polygon::from_ring(ring) {
+            polygons.push(polygon);
+        }
+    }
+    Ok(polygons)
+}
+
+/// Read the end points of every segment of the LINESTRINGs and
+/// MULTILINESTRINGs of WKT text, copies of a point included.
+pub fn read_pairs<R>(reader: R) -> io::Result<Vec<(Vertex, Vertex)>>
+where
+    R: Read,
+{
+    let mut pairs = Vec::new();
+    for line in read_shapes(reader)?.lines {
+        pairs.extend(line.windows(2).map(|pair| (pair[0], pair[1])));
+    }
+    Ok(pairs)
+}
+
+/// Read edges from the LINESTRINGs and MULTILINESTRINGs of WKT text.
+/// Segments between copies of a point are skipped.
+pub fn read_edges<R>(reader: R) -> io::Result<Vec<Edge>>
+where
+    R: Read,
+{
+    read_pairs(reader).map(pair_edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let points = vec![Vertex::new(1, 2), Vertex::new(-4, 6)];
+        let mut buffer = Vec::new();
+        write_points(&mut buffer, &points).unwrap();
+        assert_eq!(buffer, b"MULTIPOINT((1 2), (-4 6))\n");
+        let again = read_points(buffer.as_slice()).unwrap();
+        assert_eq!((again[1].x, again[1].y), (-4, 6));
+
+        let edges = vec![Edge::new(0, 0, 3, 4), Edge::new(3, 4, 3, 0)];
+        let mut buffer = Vec::new();
+        write_edges(&mut buffer, &edges).unwrap();
+        assert_eq!(buffer, b"MULTILINESTRING((0 0, 3 4), (3 4, 3 0))\n");
+        let again = read_edges(buffer.as_slice()).unwrap();
+        let lengths: Vec<f32> = again.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [5.0, 4.0]);
+
+        let mut buffer = Vec::new();
+        write_points(&mut buffer, &[]).unwrap();
+        assert!(read_points(buffer.as_slice()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_postgis() {
+        let text = "POINT(1.4 2.6)\n\
+                    SRID=4326;point z (3 4 5)\n\
+                    MULTIPOINT(5 6, 7 8)\n\
+                    POLYGON((0 0, 1 0, 1 1, 0 0))\n\
+                    GEOMETRYCOLLECTION(POINT(9 9),\n\
+                    LINESTRING(0 0, 3 4, 3 4, 3 0))\n\
+                    LINESTRING EMPTY";
+        let points = read_points(text.as_bytes()).unwrap();
+        let xy: Vec<(i32, i32)> = points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(xy, [(1, 3), (3, 4), (5, 6), (7, 8), (9, 9)]);
+        let edges = read_edges(text.as_bytes()).unwrap();
+        let lengths: Vec<f32> = edges.iter().map(|e| e.len()).collect();
+        assert_eq!(lengths, [5.0, 4.0]);
+
+        assert!(read_points("POINT(1)".as_bytes()).is_err());
+        assert!(read_points("POINT(1 2".as_bytes()).is_err());
+        assert!(read_points("(1 2)".as_bytes()).is_err());
+        assert!(read_edges("LINESTRING(a b, 1 2)".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_read_deep() {
+        // Too deep to read, rather than too deep for the stack
+        let deep = "POINT".to_string() + &"(".repeat(100_000);
+        assert!(read_points(deep.as_bytes()).is_err());
+        let deep = "GEOMETRYCOLLECTION(".repeat(100_000);
+        assert!(read_points(deep.as_bytes()).is_err());
+        let nested =
+            "GEOMETRYCOLLECTION(".repeat(10) + "POINT(1 2)" + &")".repeat(10);
+        assert_eq!(read_points(nested.as_bytes()).unwrap().len(), 1);
+    }
+}